@@ -7,6 +7,15 @@ fn main() {
         .with_crate(crate_dir)
         .with_language(cbindgen::Language::C)
         .include_item("Response")
+        .include_item("ResponseStatus")
+        .with_define("feature", "checksum", "PQ_MESSAGE_LIB_CHECKSUM")
+        .with_define("feature", "auth", "PQ_MESSAGE_LIB_AUTH")
+        .with_define("feature", "compress", "PQ_MESSAGE_LIB_COMPRESS")
+        .with_define("feature", "uuid", "PQ_MESSAGE_LIB_UUID")
+        // C has no standard 128-bit integer, see `Identifier`
+        .with_after_include(
+            "\n#if defined(PQ_MESSAGE_LIB_UUID)\ntypedef unsigned __int128 u128;\n#endif",
+        )
         .generate()
         .expect("Unable to generate bindings")
         .write_to_file("pq_message_lib.h");
@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // Describing any input must not panic
+    let _ = pq_message_lib::inspect::describe_request(data);
+    let _ = pq_message_lib::inspect::describe_response(data);
+});
@@ -0,0 +1,42 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use pq_message_lib::request::{
+    deserialize_request, deserialize_request_header, get_serialized_request_header_size,
+    serialize_request_header_vec, RequestHeader,
+};
+
+fuzz_target!(|input: (RequestHeader, Vec<u8>)| {
+    let (header, body) = input;
+
+    // A header that can be serialized has to survive a round trip together with its body
+    if let Ok(mut message) = serialize_request_header_vec(
+        header.identifier,
+        header.data_len,
+        header.algorithm,
+        header.operation,
+        header.deadline_ms,
+    ) {
+        message.extend_from_slice(&body);
+
+        let deserialized = deserialize_request_header(&message).unwrap();
+        assert_eq!(deserialized.identifier, header.identifier);
+        assert_eq!(deserialized.data_len, header.data_len);
+        assert_eq!(deserialized.algorithm, header.algorithm);
+        assert_eq!(deserialized.deadline_ms, header.deadline_ms);
+    }
+
+    // The body does not need to match the data_len of the header
+    let header_size = get_serialized_request_header_size() as usize;
+    let message: Vec<u8> = body;
+    if let Ok(header) = deserialize_request_header(&message) {
+        let body = message
+            .get(header_size..)
+            .and_then(|body| body.get(..header.data_len as usize))
+            .map(<[u8]>::to_vec);
+        if let Some(body) = body {
+            let request = deserialize_request(header, body);
+            assert_eq!(request.body.len(), request.header.data_len as usize);
+        }
+    }
+});
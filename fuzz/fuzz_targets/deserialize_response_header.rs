@@ -0,0 +1,23 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use pq_message_lib::response::{get_serialized_response_header_size, ResponseHeader};
+
+// The C function reads a full header from the pointer it is given, which is only sound when the
+// buffer is at least that large.
+fn deserialize_response_header(data: &[u8]) -> Option<(i16, ResponseHeader)> {
+    if data.len() < get_serialized_response_header_size() as usize {
+        return None;
+    }
+
+    let mut response_header = ResponseHeader::default();
+    let status = unsafe {
+        pq_message_lib::response::deserialize_response_header(data.as_ptr(), &mut response_header)
+    };
+
+    Some((status, response_header))
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = deserialize_response_header(data);
+});
@@ -0,0 +1,22 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: (u16, u8, &[u8])| {
+    let (empty_entries, max_entries, data) = input;
+    let max_entries = max_entries as usize;
+
+    // A run of empty entries in front of whatever else the input holds, so that buffers claiming far
+    // more entries than the cap are common
+    let mut buffer = vec![0; empty_entries as usize * pq_message_lib::LENGTH_PREFIX_SIZE];
+    buffer.extend_from_slice(data);
+
+    let count = pq_message_lib::request::EntryIter::new(&buffer).count();
+    match pq_message_lib::request::destructure_entries_bounded(&buffer, max_entries) {
+        Ok(entries) => assert!(entries.len() <= max_entries),
+        Err(pq_message_lib::MessageError::TooManyEntries { max }) => {
+            assert_eq!(max, max_entries);
+            assert!(count > max_entries);
+        }
+        Err(_) => (),
+    }
+});
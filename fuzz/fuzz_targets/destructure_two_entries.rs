@@ -0,0 +1,30 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let rust = pq_message_lib::request::destructure_two_entries(data);
+
+    let mut entry1_length = 0;
+    let mut entry2_length = 0;
+    let mut entry1 = std::ptr::null();
+    let mut entry2 = std::ptr::null();
+    let status = unsafe {
+        pq_message_lib::response::destructure_two_entries(
+            data.as_ptr(),
+            data.len(),
+            &mut entry1_length,
+            &mut entry2_length,
+            &mut entry1,
+            &mut entry2,
+        )
+    };
+
+    // Both sides have to agree on what a valid buffer is
+    assert_eq!(rust.is_ok(), status == 0);
+    if let Ok((rust_entry1, rust_entry2)) = rust {
+        assert_eq!(rust_entry1.as_ptr(), entry1);
+        assert_eq!(rust_entry1.len(), entry1_length);
+        assert_eq!(rust_entry2.as_ptr(), entry2);
+        assert_eq!(rust_entry2.len(), entry2_length);
+    }
+});
@@ -0,0 +1,44 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use pq_message_lib::response::{
+    parse_response, serialize_failure_response, serialize_response, serialize_response_with_error,
+    ResponseHeader, ResponseStatus, ResponseView,
+};
+
+fuzz_target!(|input: (ResponseHeader, Vec<u8>)| {
+    let (header, body) = input;
+
+    // The serializers derive data_len and the flags from what they are given, so whatever the header
+    // holds the response they produce has to survive a round trip
+    let response = match header.status() {
+        Some(ResponseStatus::Ok) => serialize_response(header.identifier, &body),
+        Some(status) if header.is_diagnostic() => serialize_response_with_error(
+            header.identifier,
+            status,
+            &String::from_utf8_lossy(&body),
+        ),
+        Some(status) => serialize_failure_response(header.identifier, status),
+        None => return,
+    };
+    if let Ok(response) = response {
+        let view = ResponseView::from_bytes(&response).unwrap();
+        assert_eq!(view.header().identifier, header.identifier);
+        assert_eq!(view.header().status(), header.status());
+        match header.status() {
+            // Without the compress feature the data is sent as it is
+            Some(ResponseStatus::Ok) => assert_eq!(view.body(), &body[..]),
+            _ if header.is_diagnostic() => {
+                assert_eq!(view.error_message(), Some(&*String::from_utf8_lossy(&body)));
+            }
+            _ => assert!(view.body().is_empty()),
+        }
+    }
+
+    // A header as it is, which need not hold up the invariants, is only accepted when it does
+    let mut message = header.to_bytes();
+    message.extend_from_slice(&body);
+    if header.validate().is_err() {
+        assert!(parse_response(&message).is_err());
+    }
+});
@@ -0,0 +1,22 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|lengths: (usize, usize)| {
+    let (entry1_length, entry2_length) = lengths;
+    let total_length =
+        pq_message_lib::request::structure_two_entries_length(entry1_length, entry2_length);
+
+    // Either the overflow is reported or the length is large enough to hold both entries
+    let expected = entry1_length
+        .checked_add(entry2_length)
+        .and_then(|length| length.checked_add(2 * pq_message_lib::LENGTH_PREFIX_SIZE))
+        .filter(|&length| length <= i64::MAX as usize);
+    match expected {
+        Some(expected) => {
+            assert_eq!(total_length as usize, expected);
+            assert!(total_length as usize >= entry1_length);
+            assert!(total_length as usize >= entry2_length);
+        }
+        None => assert_eq!(total_length, -1),
+    }
+});
@@ -3,95 +3,560 @@
 #include <stdint.h>
 #include <stdlib.h>
 
+#if defined(PQ_MESSAGE_LIB_UUID)
+typedef unsigned __int128 u128;
+#endif
+
+/**
+ * Number of `Algorithm` variants, `NoAlgorithm` included. The discriminants are `0..ALGORITHM_COUNT`.
+ */
+#define ALGORITHM_COUNT 38
+
+#if defined(PQ_MESSAGE_LIB_AUTH)
+/**
+ * Number of bytes of the HMAC-SHA256 tag that follows an authenticated header.
+ */
+#define AUTH_TAG_SIZE 32
+#endif
+
+/**
+ * The most entries `destructure_entries` returns, so that a buffer of many empty entries cannot make it
+ * allocate an arbitrary amount of memory. Far more than any operation uses; pass another cap to
+ * `destructure_entries_bounded` when needed.
+ */
+#define DEFAULT_MAX_ENTRIES 64
+
+#if !defined(PQ_MESSAGE_LIB_UUID)
+/**
+ * Size of the identifier in either header: 8 bytes, or 16 with the `uuid` feature.
+ */
+#define IDENTIFIER_SIZE 8
+#endif
+
+#if defined(PQ_MESSAGE_LIB_UUID)
+/**
+ * Size of the identifier in either header: 8 bytes, or 16 with the `uuid` feature.
+ */
+#define IDENTIFIER_SIZE 16
+#endif
+
+/**
+ * Size in bytes of the length prefix in front of every structured entry. The prefix is a little-endian `u64`,
+ * independent of the size of `usize`, so that peers built for different pointer widths agree on the format.
+ */
+#define LENGTH_PREFIX_SIZE 8
+
+/**
+ * Number of `Operation` variants, `NoOperation` included. The discriminants are `0..OPERATION_COUNT`.
+ */
+#define OPERATION_COUNT 8
+
+/**
+ * Offset of the algorithm in a request header, the `u32` discriminant of the `Algorithm`.
+ */
+#define REQUEST_ALGORITHM_OFFSET (REQUEST_DATA_LEN_OFFSET + 4)
+
+/**
+ * Offset of `data_len` in a request header, a `u32`.
+ */
+#define REQUEST_DATA_LEN_OFFSET (REQUEST_IDENTIFIER_OFFSET + IDENTIFIER_SIZE)
+
+/**
+ * Offset of `deadline_ms` in a request header, a `u64`.
+ */
+#define REQUEST_DEADLINE_MS_OFFSET (REQUEST_OPERATION_OFFSET + 4)
+
+/**
+ * Offset of the flags in a request header, a `u8`.
+ */
+#define REQUEST_FLAGS_OFFSET (REQUEST_DEADLINE_MS_OFFSET + 8)
+
+/**
+ * Size of a request header, i.e. `get_serialized_request_header_size()`.
+ */
+#define REQUEST_HEADER_SIZE (REQUEST_FLAGS_OFFSET + 1)
+
+/**
+ * Offset of the identifier in a request header, see `IDENTIFIER_SIZE`.
+ */
+#define REQUEST_IDENTIFIER_OFFSET (REQUEST_VERSION_OFFSET + 1)
+
+/**
+ * Offset of the operation in a request header, the `u32` discriminant of the `Operation`.
+ */
+#define REQUEST_OPERATION_OFFSET (REQUEST_ALGORITHM_OFFSET + 4)
+
+/**
+ * Offset of the format version in a request header, a `u8`.
+ */
+#define REQUEST_VERSION_OFFSET 0
+
+/**
+ * Offset of `data_len` in a response header, a `u32`.
+ */
+#define RESPONSE_DATA_LEN_OFFSET (RESPONSE_SUCCESS_OFFSET + 1)
+
+/**
+ * Offset of the flags in a response header, a `u8`.
+ */
+#define RESPONSE_FLAGS_OFFSET (RESPONSE_DATA_LEN_OFFSET + 4)
+
+/**
+ * Bit of `ResponseHeader::flags` that marks the data as compressed, see `decompress_response_body`.
+ */
+#define RESPONSE_FLAG_COMPRESSED 1
+
+/**
+ * Bit of `ResponseHeader::flags` that marks the data of a failure as a UTF-8 diagnostic message instead of
+ * result data, see `serialize_response_with_error`.
+ */
+#define RESPONSE_FLAG_DIAGNOSTIC 2
+
+/**
+ * Size of a response header, i.e. `get_serialized_response_header_size()`.
+ */
+#define RESPONSE_HEADER_SIZE (RESPONSE_FLAGS_OFFSET + 1)
+
+/**
+ * Offset of the identifier in a response header, see `IDENTIFIER_SIZE`.
+ */
+#define RESPONSE_IDENTIFIER_OFFSET (RESPONSE_VERSION_OFFSET + 1)
+
+/**
+ * Offset of the success status in a response header, an `i8`.
+ */
+#define RESPONSE_SUCCESS_OFFSET (RESPONSE_IDENTIFIER_OFFSET + IDENTIFIER_SIZE)
+
+/**
+ * Offset of the format version in a response header, a `u8`.
+ */
+#define RESPONSE_VERSION_OFFSET 0
+
+/**
+ * Set in the format version of headers with 128-bit identifiers, i.e. those written with the `uuid`
+ * feature. Those headers have another layout, so a peer with the other identifier width rejects them
+ * as a version mismatch instead of misreading them. The versions announced in the response to a `Hello`
+ * carry it as well.
+ */
+#define UUID_FORMAT_VERSION_FLAG 128
+
 /**
  * All possible algorithms that can be requested.
+ * The discriminants are sent on the wire, so a variant is never renumbered and a new one takes the next free
+ * number (`ALGORITHM_COUNT`), wherever it is listed. Algorithms are ordered by their discriminant, so the
+ * order is as stable as the discriminants themselves and does not depend on where a variant is listed.
  */
 typedef enum {
-  NoAlgorithm,
-  FRODO640__ECDHp256,
-  FRODO640,
-  FRODO976__ECDHp384,
-  FRODO976,
-  FRODO1344__ECDHp521,
-  FRODO1344,
-  NTRU_HRSS_701,
-  NTRU_HRSS_701__ECDHp256,
-  NTRU_HPS_2048509,
-  NTRU_HPS_2048509__ECDHp256,
-  RND5_1CCA_5D,
-  RND5_1CCA_5D__ECDHp256,
-  RND5_3CCA_5D,
-  RND5_3CCA_5D__ECDHp384,
-  RND5_5CCA_5D,
-  RND5_5CCA_5D__ECDHp521,
-  KYBER_512,
-  KYBER_512__ECDHp256,
-  KYBER_768,
-  KYBER_768__ECDHp384,
-  KYBER_1024,
-  KYBER_1024__ECDHp521,
-  SABER_LIGHT,
-  SABER_LIGHT__ECDHp256,
-  SABER,
-  SABER__ECDHp384,
-  SABER_FIRE,
-  SABER_FIRE__ECDHp521,
+  /**
+   * Placeholder for an unset algorithm, never a real one. Discriminant 0 is permanently reserved for it:
+   * a zeroed header, a default `RequestHeader` and validation all rely on 0 meaning "unset".
+   */
+  NoAlgorithm = 0,
+  FRODO640__ECDHp256 = 1,
+  FRODO640 = 2,
+  FRODO976__ECDHp384 = 3,
+  FRODO976 = 4,
+  FRODO1344__ECDHp521 = 5,
+  FRODO1344 = 6,
+  NTRU_HRSS_701 = 7,
+  NTRU_HRSS_701__ECDHp256 = 8,
+  NTRU_HPS_2048509 = 9,
+  NTRU_HPS_2048509__ECDHp256 = 10,
+  RND5_1CCA_5D = 11,
+  RND5_1CCA_5D__ECDHp256 = 12,
+  RND5_3CCA_5D = 13,
+  RND5_3CCA_5D__ECDHp384 = 14,
+  RND5_5CCA_5D = 15,
+  RND5_5CCA_5D__ECDHp521 = 16,
+  KYBER_512 = 17,
+  KYBER_512__ECDHp256 = 18,
+  KYBER_768 = 19,
+  KYBER_768__ECDHp384 = 20,
+  KYBER_1024 = 21,
+  KYBER_1024__ECDHp521 = 22,
+  SABER_LIGHT = 23,
+  SABER_LIGHT__ECDHp256 = 24,
+  SABER = 25,
+  SABER__ECDHp384 = 26,
+  SABER_FIRE = 27,
+  SABER_FIRE__ECDHp521 = 28,
+  /**
+   * ML-KEM-512 as standardized in FIPS 203. Its keys and ciphertexts have the same sizes as those of
+   * `KYBER_512`, the round 3 submission it is based on, but the two do not interoperate.
+   */
+  ML_KEM_512 = 29,
+  ML_KEM_512__ECDHp256 = 30,
+  /**
+   * ML-KEM-768, see `ML_KEM_512`. Sized like `KYBER_768`.
+   */
+  ML_KEM_768 = 31,
+  ML_KEM_768__ECDHp384 = 32,
+  /**
+   * ML-KEM-1024, see `ML_KEM_512`. Sized like `KYBER_1024`.
+   */
+  ML_KEM_1024 = 33,
+  ML_KEM_1024__ECDHp521 = 34,
+  /**
+   * ML-DSA-44 as standardized in FIPS 204. Unlike every algorithm above it is a signature scheme, see
+   * `Algorithm::supports_signatures`.
+   */
+  ML_DSA_44 = 35,
+  /**
+   * ML-DSA-65, see `ML_DSA_44`.
+   */
+  ML_DSA_65 = 36,
+  /**
+   * ML-DSA-87, see `ML_DSA_44`.
+   */
+  ML_DSA_87 = 37,
 } Algorithm;
 
 /**
  * All possible operations that can be requested.
+ * The discriminants are sent on the wire just like those of `Algorithm`, so the same rules apply, including
+ * the ordering by discriminant.
  */
 typedef enum {
-  NoOperation,
-  KeypairGeneration,
-  Encapsulation,
-  Decapsulation,
+  /**
+   * Placeholder for an unset operation, never a real one. Discriminant 0 is permanently reserved for it,
+   * as for `Algorithm::NoAlgorithm`.
+   */
+  NoOperation = 0,
+  KeypairGeneration = 1,
+  Encapsulation = 2,
+  Decapsulation = 3,
+  /**
+   * Signs the message in the request body.
+   */
+  Sign = 4,
+  /**
+   * Verifies the signature in the request body. A processing error is reported through a
+   * non-zero `ResponseHeader::success` and an empty body. When the verification could be performed
+   * `success` is 0 and the body is a single byte: 1 when the signature is valid and 0 when it is not.
+   */
+  Verify = 5,
+  /**
+   * Asks the responder which format versions it supports, so that a requester can fall back to an older
+   * version. The request has no algorithm and an empty body and is accepted by `deserialize_request_header`
+   * whatever its version. The body of the response lists the supported versions, one byte each.
+   * See `build_hello_request`, `response::build_hello_response` and `parse_hello_response`.
+   */
+  Hello = 6,
+  /**
+   * Asks the responder to abort the request it received earlier with the same identifier, e.g. because the
+   * requester went away during a long key generation. Unlike every other operation the identifier does not
+   * name a new request but the target of the cancellation. The request has no algorithm and an empty body
+   * and is not answered on its own: the responder answers the target instead, with
+   * `response::ResponseStatus::Cancelled` when it stopped the work in time. See `build_cancel_request`.
+   */
+  Cancel = 7,
 } Operation;
 
+/**
+ * Outcome of a request, stored in the `success` byte of `ResponseHeader`.
+ */
+enum ResponseStatus {
+  Ok = 0,
+  /**
+   * The operation itself failed, e.g. a decapsulation was rejected.
+   */
+  OperationFailed = -1,
+  /**
+   * The algorithm is not built into the responder.
+   */
+  UnsupportedAlgorithm = -2,
+  /**
+   * The data of the request does not fit the algorithm and operation, e.g. a key of the wrong size.
+   */
+  InvalidInput = -3,
+  /**
+   * Something went wrong in the responder that has nothing to do with the request.
+   */
+  InternalError = -4,
+  /**
+   * The operation is not built into the responder, e.g. because this version does not know it yet.
+   */
+  UnsupportedOperation = -5,
+  /**
+   * The request was aborted by a `Cancel` before it was done, see `request::Operation::Cancel`.
+   */
+  Cancelled = -6,
+};
+typedef int8_t ResponseStatus;
+
+/**
+ * Sizes in bytes of the key material belonging to an `Algorithm`.
+ * For hybrid algorithms (e.g. `FRODO976__ECDHp384`) every size is the combined size of the post-quantum
+ * part followed by the ECDH part. For the ECDH part the public key and ciphertext are uncompressed points,
+ * the secret key is the scalar and the shared secret is the x-coordinate of the shared point. Signature
+ * schemes have no ciphertext and shared secret, key encapsulation mechanisms no signature, so those sizes
+ * are 0.
+ */
+typedef struct {
+  size_t public_key_len;
+  size_t secret_key_len;
+  size_t ciphertext_len;
+  size_t shared_secret_len;
+  size_t signature_len;
+} AlgorithmSizes;
+
+#if !defined(PQ_MESSAGE_LIB_UUID)
+/**
+ * The identifier of a request, which its response carries as well. With the `uuid` feature this is 128
+ * bits wide so that e.g. a `uuid::Uuid` fits (see `request::RequestHeaderBuilder::uuid`). Both sides of
+ * the IPC channel must agree on whether this feature is used.
+ */
+typedef uint64_t Identifier;
+#endif
+
+#if defined(PQ_MESSAGE_LIB_UUID)
+/**
+ * The identifier of a request, which its response carries as well. 128 bits wide as the `uuid` feature
+ * is used.
+ */
+typedef u128 Identifier;
+#endif
+
 /**
  * Header that describes the response sent.
  * # Explanation of the header
  * - version is used for compatibility reasons. Typically there is no need to do anything with this
- *   as pq_message_lib deals with version internally.
+ *   as pq_message_lib deals with version internally. From Rust it can only be read through
+ *   `version()`, as both `ResponseHeader::new` and `Default` stamp `FORMAT_VERSION`.
  * - identifier is used so that the receiver of the `ResponseHeader` can link it back to the original request.
- * - success indicates there was a failure or not. 0 means success while anything else is a failure.
- *   Note that the data_len field will always be 0 when there was a failure.
+ * - success indicates there was a failure or not. 0 means success while anything else is a failure, the
+ *   known codes are those of `ResponseStatus` (see `status`). Note that the data_len field will always be 0
+ *   when there was a failure, unless `RESPONSE_FLAG_DIAGNOSTIC` is set.
  * - data_len describes the length of the upcoming data that belongs to this `ResponseHeader`. The data after that
- *   will belong to a new `ResponseHeader`.
+ *   will belong to a new `ResponseHeader`. As this is a `u32` a single response can carry at most 2^32 - 1 bytes.
+ *   For compressed data this is the length of the data as it is sent.
+ * - flags describes how the data is sent, see `ResponseFlags`. Only `RESPONSE_FLAG_COMPRESSED` and
+ *   `RESPONSE_FLAG_DIAGNOSTIC` are defined, the other bits are reserved for features that need a bit in
+ *   the header: this version sends 0 and keeps whatever bits it reads.
  */
 typedef struct {
   uint8_t version;
-  uint64_t identifier;
+  Identifier identifier;
   int8_t success;
   uint32_t data_len;
+  uint8_t flags;
 } ResponseHeader;
 
+/**
+ * Header that describes the request sent.
+ * # Explanation of the header
+ * - version is used for compatibility reasons. Typically there is no need to do anything with this
+ *   as pq_message_lib deals with version internally. From Rust it can only be read through
+ *   `version()`, as both `RequestHeader::builder` and `Default` stamp `FORMAT_VERSION`.
+ * - identifier is used so that the receiver of the `RequestHeader` can link it back to the original request.
+ * - data_len describes the length of the upcoming data that belongs to this `RequestHeader`. The data after that
+ *   will belong to a new `RequestHeader`. As this is a `u32` a single request can carry at most 2^32 - 1 bytes;
+ *   callers are responsible for not truncating larger lengths when filling in this field.
+ * - algorithm is the `Algorithm` that the request is about.
+ * - operation is the `Operation` that the request is about.
+ * - deadline_ms is the moment, in milliseconds since the Unix epoch, after which the requester is no longer
+ *   interested in the response so the responder can skip the work (see `is_expired`). 0 means there is no deadline.
+ * - flags is reserved for features that need a bit in the header, so that adding them does not change the layout.
+ *   No bits are defined yet: this version sends 0 and keeps whatever bits it reads.
+ */
+typedef struct {
+  uint8_t version;
+  Identifier identifier;
+  uint32_t data_len;
+  Algorithm algorithm;
+  Operation operation;
+  uint64_t deadline_ms;
+  uint8_t flags;
+} RequestHeader;
+
 /**
  * Convenience struct to allow response body to be stored together together with the header.
+ * # Ownership
+ * A `Response` created with `Response::new` owns its body, which was allocated by Rust. Whoever ends up
+ * with the `Response`, on either side of the FFI boundary, has to release the body exactly once with
+ * `free_response_body(body, header.data_len)` and must never pass it to the C `free`. A `Response` created
+ * with `response_from_parts` only borrows its body, which stays with whoever allocated it.
  */
 typedef struct {
   ResponseHeader header;
   const unsigned char *body;
 } Response;
 
+/**
+ * Sets `sizes` to the sizes of the key material of `algorithm`.
+ * # Returns
+ * 0 on success.
+ * -1 when sizes was a null pointer.
+ * -2 when algorithm was `NoAlgorithm`.
+ * # Safety
+ * `sizes` must point to a valid `AlgorithmSizes`.
+ */
+int16_t algorithm_sizes(Algorithm algorithm, AlgorithmSizes *sizes);
+
+/**
+ * Writes a whole request into `out`: the header, `body` and with the `checksum` feature the trailer, so the
+ * result can be sent as it is. These are the same bytes as `serialize_request_header` followed by `body`
+ * (and `write_checksum`), without a deadline.
+ * # Returns
+ * The number of bytes written to `out` on success.
+ * -1 when `out` is a null pointer, or `body` is a null pointer while `body_len` is not 0.
+ * -2 and -3 just like `serialize_request_header`.
+ * -4 when `out_len` is too small to hold the request. Nothing is written in that case.
+ * -5 when `body_len` is too long to be described by the `data_len` of the header.
+ * # Safety
+ * `body` must point to at least `body_len` bytes (unless `body_len` is 0) and `out` must point to at least
+ * `out_len` writable bytes.
+ */
+int64_t build_request(unsigned char *out,
+                      size_t out_len,
+                      Identifier identifier,
+                      Algorithm algorithm,
+                      Operation operation,
+                      const unsigned char *body,
+                      size_t body_len);
+
+/**
+ * Given a pointer of a buffer which contains any number of data fields returns how many there are, without
+ * copying anything, so that the arrays for `destructure_n_entries` can be allocated with exactly that
+ * capacity. An empty buffer contains 0 entries, which `destructure_n_entries` rejects.
+ * # Returns
+ * The number of entries on success.
+ * -1 when data pointer was null.
+ * -2 if parsing one of the lengths is unsuccessful, e.g. when a length does not fit in a `size_t`
+ * -3 if the provided data would cause an out of bounds access
+ * # Safety
+ * `data` must point to at least `data_size` readable bytes.
+ */
+int64_t count_entries(const unsigned char *data,
+                      size_t data_size);
+
+/**
+ * Same as `ct_eq`, for `a_len` bytes at `a` and `b_len` bytes at `b`.
+ * # Returns
+ * 1 when the buffers are equal.
+ * 0 when they are not.
+ * -1 when a or b was a null pointer.
+ * # Safety
+ * `a` must point to at least `a_len` readable bytes and `b` to at least `b_len` readable bytes.
+ */
+int16_t ct_eq_c(const unsigned char *a, size_t a_len, const unsigned char *b, size_t b_len);
+
+#if defined(PQ_MESSAGE_LIB_COMPRESS)
+/**
+ * C version of `decompress_response_body` which writes the data into `out_buffer`, see
+ * `get_decompressed_response_body_size` for how large it has to be.
+ * # Returns
+ * The number of bytes written to `out_buffer` on success.
+ * -1 when header, body or out_buffer was a null pointer.
+ * -2 when out_buffer_len is too small to hold the data.
+ * -3 when the compressed data is corrupt.
+ * # Safety
+ * `header` must point to a valid `ResponseHeader`, `body` to at least `data_len` bytes and `out_buffer` to at
+ * least `out_buffer_len` writable bytes.
+ */
+int64_t decompress_response_body_c(const ResponseHeader *header,
+                                   const unsigned char *body,
+                                   unsigned char *out_buffer,
+                                   size_t out_buffer_len);
+#endif
+
+/**
+ * Given a pointer to `data_len` bytes will return the `RequestHeader` at their start, the counterpart of
+ * `deserialize_response_header` for a responder written in C. This header can be used to determine how many
+ * bytes of data are coming up. A `Hello` is accepted whatever its version, just like with
+ * `deserialize_request_header`.
+ * # Returns
+ * 0 on success.
+ * -1 when a null pointer was passed in.
+ * -2 when `data_len` is too short to hold the header (or with the `checksum` feature the whole message).
+ * -3 for deserialization failure, e.g. an unknown algorithm or operation.
+ * -4 for a version in the header that this crate does not accept. `request_header` is still set so that
+ * the version of the other side can be read.
+ * -5 for mismatch of the checksum trailer (only with the `checksum` feature).
+ * # Safety
+ * `data` must point to at least `data_len` readable bytes and `request_header` to a writable `RequestHeader`.
+ * With the `checksum` feature `data` must hold the complete message, that is the header followed by its data
+ * and the checksum trailer.
+ */
+int16_t deserialize_request_header_c(const unsigned char *data,
+                                     size_t data_len,
+                                     RequestHeader *request_header);
+
 /**
  * Given a pointer will return a `ResponseHeader`. This header can be used to determine how many bytes
  * of data are coming up.
  * # Returns
  * 0 on success.
  * -1 when a null pointer was passed in.
- * -2 for when the header will not fit in memory due to architecture.
+ * -2 for when the header (or with the `checksum` feature the whole message) will not fit in memory due to
+ * architecture.
  * -3 for deserialization failure.
- * -4 for mismatch of version in header.
+ * -4 for a version in the header that this crate does not accept. `response_header` is still set so that
+ * the response to a `Hello` can be read.
+ * -5 for mismatch of the checksum trailer (only with the `checksum` feature).
+ * -6 for a success marked as diagnostic (see `ResponseHeader::validate`). `response_header` is still set.
+ * -7 for a failure that carries data other than a diagnostic message, a protocol violation of the responder
+ * (see `ResponseHeader::validate`). `response_header` is still set.
  * # Safety
  * Unsafe because there is no absolute guarantee we don't get a pointer handed somewhere
  * in program space that happens to deserialize succesfully to a ResponseHeader.
  * When used in combination with `get_serialized_response_header_size` this function
  * will be able to safely and correctly deserialize a response header.
+ * With the `checksum` feature `response_data` must point to the complete message, that is the header
+ * followed by `data_len` bytes of data and the checksum trailer.
  */
 int16_t deserialize_response_header(const unsigned char *response_data,
                                     ResponseHeader *response_header);
 
+/**
+ * Given a pointer of a buffer which contains any number of data fields it will set the first `capacity`
+ * elements of `entries` to those locations within the buffer, in the order they were structured. Additionally
+ * it will set the lengths in `entry_lengths` and the number of entries found in `entry_count`.
+ * # Returns
+ * 0 on success.
+ * -1 when data pointer was null.
+ * -2 when entry_lengths pointer was null.
+ * -3 when entries pointer was null.
+ * -4 when entry_count pointer was null.
+ * -5 when data does not contain any entries.
+ * -6 when data contains more than `capacity` entries.
+ * -7 if parsing one of the lengths is unsuccessful, e.g. when a length does not fit in a `size_t`
+ * -8 if the provided data would cause an out of bounds access
+ * # Safety
+ * `entry_lengths` and `entries` must both point to at least `capacity` elements. Just like
+ * `destructure_two_entries` there is no way for this function to realize that `data_size` is invalid.
+ * The entries point into `data`, which remains owned by the caller, so they must not be freed.
+ */
+int16_t destructure_n_entries(const unsigned char *data,
+                              size_t data_size,
+                              size_t capacity,
+                              size_t *entry_lengths,
+                              const unsigned char **entries,
+                              size_t *entry_count);
+
+/**
+ * Given a pointer of a buffer which contains three data fields it will set entry1, entry2 and entry3 pointers to
+ * those locations within the buffer. Additionally it will set the lengths appropriately.
+ * # Returns
+ * 0 on success.
+ * -1 when data pointer was null.
+ * -2, -3 or -4 when the entry1_length, entry2_length or entry3_length pointer was null.
+ * -5, -6 or -7 when the entry1, entry2 or entry3 pointer was null.
+ * -8, -9 or -10 if parsing the length of entry1, entry2 or entry3 is unsuccessful, e.g. when it does not fit
+ * in a `size_t`.
+ * -11, -12 or -13 if entry1, entry2 or entry3 would cause an out of bounds access.
+ * # Safety
+ * Same as `destructure_two_entries`.
+ */
+int16_t destructure_three_entries(const unsigned char *data,
+                                  size_t data_size,
+                                  size_t *entry1_length,
+                                  size_t *entry2_length,
+                                  size_t *entry3_length,
+                                  const unsigned char **entry1,
+                                  const unsigned char **entry2,
+                                  const unsigned char **entry3);
+
 /**
  * Given a pointer of a buffer which contains two data fields it will set entry1 and entry2 pointers to those locations
  * within the buffer. Additionally it will set the length appropriately.
@@ -102,7 +567,7 @@ int16_t deserialize_response_header(const unsigned char *response_data,
  * -3 when entry2_length pointer was null.
  * -4 when entry1 pointer was null.
  * -5 when entry2 pointer was null.
- * -6 or -7 if parsing the lengths is unsuccessful
+ * -6 or -7 if parsing the lengths is unsuccessful, e.g. when a length does not fit in a `size_t`
  * -8 if the provided data would cause an out of bounds access
  * # Safety
  * This function does extensive checking on null pointers and checks whether
@@ -110,6 +575,7 @@ int16_t deserialize_response_header(const unsigned char *response_data,
  * Having said that, if the caller inputs an invalid `data_size` there is no way
  * for this function to ever realize this; this will not happen unless the caller
  * has a bug since the header will inform the caller how large the buffer should be.
+ * The entries point into `data`, which remains owned by the caller, so they must not be freed.
  */
 int16_t destructure_two_entries(const unsigned char *data,
                                 size_t data_size,
@@ -118,40 +584,264 @@ int16_t destructure_two_entries(const unsigned char *data,
                                 const unsigned char **entry1,
                                 const unsigned char **entry2);
 
+/**
+ * Same as `destructure_two_entries` except that it additionally sets consumed to the number of bytes
+ * taken up by the two entries and verifies that this is all of `data`.
+ * # Returns
+ * 0 on success.
+ * -1 up to -8 just like `destructure_two_entries`.
+ * -9 when consumed pointer was null.
+ * -10 when data contains more than the two entries. The entries and consumed are still set in that case.
+ * # Safety
+ * Same as `destructure_two_entries`.
+ */
+int16_t destructure_two_entries_checked(const unsigned char *data,
+                                        size_t data_size,
+                                        size_t *entry1_length,
+                                        size_t *entry2_length,
+                                        const unsigned char **entry1,
+                                        const unsigned char **entry2,
+                                        size_t *consumed);
+
+/**
+ * Returns the size of the length prefix in front of every entry, i.e. `LENGTH_PREFIX_SIZE`. The prefix is
+ * always a little-endian 64-bit integer, whatever the width of `size_t`, see `write_entry_prefix`.
+ */
+size_t entry_prefix_size(void);
+
+/**
+ * Securely clears and frees the body of a `Response` created with `Response::new`. Nothing happens when
+ * body is a null pointer.
+ * # Safety
+ * `body` must be the body of a `Response` created with `Response::new` and `len` its `header.data_len`.
+ * The body must not be used or freed again afterwards.
+ */
+void free_response_body(unsigned char *body,
+                        size_t len);
+
+#if defined(PQ_MESSAGE_LIB_CHECKSUM)
+/**
+ * Returns the size of the checksum trailer which has to follow the data of every message.
+ */
+size_t get_checksum_size(void);
+#endif
+
+#if defined(PQ_MESSAGE_LIB_COMPRESS)
+/**
+ * Returns the size of the buffer needed by `decompress_response_body_c` for the data belonging to `header`.
+ * For data that is not compressed this is simply its `data_len`.
+ * # Returns
+ * The size on success.
+ * -1 when header or body was a null pointer.
+ * -2 when the compressed data is too short to be valid.
+ * # Safety
+ * `header` must point to a valid `ResponseHeader` and `body` to at least `data_len` bytes.
+ */
+int64_t get_decompressed_response_body_size(const ResponseHeader *header,
+                                            const unsigned char *body);
+#endif
+
 /**
  * Returns the size needed for the buffer where the serialized request header will be stored.
- * Will evaluate only when used for the first time.
+ * The size is a constant computed at compile time, so this cannot fail or panic.
  */
 uint64_t get_serialized_request_header_size(void);
 
 /**
  * Returns the size needed for the buffer where the serialized response header will be stored.
- * Will evaluate only when used for the first time.
+ * The size is a constant computed at compile time, so this cannot fail or panic.
  */
 uint64_t get_serialized_response_header_size(void);
 
+/**
+ * Returns a description of a code returned by `destructure_two_entries` or
+ * `destructure_two_entries_checked`, so that C code does not need its own table of them. Other functions
+ * use the same negative numbers for other failures, so only pass codes of these two.
+ * # Returns
+ * A static, null-terminated string that must not be freed. "success" for 0 and "unknown error" for a
+ * code these functions never return.
+ */
+const char *pq_message_error_str(int16_t code);
+
+/**
+ * Same as `self_test`, for C.
+ * # Returns
+ * 0 on success.
+ * -1 when the self-test failed. With the `logging` feature the failed step is logged.
+ */
+int16_t pq_message_self_test(void);
+
+/**
+ * Reads the length prefix at the start of `buf` into `len`, so that the entry follows after the returned
+ * number of bytes. The length is not checked against the rest of `buf`, nor against what fits in a
+ * `size_t`; do so before using it.
+ * # Returns
+ * The number of bytes consumed, i.e. `entry_prefix_size()`, on success.
+ * -1 when buf or len was a null pointer.
+ * -2 when buf_len is too short to hold a prefix. `len` is not changed in that case.
+ * # Safety
+ * `buf` must point to at least `buf_len` readable bytes and `len` to a writable `uint64_t`.
+ */
+int64_t read_entry_prefix(const unsigned char *buf,
+                          size_t buf_len,
+                          uint64_t *len);
+
+/**
+ * Fills in `out` with `header` and `body` after checking that `header.data_len` is exactly `body_len`, so that
+ * a `Response` built in C never describes more or less data than its body holds. The body is not copied,
+ * so it must outlive `out` and must not be released with `free_response_body`.
+ * # Returns
+ * 0 on success.
+ * -1 when out was a null pointer.
+ * -2 when body was a null pointer while body_len is not 0.
+ * -3 when `header.data_len` does not match body_len. `out` is left untouched in that case.
+ * # Safety
+ * `body` must point to at least `body_len` bytes (unless body_len is 0) and `out` must point to a writable
+ * `Response`.
+ */
+int16_t response_from_parts(ResponseHeader header,
+                            const unsigned char *body,
+                            size_t body_len,
+                            Response *out);
+
 /**
  * Receive a serialized header. Simply attach the raw bytes behind this serialized header when sending
- * over a channel.
+ * over a channel. With the `checksum` feature the message must be finished with `write_checksum`.
+ * `deadline_ms` is described at `RequestHeader`; pass 0 for no deadline.
  * # Returns
- * 0 on success, -1 on serialization failure.
+ * The number of bytes written to `target_buffer` on success, i.e. `get_serialized_request_header_size()`.
+ * -1 on serialization failure, -2 when the algorithm and operation are not valid together
+ * (see `RequestHeader::validate`), -3 when the algorithm or operation is `NoAlgorithm` or `NoOperation`, which
+ * are only placeholders. This used to return 0 on success, so check for a negative value to
+ * detect failure.
  * # Safety
  * Ensure that `target_buffer` is large enough before executing this function.
  */
-int16_t serialize_request_header(unsigned char *target_buffer,
+int64_t serialize_request_header(unsigned char *target_buffer,
                                  size_t target_buffer_len,
-                                 uint64_t identifier,
+                                 Identifier identifier,
                                  uint32_t data_len,
                                  Algorithm algorithm,
-                                 Operation operation);
+                                 Operation operation,
+                                 uint64_t deadline_ms);
+
+/**
+ * Same as `serialize_request_header` except that the header is written at `offset` within
+ * `target_buffer`, e.g. to build several framed messages in one reused buffer without querying
+ * `get_serialized_request_header_size` first.
+ * # Returns
+ * The offset right after the header on success, where the body can be written.
+ * -1 on serialization failure or when the header does not fit in `target_buffer_len` bytes at `offset`.
+ * -2 and -3 just like `serialize_request_header`.
+ * # Safety
+ * `target_buffer` must point to at least `target_buffer_len` writable bytes.
+ */
+int64_t serialize_request_header_at(unsigned char *target_buffer,
+                                    size_t target_buffer_len,
+                                    size_t offset,
+                                    Identifier identifier,
+                                    uint32_t data_len,
+                                    Algorithm algorithm,
+                                    Operation operation,
+                                    uint64_t deadline_ms);
+
+/**
+ * C version of `serialize_response` which writes the serialized response into `out_buffer`. A null `data`
+ * results in only the header with `ResponseStatus::OperationFailed` as success status.
+ * # Returns
+ * The number of bytes written to `out_buffer` on success.
+ * -1 when out_buffer was a null pointer.
+ * -2 when out_buffer_len is too small to hold the response.
+ * -3 when data_len is too long to be described by the `data_len` of the header.
+ * -4 on serialization failure.
+ * # Safety
+ * `data` must point to at least `data_len` bytes (unless it is a null pointer) and `out_buffer` must point to
+ * at least `out_buffer_len` writable bytes. The buffer needs room for `get_serialized_response_header_size()`
+ * plus `data_len` bytes (plus the trailer with the `checksum` feature).
+ */
+int64_t serialize_response_c(Identifier identifier,
+                             const unsigned char *data,
+                             size_t data_len,
+                             unsigned char *out_buffer,
+                             size_t out_buffer_len);
+
+/**
+ * Given `entry_count` entries and their lengths this function will put them back-to-back into data with
+ * length included. This is the generalization of `structure_two_entries`; for two entries both produce
+ * exactly the same bytes.
+ * # Returns
+ * 0 on success.
+ * -1 when data was a null pointer.
+ * -2 when entry_lengths was a null pointer.
+ * -3 when entries was a null pointer.
+ * -4 when one of the entries was a null pointer while its length is not 0.
+ * -5 when entry_count was 0.
+ * -6 when the structured entries do not fit in a `size_t`, see `structure_n_entries_length`.
+ * # Safety
+ * `entry_lengths` and `entries` must both point to at least `entry_count` elements. If any of the
+ * lengths are not appropriate (too long for example) then an out of bounds access will occur; this
+ * is a bug introduced by the caller. When used in combination with the `structure_n_entries_length`
+ * function this will never occur.
+ */
+int16_t structure_n_entries(unsigned char *data,
+                            const size_t *entry_lengths,
+                            const unsigned char *const *entries,
+                            size_t entry_count);
+
+/**
+ * Given the lengths of `entry_count` entries returns the length of the buffer required to fit all entries
+ * including their lengths.
+ * # Returns
+ * The required length on success.
+ * 0 when `entry_lengths` was a null pointer or `entry_count` was 0.
+ * -1 when the length does not fit in a `size_t` (or an `int64_t`). Never allocate a buffer in that case.
+ * # Safety
+ * `entry_lengths` must point to at least `entry_count` lengths.
+ */
+int64_t structure_n_entries_length(const size_t *entry_lengths,
+                                   size_t entry_count);
+
+/**
+ * Given three entries and their length this function will put them back-to-back into data with length included,
+ * e.g. a shared secret, some metadata and a ciphertext.
+ * # Returns
+ * 0 on success.
+ * -1 when data was a null pointer.
+ * -2 when entry1 was a null pointer while entry1_length is not 0.
+ * -3 when entry2 was a null pointer while entry2_length is not 0.
+ * -4 when entry3 was a null pointer while entry3_length is not 0.
+ * -5 when the structured entries do not fit in a `size_t`, see `structure_three_entries_length`.
+ * # Safety
+ * If any of the lengths are not appropriate (too long for example) then an out of bounds access will
+ * occur; this is a bug introduced by the caller. When used in combination with the
+ * `structure_three_entries_length` function this will never occur.
+ */
+int16_t structure_three_entries(unsigned char *data,
+                                size_t entry1_length,
+                                size_t entry2_length,
+                                size_t entry3_length,
+                                const unsigned char *entry1,
+                                const unsigned char *entry2,
+                                const unsigned char *entry3);
+
+/**
+ * Given the length of three entries returns the length of the buffer required to fit all three entries including
+ * their lengths.
+ * # Returns
+ * The length on success.
+ * -1 when the length does not fit in a `size_t` (or an `int64_t`). Never allocate a buffer in that case.
+ */
+int64_t structure_three_entries_length(size_t entry1_length,
+                                       size_t entry2_length,
+                                       size_t entry3_length);
 
 /**
  * Given two entries and their length this function will put them back-to-back into data with length included.
  * # Returns
  * 0 on success.
  * -1 when data was a null pointer.
- * -2 when entry1 was a null pointer.
- * -3 when entry2 was a null pointer.
+ * -2 when entry1 was a null pointer while entry1_length is not 0.
+ * -3 when entry2 was a null pointer while entry2_length is not 0.
  * # Safety
  * If entry1_length or entry2_length are not appropriate (too long for example) then an out of bounds
  * access will occur; this is a bug introduced by the caller. When used in combination with the
@@ -165,6 +855,64 @@ int16_t structure_two_entries(unsigned char *data,
 
 /**
  * Given the length of two entries returns the length of the buffer required to fit both entries including their lengths.
+ * # Returns
+ * The length on success.
+ * -1 when the length does not fit in a `size_t` (or an `int64_t`). Never allocate a buffer in that case.
+ */
+int64_t structure_two_entries_length(size_t entry1_length,
+                                     size_t entry2_length);
+
+/**
+ * Fills `out` with the algorithms supported by this build (see `Algorithm::is_supported`), so that a
+ * requester can avoid asking for algorithms that will fail. Call it with a null `out` and a `capacity` of 0
+ * to find out how many there are.
+ * # Returns
+ * The number of algorithms written to `out` on success.
+ * Minus the number of supported algorithms when `capacity` is too small (or `out` is null), in which case
+ * nothing is written.
+ * # Safety
+ * `out` must point to at least `capacity` writable `Algorithm`s.
+ */
+int64_t supported_algorithms(Algorithm *out,
+                             size_t capacity);
+
+#if defined(PQ_MESSAGE_LIB_CHECKSUM)
+/**
+ * Given a buffer with a serialized header, its data and room for the trailer (see `get_checksum_size`)
+ * this function will compute the checksum and write it into the last bytes of the buffer.
+ * # Returns
+ * 0 on success.
+ * -1 when message was a null pointer.
+ * -2 when message_len cannot even hold the trailer.
+ * # Safety
+ * `message` must point to at least `message_len` writable bytes.
+ */
+int16_t write_checksum(unsigned char *message,
+                       size_t message_len);
+#endif
+
+/**
+ * Writes the length prefix of an entry of `len` bytes to the start of `buf`, exactly as
+ * `structure_two_entries` does.
+ * # Returns
+ * The number of bytes written, i.e. `entry_prefix_size()`, on success.
+ * -1 when buf was a null pointer.
+ * -2 when buf_len cannot hold the prefix. Nothing is written in that case.
+ * # Safety
+ * `buf` must point to at least `buf_len` writable bytes.
+ */
+int64_t write_entry_prefix(unsigned char *buf, size_t buf_len, uint64_t len);
+
+/**
+ * Securely clears `len` bytes starting at `buffer`. The clearing is guaranteed to not be optimized
+ * away by the compiler, which is not the case for a plain `memset` on memory that is about to be freed.
+ * Use this on buffers that held cryptographically sensitive data (e.g. a private key or a received
+ * response) before freeing them.
+ * # Returns
+ * 0 on success.
+ * -1 when buffer was a null pointer.
+ * # Safety
+ * `buffer` must point to at least `len` writable bytes.
  */
-size_t structure_two_entries_length(size_t entry1_length,
-                                    size_t entry2_length);
+int16_t zeroize_buffer(unsigned char *buffer,
+                       size_t len);
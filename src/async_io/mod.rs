@@ -0,0 +1,138 @@
+use crate::request::{RequestHeader, RequestStreamWriter, MAX_MESSAGE_SIZE};
+use crate::response::{ResponseHeader, ResponseStatus};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Writes a request, i.e. the header followed by `body`, to `writer`. The same bytes as a single request
+/// pushed to a `RequestStreamWriter` are written (so with the `checksum` feature the trailer is included).
+/// # Errors
+/// The errors of `RequestStreamWriter::push` and `MessageError::Io` when writing fails.
+pub async fn write_request<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    header: &RequestHeader,
+    body: &[u8],
+) -> Result<(), crate::MessageError> {
+    let mut stream = RequestStreamWriter::new();
+    stream.push(header, body)?;
+    let serialized = zeroize::Zeroizing::new(stream.into_bytes());
+
+    writer.write_all(&serialized).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Reads a request written by `write_request` (or any other request) from `reader`: first the header, then
+/// exactly `data_len` bytes of data.
+/// # Errors
+/// `MessageError::UnexpectedEof` when the stream ends before the whole request has been read,
+/// `MessageError::MessageTooLarge` when the request would exceed `request::MAX_MESSAGE_SIZE`, the errors of
+/// `request::deserialize_request_header`, `MessageError::ChecksumMismatch` with the `checksum` feature and
+/// `MessageError::Io` when reading fails.
+pub async fn read_request<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> Result<(RequestHeader, Vec<u8>), crate::MessageError> {
+    read_request_bounded(reader, MAX_MESSAGE_SIZE).await
+}
+
+/// Like `read_request`, but accepts requests of up to `max_message_size` bytes, header and trailer included,
+/// instead of `request::MAX_MESSAGE_SIZE`.
+/// # Errors
+/// The errors of `read_request`, with `MessageError::MessageTooLarge` for a request exceeding
+/// `max_message_size`.
+pub async fn read_request_bounded<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_message_size: usize,
+) -> Result<(RequestHeader, Vec<u8>), crate::MessageError> {
+    let mut serialized = vec![0; crate::request::get_serialized_request_header_size() as usize];
+    read_exact(reader, &mut serialized).await?;
+    let header = crate::request::deserialize_request_header(&serialized)?;
+    let body = read_body(reader, serialized, header.data_len, max_message_size).await?;
+
+    Ok((header, body))
+}
+
+/// Writes the response to the request with `identifier` to `writer`: `response::serialize_response` for
+/// `Ok` and `response::serialize_failure_response` for `Err`.
+/// # Errors
+/// The errors of those functions and `MessageError::Io` when writing fails.
+pub async fn write_response<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    identifier: crate::Identifier,
+    data: Result<&[u8], ResponseStatus>,
+) -> Result<(), crate::MessageError> {
+    let serialized = zeroize::Zeroizing::new(crate::response::serialize_response_result(
+        identifier, data,
+    )?);
+
+    writer.write_all(&serialized).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Reads a response from `reader`: first the header, then exactly `data_len` bytes of data. The data is
+/// returned as it was sent, so compressed data (see `ResponseHeader::is_compressed`) has to be
+/// decompressed afterwards.
+/// # Errors
+/// `MessageError::UnexpectedEof` when the stream ends before the whole response has been read,
+/// `MessageError::MessageTooLarge` when the response would exceed `request::MAX_MESSAGE_SIZE`, the errors of
+/// `response::deserialize_response_header_bounded`, `MessageError::ChecksumMismatch` with the `checksum`
+/// feature and `MessageError::Io` when reading fails.
+pub async fn read_response<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> Result<(ResponseHeader, Vec<u8>), crate::MessageError> {
+    read_response_bounded(reader, MAX_MESSAGE_SIZE).await
+}
+
+/// Like `read_response`, but accepts responses of up to `max_message_size` bytes, header and trailer
+/// included, instead of `request::MAX_MESSAGE_SIZE`.
+/// # Errors
+/// The errors of `read_response`, with `MessageError::MessageTooLarge` for a response exceeding
+/// `max_message_size`.
+pub async fn read_response_bounded<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_message_size: usize,
+) -> Result<(ResponseHeader, Vec<u8>), crate::MessageError> {
+    let mut serialized = vec![0; crate::response::get_serialized_response_header_size() as usize];
+    read_exact(reader, &mut serialized).await?;
+    let header = crate::response::read_response_header(&serialized)?;
+    let body = read_body(reader, serialized, header.data_len, max_message_size).await?;
+
+    Ok((header, body))
+}
+
+/// Reads the `data_len` bytes of data following the already read `serialized` header, and with the
+/// `checksum` feature verifies the trailer over both. Nothing is read when the message would exceed
+/// `max_message_size`.
+async fn read_body<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    serialized: Vec<u8>,
+    data_len: u32,
+    max_message_size: usize,
+) -> Result<Vec<u8>, crate::MessageError> {
+    let header_size = serialized.len();
+    let message_size = crate::check_message_size(header_size, data_len, max_message_size)?;
+
+    let mut message = zeroize::Zeroizing::new(serialized);
+    message.resize(message_size, 0);
+    read_exact(reader, &mut message[header_size..]).await?;
+
+    #[cfg(feature = "checksum")]
+    crate::checksum::verify_checksum(&message)?;
+
+    Ok(message[header_size..header_size + data_len as usize].to_vec())
+}
+
+/// Fills `buffer` from `reader`, however many reads that takes.
+async fn read_exact<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    buffer: &mut [u8],
+) -> Result<(), crate::MessageError> {
+    match reader.read_exact(buffer).await {
+        Ok(_) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Err(crate::MessageError::UnexpectedEof)
+        }
+        Err(error) => Err(error.into()),
+    }
+}
@@ -0,0 +1,30 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// Number of bytes of the HMAC-SHA256 tag that follows an authenticated header.
+pub const AUTH_TAG_SIZE: usize = 32;
+
+fn mac(key: &[u8], data: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac
+}
+
+/// Computes the HMAC-SHA256 tag of `data` under `key`.
+pub fn compute_tag(key: &[u8], data: &[u8]) -> [u8; AUTH_TAG_SIZE] {
+    mac(key, data).finalize().into_bytes().into()
+}
+
+/// Verifies that `tag` is the HMAC-SHA256 tag of `data` under `key`. The comparison takes the same
+/// time wherever the tags differ so it does not reveal how much of a forged tag was correct.
+/// # Errors
+/// `MessageError::AuthFailed` when the tag does not match.
+pub fn verify_tag(key: &[u8], data: &[u8], tag: &[u8]) -> Result<(), crate::MessageError> {
+    mac(key, data).verify_slice(tag).map_err(|_| {
+        log_debug!(
+            "authentication tag over {} bytes does not match",
+            data.len()
+        );
+        crate::MessageError::AuthFailed
+    })
+}
@@ -0,0 +1,191 @@
+use crate::request::{Operation, PendingRequests, RequestHeader, RequestStreamWriter};
+use crate::response::{ResponseReader, ResponseStatus};
+
+/// Sends requests over a single stream and reads the responses coming back on it, matching them to
+/// their request by identifier. Requests can be sent ahead with `send` and their responses collected with
+/// `receive` in any order; a response that arrives for another request than the one being waited for is
+/// buffered until it is asked for. `request` does both for a single request. A request that was sent ahead
+/// can be cancelled with `cancel`.
+///
+/// Everything blocks on the underlying stream. A response is limited to `max_message_size` bytes, header and
+/// trailer included (`request::MAX_MESSAGE_SIZE` by default), so that a misbehaving peer cannot make it
+/// allocate an arbitrary amount of memory.
+pub struct Channel<T: std::io::Read + std::io::Write> {
+    stream: T,
+    pending: PendingRequests,
+    received: alloc::collections::BTreeMap<crate::Identifier, Result<Vec<u8>, crate::MessageError>>,
+    max_message_size: usize,
+}
+
+impl<T: std::io::Read + std::io::Write> Channel<T> {
+    pub fn new(stream: T) -> Self {
+        Channel {
+            stream,
+            pending: PendingRequests::new(),
+            received: alloc::collections::BTreeMap::new(),
+            max_message_size: crate::request::MAX_MESSAGE_SIZE,
+        }
+    }
+
+    /// Sets the largest response that is accepted, header and trailer included.
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = max_message_size;
+    }
+
+    /// Sends a request and waits for its response, see `send` and `receive`.
+    pub fn request(
+        &mut self,
+        header: RequestHeader,
+        body: &[u8],
+    ) -> Result<Vec<u8>, crate::MessageError> {
+        let identifier = header.identifier;
+        self.send(header, body)?;
+
+        self.receive(identifier)
+    }
+
+    /// Writes a request to the stream without waiting for its response.
+    /// # Errors
+    /// The errors of `RequestStreamWriter::push`, `MessageError::InvalidField` when a request with the same
+    /// identifier is still waiting for its response or `header` is a `Cancel`, which is sent with `cancel`
+    /// instead, and `MessageError::Io` when writing fails.
+    pub fn send(&mut self, header: RequestHeader, body: &[u8]) -> Result<(), crate::MessageError> {
+        if header.operation == Operation::Cancel {
+            return Err(crate::MessageError::InvalidField {
+                field: "operation",
+                reason: "a request is cancelled with Channel::cancel",
+            });
+        }
+        let mut writer = RequestStreamWriter::new();
+        writer.push(&header, body)?;
+        if self.received.contains_key(&header.identifier) {
+            return Err(crate::MessageError::InvalidField {
+                field: "identifier",
+                reason: "the response to a request with this identifier has not been received yet",
+            });
+        }
+        self.pending.register(header)?;
+
+        let serialized = zeroize::Zeroizing::new(writer.into_bytes());
+        self.stream.write_all(&serialized)?;
+        self.stream.flush()?;
+
+        Ok(())
+    }
+
+    /// Asks the responder to abort the request with `identifier`, which was sent with `send` and is still
+    /// waiting for its response. The `Cancel` request names its target instead of being a request of its own,
+    /// so it is not registered and nothing answers it. The target is answered instead, with
+    /// `ResponseStatus::Cancelled` when the responder stopped in time and with its result otherwise; `receive`
+    /// returns that response as usual and stops waiting for the request.
+    /// # Errors
+    /// `MessageError::InvalidField` when no request with `identifier` is waiting for its response, e.g.
+    /// because it was answered already, and `MessageError::Io` when writing fails.
+    pub fn cancel(&mut self, identifier: crate::Identifier) -> Result<(), crate::MessageError> {
+        if !self.pending.contains(identifier) {
+            return Err(crate::MessageError::InvalidField {
+                field: "identifier",
+                reason: "no request with this identifier is waiting for its response",
+            });
+        }
+
+        self.stream
+            .write_all(&crate::request::build_cancel_request(identifier))?;
+        self.stream.flush()?;
+
+        Ok(())
+    }
+
+    /// Returns the data of the response to the request with `identifier`, reading responses from the stream
+    /// until it arrives. Compressed data is decompressed.
+    /// # Errors
+    /// `MessageError::RequestFailed` when the responder reported a failure, `MessageError::InvalidField` when
+    /// no request with `identifier` was sent or a response arrives for a request that was never sent,
+    /// `MessageError::MessageTooLarge` when a response exceeds `max_message_size` bytes and the errors of
+    /// `ResponseReader`. After an error while reading from the stream the channel cannot be used anymore.
+    pub fn receive(
+        &mut self,
+        identifier: crate::Identifier,
+    ) -> Result<Vec<u8>, crate::MessageError> {
+        if let Some(response) = self.received.remove(&identifier) {
+            return response;
+        } else if !self.pending.contains(identifier) {
+            return Err(crate::MessageError::InvalidField {
+                field: "identifier",
+                reason: "no request with this identifier is waiting for its response",
+            });
+        }
+
+        loop {
+            let (response_identifier, response) = self.read_response()?;
+            if response_identifier == identifier {
+                return response;
+            }
+            self.received.insert(response_identifier, response);
+        }
+    }
+
+    /// Reads the next response from the stream and returns the identifier it belongs to together with its
+    /// outcome. Only the outer error means that the stream itself could not be read.
+    #[allow(clippy::type_complexity)]
+    fn read_response(
+        &mut self,
+    ) -> Result<(crate::Identifier, Result<Vec<u8>, crate::MessageError>), crate::MessageError>
+    {
+        let mut reader = ResponseReader::new(&mut self.stream)?;
+        let header = reader.header();
+        crate::check_message_size(
+            crate::response::get_serialized_response_header_size() as usize,
+            header.data_len,
+            self.max_message_size,
+        )?;
+        let identifier = header.identifier;
+        let status = header.status();
+        let raw_status = header.raw_status();
+        if self.pending.match_response(header).is_none() {
+            return Err(crate::MessageError::InvalidField {
+                field: "identifier",
+                reason: "response to a request that was not sent",
+            });
+        }
+
+        let mut body = vec![0; reader.remaining()];
+        let mut filled = 0;
+        while filled < body.len() {
+            filled += reader.read_chunk(&mut body[filled..])?;
+        }
+
+        if status != Some(ResponseStatus::Ok) {
+            if reader.header().is_diagnostic() {
+                log_debug!(
+                    "request {} failed: {}",
+                    identifier,
+                    String::from_utf8_lossy(&body)
+                );
+            }
+            return Ok((
+                identifier,
+                Err(crate::MessageError::RequestFailed(raw_status)),
+            ));
+        }
+
+        #[cfg(feature = "compress")]
+        let body = crate::response::decompress_response_body(reader.header(), &body);
+        #[cfg(not(feature = "compress"))]
+        let body = if reader.header().is_compressed() {
+            Err(crate::MessageError::InvalidField {
+                field: "flags",
+                reason: "compressed data needs the compress feature",
+            })
+        } else {
+            Ok(body)
+        };
+
+        Ok((identifier, body))
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> T {
+        self.stream
+    }
+}
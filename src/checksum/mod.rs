@@ -0,0 +1,101 @@
+use alloc::vec::Vec;
+use core::convert::TryInto;
+
+/// Number of bytes of the checksum trailer.
+pub const CHECKSUM_SIZE: usize = core::mem::size_of::<u32>();
+
+/// Computes the CRC32C (Castagnoli) checksum of `data`.
+pub fn crc32c(data: &[u8]) -> u32 {
+    !crc32c_update(!0, data)
+}
+
+/// Feeds `data` into a running CRC32C computation. Start with `!0` and invert the final value to
+/// obtain the checksum, which allows a message to be checksummed in pieces.
+pub(crate) fn crc32c_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82F6_3B78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// Appends the checksum trailer to a message consisting of a serialized header and its data.
+pub fn append_checksum(message: &mut Vec<u8>) {
+    let checksum = crc32c(message);
+    message.extend(&checksum.to_le_bytes());
+}
+
+/// Writes the checksum of everything before the last `CHECKSUM_SIZE` bytes of `message` into those bytes,
+/// which have to be there.
+pub(crate) fn fill_checksum(message: &mut [u8]) {
+    let (contents, trailer) = message.split_at_mut(message.len() - CHECKSUM_SIZE);
+    trailer.copy_from_slice(&crc32c(contents).to_le_bytes());
+}
+
+/// Verifies the checksum trailer at the end of `message`. On success the message without its trailer
+/// is returned.
+/// # Errors
+/// `MessageError::BufferTooShort` when `message` cannot even hold a trailer and
+/// `MessageError::ChecksumMismatch` when the trailer does not match the contents of the message.
+pub fn verify_checksum(message: &[u8]) -> Result<&[u8], crate::MessageError> {
+    if message.len() < CHECKSUM_SIZE {
+        return Err(crate::MessageError::BufferTooShort {
+            needed: CHECKSUM_SIZE,
+            got: message.len(),
+        });
+    }
+
+    let (contents, trailer) = message.split_at(message.len() - CHECKSUM_SIZE);
+    let expected = u32::from_le_bytes(trailer.try_into().unwrap());
+    let found = crc32c(contents);
+    if expected != found {
+        log_debug!(
+            "checksum mismatch over {} bytes: expected {:#010x} but found {:#010x}",
+            contents.len(),
+            expected,
+            found
+        );
+        return Err(crate::MessageError::ChecksumMismatch { expected, found });
+    }
+
+    Ok(contents)
+}
+
+/// Returns the size of the checksum trailer which has to follow the data of every message.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub extern "C" fn get_checksum_size() -> libc::size_t {
+    CHECKSUM_SIZE
+}
+
+/// Given a buffer with a serialized header, its data and room for the trailer (see `get_checksum_size`)
+/// this function will compute the checksum and write it into the last bytes of the buffer.
+/// # Returns
+/// 0 on success.
+/// -1 when message was a null pointer.
+/// -2 when message_len cannot even hold the trailer.
+/// # Safety
+/// `message` must point to at least `message_len` writable bytes.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn write_checksum(
+    message: *mut libc::c_uchar,
+    message_len: libc::size_t,
+) -> i16 {
+    if message.is_null() {
+        return -1;
+    } else if message_len < CHECKSUM_SIZE {
+        return -2;
+    }
+
+    fill_checksum(core::slice::from_raw_parts_mut(message, message_len));
+
+    0
+}
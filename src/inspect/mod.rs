@@ -0,0 +1,112 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::request::RequestHeader;
+use crate::response::ResponseHeader;
+
+// Number of bytes of the data that are shown by `describe_request` and `describe_response`.
+const PREVIEW_LEN: usize = 16;
+
+/// Returns a human-readable description of the request in `buf`, a serialized header followed by its data,
+/// e.g. for logging while debugging. Never panics: when the header cannot be parsed the description says why
+/// and shows the start of `buf` instead. The data is not interpreted, only its first 16 bytes
+/// are shown, so do not log descriptions of requests that carry secrets in production.
+pub fn describe_request(buf: &[u8]) -> String {
+    let header = match RequestHeader::from_bytes(buf) {
+        Ok(header) => header,
+        Err(error) => return describe_failure("request", &error, buf),
+    };
+
+    let mut description = format!(
+        "request: version {}{}, identifier {}, algorithm {}, operation {}, data_len {}, deadline_ms {}, flags {:#04x}",
+        header.version(),
+        version_note(header.version()),
+        header.identifier,
+        header.algorithm,
+        header.operation,
+        header.data_len,
+        header.deadline_ms,
+        header.flags,
+    );
+    let header_size = crate::request::get_serialized_request_header_size() as usize;
+    describe_data(&mut description, &buf[header_size..], header.data_len);
+
+    description
+}
+
+/// Returns a human-readable description of the response in `buf`, a serialized header followed by its data,
+/// just like `describe_request` does for requests.
+pub fn describe_response(buf: &[u8]) -> String {
+    let header = match ResponseHeader::from_bytes(buf) {
+        Ok(header) => header,
+        Err(error) => return describe_failure("response", &error, buf),
+    };
+
+    let status = match header.status() {
+        Some(status) => format!("{:?}", status),
+        None => format!("unknown status {}", header.raw_status()),
+    };
+    let mut description = format!(
+        "response: version {}{}, identifier {}, status {}, data_len {}, flags {:#04x}{}",
+        header.version(),
+        version_note(header.version()),
+        header.identifier,
+        status,
+        header.data_len,
+        header.flags,
+        if header.is_compressed() {
+            " (compressed)"
+        } else if header.is_diagnostic() {
+            " (diagnostic)"
+        } else {
+            ""
+        },
+    );
+    let header_size = crate::response::get_serialized_response_header_size() as usize;
+    describe_data(&mut description, &buf[header_size..], header.data_len);
+
+    description
+}
+
+/// Points out a version that is not accepted, as that header will be rejected.
+fn version_note(version: u8) -> String {
+    if crate::check_format_version(version).is_ok() {
+        String::new()
+    } else {
+        format!(" (expected {})", crate::FORMAT_VERSION)
+    }
+}
+
+fn describe_failure(kind: &str, error: &crate::MessageError, buf: &[u8]) -> String {
+    let mut description = format!("{}: header could not be parsed: {}; bytes:", kind, error);
+    write_preview(&mut description, buf);
+
+    description
+}
+
+/// Appends how much of the data is present and a preview of it. Whatever follows the data (e.g. a
+/// checksum trailer or the next message) is only counted.
+fn describe_data(description: &mut String, rest: &[u8], data_len: u32) {
+    let data_len = data_len as usize;
+    let data = &rest[..rest.len().min(data_len)];
+
+    let _ = write!(description, "; data:");
+    write_preview(description, data);
+    if data.len() < data_len {
+        let _ = write!(description, " (missing {} bytes)", data_len - data.len());
+    } else if rest.len() > data_len {
+        let _ = write!(description, " ({} trailing bytes)", rest.len() - data_len);
+    }
+}
+
+fn write_preview(description: &mut String, bytes: &[u8]) {
+    for byte in bytes.iter().take(PREVIEW_LEN) {
+        let _ = write!(description, " {:02x}", byte);
+    }
+    if bytes.len() > PREVIEW_LEN {
+        let _ = write!(description, " ... ({} bytes)", bytes.len());
+    } else if bytes.is_empty() {
+        let _ = write!(description, " none");
+    }
+}
@@ -0,0 +1,59 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+
+use crate::request::RequestHeader;
+
+// A `RequestHeader` as it appears in JSON. The algorithm and operation are spelled out by name so that a dump
+// can be read without looking up discriminants.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct JsonRequestHeader<'a> {
+    version: u8,
+    identifier: crate::Identifier,
+    data_len: u32,
+    #[serde(borrow)]
+    algorithm: Cow<'a, str>,
+    #[serde(borrow)]
+    operation: Cow<'a, str>,
+    deadline_ms: u64,
+    flags: u8,
+}
+
+/// Returns `header` as a JSON object with the same fields as the serialized header, e.g.
+/// `{"version":10,"identifier":1234,"data_len":0,"algorithm":"FRODO976__ECDHp384","operation":"KeypairGeneration","deadline_ms":0,"flags":0}`.
+/// The algorithm and operation are given by `Algorithm::as_str` and `Operation::as_str`. This is meant for
+/// comparing headers with implementations in other languages while debugging, the wire format is unaffected.
+pub fn request_header_to_json(header: &RequestHeader) -> String {
+    let json = JsonRequestHeader {
+        version: header.version(),
+        identifier: header.identifier,
+        data_len: header.data_len,
+        algorithm: Cow::Borrowed(header.algorithm.as_str()),
+        operation: Cow::Borrowed(header.operation.as_str()),
+        deadline_ms: header.deadline_ms,
+        flags: header.flags,
+    };
+
+    serde_json::to_string(&json).expect("integers and strings always serialize to JSON")
+}
+
+/// Reads a header written by `request_header_to_json`. The algorithm may be given by its canonical name as
+/// well, see `Algorithm::from_str`. Just like `RequestHeader::from_bytes` the version is not checked.
+/// # Errors
+/// `MessageError::InvalidJson` when `json` is not such an object, `MessageError::UnknownAlgorithmName` and
+/// `MessageError::UnknownOperationName` when the algorithm or operation has no such name.
+pub fn request_header_from_json(json: &str) -> Result<RequestHeader, crate::MessageError> {
+    let json: JsonRequestHeader = serde_json::from_str(json).map_err(|error| {
+        log_debug!("could not parse request header from JSON: {}", error);
+        crate::MessageError::InvalidJson(error.to_string())
+    })?;
+
+    Ok(RequestHeader {
+        version: json.version,
+        identifier: json.identifier,
+        data_len: json.data_len,
+        algorithm: json.algorithm.parse()?,
+        operation: json.operation.parse()?,
+        deadline_ms: json.deadline_ms,
+        flags: json.flags,
+    })
+}
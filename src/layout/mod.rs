@@ -0,0 +1,40 @@
+// Every offset is the previous offset plus the size of the previous field, so reordering or resizing a field
+// here moves all later ones. `test_layout_offsets` checks them against what `to_bytes` produces. The
+// checksum trailer of the `checksum` feature follows the data and is not part of either header.
+
+/// Size of the identifier in either header: 8 bytes, or 16 with the `uuid` feature.
+#[cfg(not(feature = "uuid"))]
+pub const IDENTIFIER_SIZE: usize = 8;
+/// Size of the identifier in either header: 8 bytes, or 16 with the `uuid` feature.
+#[cfg(feature = "uuid")]
+pub const IDENTIFIER_SIZE: usize = 16;
+
+/// Offset of the format version in a request header, a `u8`.
+pub const REQUEST_VERSION_OFFSET: usize = 0;
+/// Offset of the identifier in a request header, see `IDENTIFIER_SIZE`.
+pub const REQUEST_IDENTIFIER_OFFSET: usize = REQUEST_VERSION_OFFSET + 1;
+/// Offset of `data_len` in a request header, a `u32`.
+pub const REQUEST_DATA_LEN_OFFSET: usize = REQUEST_IDENTIFIER_OFFSET + IDENTIFIER_SIZE;
+/// Offset of the algorithm in a request header, the `u32` discriminant of the `Algorithm`.
+pub const REQUEST_ALGORITHM_OFFSET: usize = REQUEST_DATA_LEN_OFFSET + 4;
+/// Offset of the operation in a request header, the `u32` discriminant of the `Operation`.
+pub const REQUEST_OPERATION_OFFSET: usize = REQUEST_ALGORITHM_OFFSET + 4;
+/// Offset of `deadline_ms` in a request header, a `u64`.
+pub const REQUEST_DEADLINE_MS_OFFSET: usize = REQUEST_OPERATION_OFFSET + 4;
+/// Offset of the flags in a request header, a `u8`.
+pub const REQUEST_FLAGS_OFFSET: usize = REQUEST_DEADLINE_MS_OFFSET + 8;
+/// Size of a request header, i.e. `get_serialized_request_header_size()`.
+pub const REQUEST_HEADER_SIZE: usize = REQUEST_FLAGS_OFFSET + 1;
+
+/// Offset of the format version in a response header, a `u8`.
+pub const RESPONSE_VERSION_OFFSET: usize = 0;
+/// Offset of the identifier in a response header, see `IDENTIFIER_SIZE`.
+pub const RESPONSE_IDENTIFIER_OFFSET: usize = RESPONSE_VERSION_OFFSET + 1;
+/// Offset of the success status in a response header, an `i8`.
+pub const RESPONSE_SUCCESS_OFFSET: usize = RESPONSE_IDENTIFIER_OFFSET + IDENTIFIER_SIZE;
+/// Offset of `data_len` in a response header, a `u32`.
+pub const RESPONSE_DATA_LEN_OFFSET: usize = RESPONSE_SUCCESS_OFFSET + 1;
+/// Offset of the flags in a response header, a `u8`.
+pub const RESPONSE_FLAGS_OFFSET: usize = RESPONSE_DATA_LEN_OFFSET + 4;
+/// Size of a response header, i.e. `get_serialized_response_header_size()`.
+pub const RESPONSE_HEADER_SIZE: usize = RESPONSE_FLAGS_OFFSET + 1;
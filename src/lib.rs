@@ -4,32 +4,485 @@
 //! send the result back.
 //! One should take care that the IPC channel used is not readable by everyone
 //! as cryptographically sensitive data will go over this channel.
+//!
+//! Only the following leave no plaintext residue behind in memory once they are done:
+//! - `secure::zeroize_buffer` for any buffer the caller passes to it.
+//! - `response::ZeroizingResponse` for the serialized response it wraps, once it is dropped.
+//!
+//! All other functions leave clearing of the buffers they are given up to the caller.
+//!
+//! Everything on the wire is little-endian, regardless of the endianness of the host. This holds for
+//! the serialized headers as well as for the length prefixes written by the structuring functions.
+//! The headers are serialized field by field by this crate itself (see `request::RequestHeader::to_bytes`),
+//! so the wire format does not depend on the defaults of any serialization library.
+//!
+//! The CRC32C of the `checksum` feature is a trailer after the data instead of a field of either header.
+//! A field in the header would have to be known before the header is written, so `response::ResponseWriter`
+//! could no longer send the header ahead of data it has not seen yet, and the header layout would differ
+//! with the feature. As a trailer it is computed while the data passes, and the headers, `data_len` and
+//! `layout` are the same with and without it; every reader adds `checksum::CHECKSUM_SIZE` to the size of
+//! a message itself.
+//!
+//! Without the default `std` feature this crate is `no_std` and only needs `alloc`. The Rust side of
+//! serializing and deserializing headers remains available, while the C functions (which need `libc`)
+//! and the streaming `response::ResponseWriter`, `response::ResponseReader` and `channel::Channel` are
+//! left out.
 
-#[macro_use]
-extern crate lazy_static;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+#[cfg(all(test, feature = "serde"))]
+use alloc::{string::ToString, vec::Vec};
+use core::convert::TryFrom;
+
+// `log::trace!` and `log::debug!` with the `logging` feature. Without it nothing is logged and the arguments
+// are never evaluated, but they are still type checked so that both builds stay in sync. Never pass data
+// (keys, ciphertexts, ...) to these, only header fields and lengths.
+#[cfg(feature = "logging")]
+macro_rules! log_trace {
+    ($($arg:tt)+) => {
+        log::trace!($($arg)+)
+    };
+}
+#[cfg(not(feature = "logging"))]
+macro_rules! log_trace {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
+#[cfg(feature = "logging")]
+macro_rules! log_debug {
+    ($($arg:tt)+) => {
+        log::debug!($($arg)+)
+    };
+}
+#[cfg(not(feature = "logging"))]
+macro_rules! log_debug {
+    ($($arg:tt)+) => {
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    };
+}
 
 // Increase format version whenever the Request format is changed
-const FORMAT_VERSION: u8 = 1;
+const FORMAT_VERSION: u8 = 10 | IDENTIFIER_FORMAT_FLAG;
+
+/// Set in the format version of headers with 128-bit identifiers, i.e. those written with the `uuid`
+/// feature. Those headers have another layout, so a peer with the other identifier width rejects them
+/// as a version mismatch instead of misreading them. The versions announced in the response to a `Hello`
+/// carry it as well.
+pub const UUID_FORMAT_VERSION_FLAG: u8 = 0x80;
+
+#[cfg(not(feature = "uuid"))]
+const IDENTIFIER_FORMAT_FLAG: u8 = 0;
+#[cfg(feature = "uuid")]
+const IDENTIFIER_FORMAT_FLAG: u8 = UUID_FORMAT_VERSION_FLAG;
+
+/// The identifier of a request, which its response carries as well. With the `uuid` feature this is 128
+/// bits wide so that e.g. a `uuid::Uuid` fits (see `request::RequestHeaderBuilder::uuid`). Both sides of
+/// the IPC channel must agree on whether this feature is used.
+#[cfg(not(feature = "uuid"))]
+pub type Identifier = u64;
+/// The identifier of a request, which its response carries as well. 128 bits wide as the `uuid` feature
+/// is used.
+#[cfg(feature = "uuid")]
+pub type Identifier = u128;
+
+// The oldest format version that is still accepted when reading a header, so that during a rolling
+// upgrade the side that is ahead can still read what the other side sends. Every version in
+// `MIN_FORMAT_VERSION..=FORMAT_VERSION` must be readable by `RequestHeader::from_bytes` and
+// `ResponseHeader::from_bytes`, which means only lowering this after a bump that merely appended fields
+// and teaching `from_bytes` to fill in those fields for the older version. Up to version 6 every bump changed
+// the layout in a way that rules this out (version 6 added `flags` in the middle of a response). Version 7
+// only appended `flags` to requests, but reading version 6 has not been implemented, so only the current
+// version was accepted. Version 8 only added the ML-KEM algorithms and kept the layout, so version 7 is
+// accepted again. Version 9 only added the `Cancel` operation and version 10 the ML-DSA algorithms. Writing
+// always uses `FORMAT_VERSION`.
+const MIN_FORMAT_VERSION: u8 = 7 | IDENTIFIER_FORMAT_FLAG;
+
+// Versions that are announced in the response to a `Hello`. These are exactly the versions that are
+// accepted when reading, derived from `MIN_FORMAT_VERSION..=FORMAT_VERSION` so that they follow every bump.
+const SUPPORTED_FORMAT_VERSIONS: [u8; (FORMAT_VERSION - MIN_FORMAT_VERSION) as usize + 1] = {
+    let mut versions = [0; (FORMAT_VERSION - MIN_FORMAT_VERSION) as usize + 1];
+    let mut index = 0;
+    while index < versions.len() {
+        versions[index] = MIN_FORMAT_VERSION + index as u8;
+        index += 1;
+    }
+    versions
+};
+
+/// Returns whether a header of `version` is accepted when reading it, i.e. whether it lies within
+/// `MIN_FORMAT_VERSION..=FORMAT_VERSION`.
+/// # Errors
+/// `MessageError::VersionMismatch` when it is not.
+fn check_format_version(version: u8) -> Result<(), MessageError> {
+    if !(MIN_FORMAT_VERSION..=FORMAT_VERSION).contains(&version) {
+        log_debug!("format version {} is not accepted", version);
+        return Err(MessageError::VersionMismatch {
+            expected: FORMAT_VERSION,
+            found: version,
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns the size of a message with a header of `header_size` bytes describing `data_len` bytes of data,
+/// the trailer of the `checksum` feature included. Every reader checks this before allocating for the
+/// message, so that an oversized message is refused the same way everywhere.
+/// # Errors
+/// `MessageError::MessageTooLarge` when the message exceeds `max_message_size`.
+fn check_message_size(
+    header_size: usize,
+    data_len: u32,
+    max_message_size: usize,
+) -> Result<usize, MessageError> {
+    #[cfg(not(feature = "checksum"))]
+    let trailer_size = 0;
+    #[cfg(feature = "checksum")]
+    let trailer_size = checksum::CHECKSUM_SIZE;
+
+    let message_size = header_size
+        .saturating_add(data_len as usize)
+        .saturating_add(trailer_size);
+    if message_size > max_message_size {
+        log_debug!(
+            "message of {} bytes exceeds the maximum of {}",
+            message_size,
+            max_message_size
+        );
+        return Err(MessageError::MessageTooLarge {
+            max: max_message_size,
+            found: message_size,
+        });
+    }
+
+    Ok(message_size)
+}
+
+/// Returns `len` as the `data_len` of a header. Every path that serializes a header for data of its own
+/// goes through this, so that data that is too long is refused the same way everywhere.
+/// # Errors
+/// `MessageError::LengthOverflow` when `len` does not fit in a `u32`.
+fn encode_data_len(len: usize) -> Result<u32, MessageError> {
+    u32::try_from(len).map_err(|_| {
+        log_debug!("refusing to describe {} bytes of data in data_len", len);
+        MessageError::LengthOverflow
+    })
+}
+
+// The headers used to be serialized with bincode using this configuration: fixed-size integers in
+// little-endian byte order, see the wire format in the README. The tests use it to check that the
+// hand-written serialization still produces exactly the same bytes.
+#[cfg(all(test, feature = "serde"))]
+const BINCODE_CONFIG: bincode::config::Configuration<
+    bincode::config::LittleEndian,
+    bincode::config::Fixint,
+> = bincode::config::legacy();
+
+#[cfg(all(test, feature = "serde"))]
+fn encode<T: serde::Serialize>(header: &T) -> Result<Vec<u8>, String> {
+    bincode::serde::encode_to_vec(header, BINCODE_CONFIG).map_err(|error| error.to_string())
+}
+
+#[cfg(all(test, feature = "serde"))]
+fn decode<T: serde::de::DeserializeOwned>(header: &[u8]) -> Result<T, String> {
+    bincode::serde::decode_from_slice(header, BINCODE_CONFIG)
+        .map(|(header, _)| header)
+        .map_err(|error| error.to_string())
+}
+
+/// Reads the fields of a serialized header one after the other. The caller checks that the header
+/// is long enough before reading any field.
+struct FieldReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> FieldReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        FieldReader { bytes }
+    }
+
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let (field, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+
+        let mut taken = [0; N];
+        taken.copy_from_slice(field);
+        taken
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.take::<1>()[0]
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+
+    fn identifier(&mut self) -> Identifier {
+        Identifier::from_le_bytes(self.take())
+    }
+}
+
+/// Fills `buffer` from `reader` unless `deadline` passes first, which is checked before every read. Reads
+/// that fail with `WouldBlock` or `TimedOut`, as those of a stream with a read timeout do, are retried until
+/// then. `consumed` is the number of bytes of the message that were read before `buffer`.
+/// # Errors
+/// `MessageError::Timeout` when `deadline` passes, `MessageError::UnexpectedEof` when the stream ends first
+/// and `MessageError::Io` when reading fails.
+#[cfg(feature = "std")]
+fn read_exact_until<R: std::io::Read>(
+    reader: &mut R,
+    buffer: &mut [u8],
+    consumed: usize,
+    deadline: Option<std::time::Instant>,
+) -> Result<(), MessageError> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        if deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+            log_debug!("timed out after reading {} bytes", consumed + filled);
+            return Err(MessageError::Timeout {
+                consumed: consumed + filled,
+            });
+        }
+
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => return Err(MessageError::UnexpectedEof),
+            Ok(read) => filled += read,
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    std::io::ErrorKind::Interrupted
+                        | std::io::ErrorKind::WouldBlock
+                        | std::io::ErrorKind::TimedOut
+                ) => {}
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    Ok(())
+}
+
+// `size_t` is `usize` on every target Rust supports, `libc` is only needed to spell it in `std` builds.
+#[cfg(feature = "std")]
+use libc::size_t;
+#[cfg(not(feature = "std"))]
+#[allow(non_camel_case_types)]
+type size_t = usize;
+
+/// Size in bytes of the length prefix in front of every structured entry. The prefix is a little-endian `u64`,
+/// independent of the size of `usize`, so that peers built for different pointer widths agree on the format.
+pub const LENGTH_PREFIX_SIZE: usize = 8;
+
+/// All errors that can be returned by the Rust side of this crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageError {
+    /// The provided buffer is shorter than what is needed to read from it.
+    BufferTooShort { needed: usize, got: usize },
+    /// The version stored in a header is not one of the format versions accepted by this crate.
+    VersionMismatch { expected: u8, found: u8 },
+    /// The discriminant does not belong to any `Algorithm`.
+    UnknownAlgorithm(u32),
+    /// The discriminant does not belong to any `Operation`.
+    UnknownOperation(u32),
+    /// The name does not belong to any `Algorithm`.
+    UnknownAlgorithmName(String),
+    /// The name does not belong to any `Operation`.
+    UnknownOperationName(String),
+    /// A length does not fit in the field that has to describe it.
+    LengthOverflow,
+    /// A length described in a header does not match the length of the data that belongs to it.
+    LengthMismatch { expected: usize, found: usize },
+    /// A header describes more data than the caller is willing to accept.
+    BodyTooLarge { max: u32, found: u32 },
+    /// A header describes a message, header and trailer included, of more bytes than the reader accepts, see
+    /// `request::MAX_MESSAGE_SIZE`.
+    MessageTooLarge { max: usize, found: usize },
+    /// A buffer holds more entries than the caller is willing to accept, see
+    /// `request::destructure_entries_bounded`.
+    TooManyEntries { max: usize },
+    /// A field of a header has a value that is not allowed.
+    InvalidField {
+        field: &'static str,
+        reason: &'static str,
+    },
+    /// A peer sent a header that no correct peer sends, e.g. a failure response that carries data (see
+    /// `response::ResponseHeader::validate`).
+    ProtocolViolation { reason: &'static str },
+    /// A header would be sent with `NoAlgorithm` or `NoOperation` (named by `field`), which only exist as
+    /// placeholders, e.g. for `RequestHeader::default()`.
+    Placeholder { field: &'static str },
+    /// The checksum trailer of a message does not match its contents.
+    ChecksumMismatch { expected: u32, found: u32 },
+    /// The authentication tag of a header does not match, so it was altered or made with another key.
+    AuthFailed,
+    /// The responder reported a failure with this status, see `response::ResponseStatus`.
+    RequestFailed(i8),
+    /// A stream ended in the middle of a message.
+    UnexpectedEof,
+    /// The step of `self_test::self_test` named by `step` did not give back what went in, or the test vector
+    /// named by `step` differs from the recorded one, see `self_test::verify_test_vectors`.
+    SelfTestFailed { step: &'static str },
+    /// Reading from or writing to a stream failed.
+    #[cfg(feature = "std")]
+    Io(std::io::ErrorKind),
+    /// A message did not arrive completely in time, after `consumed` bytes of it had been read from the
+    /// stream. See `request::read_request_timeout`.
+    #[cfg(feature = "std")]
+    Timeout { consumed: usize },
+    /// A header could not be read from JSON, see `json::request_header_from_json`.
+    #[cfg(feature = "json")]
+    InvalidJson(String),
+}
+
+impl core::fmt::Display for MessageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MessageError::BufferTooShort { needed, got } => {
+                write!(
+                    f,
+                    "buffer too short: needed {} bytes but got {}",
+                    needed, got
+                )
+            }
+            MessageError::VersionMismatch { expected, found } => write!(
+                f,
+                "format version mismatch: expected {} but found {}",
+                expected, found
+            ),
+            MessageError::UnknownAlgorithm(discriminant) => {
+                write!(f, "unknown algorithm with discriminant {}", discriminant)
+            }
+            MessageError::UnknownOperation(discriminant) => {
+                write!(f, "unknown operation with discriminant {}", discriminant)
+            }
+            MessageError::UnknownAlgorithmName(name) => write!(f, "unknown algorithm {}", name),
+            MessageError::UnknownOperationName(name) => write!(f, "unknown operation {}", name),
+            MessageError::LengthOverflow => write!(f, "length does not fit in its field"),
+            MessageError::LengthMismatch { expected, found } => write!(
+                f,
+                "length mismatch: header describes {} bytes but found {}",
+                expected, found
+            ),
+            MessageError::BodyTooLarge { max, found } => write!(
+                f,
+                "body too large: header describes {} bytes but at most {} are accepted",
+                found, max
+            ),
+            MessageError::MessageTooLarge { max, found } => write!(
+                f,
+                "message too large: header describes a message of {} bytes but at most {} are accepted",
+                found, max
+            ),
+            MessageError::TooManyEntries { max } => {
+                write!(f, "too many entries: at most {} are accepted", max)
+            }
+            MessageError::InvalidField { field, reason } => {
+                write!(f, "invalid value for field {}: {}", field, reason)
+            }
+            MessageError::ProtocolViolation { reason } => write!(f, "protocol violation: {}", reason),
+            MessageError::Placeholder { field } => {
+                write!(f, "field {} holds a placeholder and cannot be sent", field)
+            }
+            MessageError::ChecksumMismatch { expected, found } => write!(
+                f,
+                "checksum mismatch: expected {:#010x} but found {:#010x}",
+                expected, found
+            ),
+            MessageError::AuthFailed => write!(f, "authentication tag does not match"),
+            MessageError::RequestFailed(status) => {
+                write!(f, "request failed with status {}", status)
+            }
+            MessageError::UnexpectedEof => write!(f, "stream ended in the middle of a message"),
+            MessageError::SelfTestFailed { step } => write!(f, "self-test failed at step {}", step),
+            #[cfg(feature = "std")]
+            MessageError::Io(kind) => write!(f, "I/O error: {}", std::io::Error::from(*kind)),
+            #[cfg(feature = "std")]
+            MessageError::Timeout { consumed } => {
+                write!(f, "timed out after reading {} bytes of a message", consumed)
+            }
+            #[cfg(feature = "json")]
+            MessageError::InvalidJson(reason) => write!(f, "invalid JSON: {}", reason),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MessageError {}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for MessageError {
+    fn from(error: std::io::Error) -> Self {
+        MessageError::Io(error.kind())
+    }
+}
 
 /// Used to indicate that serialization failed.
-#[derive(Debug)]
-pub struct SerializationError;
+#[deprecated(note = "use `MessageError` instead")]
+pub type SerializationError = MessageError;
 /// Used to indicate that deserialization failed.
-#[derive(Debug)]
-pub struct DeserializationError;
+#[deprecated(note = "use `MessageError` instead")]
+pub type DeserializationError = MessageError;
 
 /// Used to indicate that destructuring failed.
-#[derive(Debug)]
-pub struct DestructureError;
+#[deprecated(note = "use `MessageError` instead")]
+pub type DestructureError = MessageError;
 
 /// This module contains everything one needs for sending and receiving request headers.
 pub mod request;
 /// This module contains everything one needs for sending and receiving response headers.
 pub mod response;
+/// This module contains helpers for clearing and comparing cryptographically sensitive data.
+pub mod secure;
+/// This module contains the offset of every field within the serialized headers, for implementations in other
+/// languages. All fields are little-endian, see the wire format in the README.
+pub mod layout;
+/// This module contains an in-memory stream that answers requests with a closure, for testing.
+#[cfg(feature = "test-util")]
+pub mod loopback;
+/// This module converts request headers to and from JSON, for debugging interoperability with other languages.
+#[cfg(feature = "json")]
+pub mod json;
+/// This module contains everything one needs for checksumming messages. Every message then consists of the
+/// serialized header, its data and a little-endian CRC32C trailer computed over both. The trailer is not part
+/// of the header so the header and `data_len` have the same meaning with and without this feature.
+/// Both sides of the IPC channel must agree on whether this feature is used.
+#[cfg(feature = "checksum")]
+pub mod checksum;
+/// This module contains the HMAC-SHA256 used to authenticate request headers, see
+/// `request::serialize_request_header_authenticated`. Only the header is authenticated, not the data after it.
+#[cfg(feature = "auth")]
+pub mod auth;
+/// This module contains a blocking `Channel` that sends requests over a stream and matches the responses
+/// coming back to them.
+#[cfg(feature = "std")]
+pub mod channel;
+/// This module contains async functions that read and write messages on Tokio streams.
+#[cfg(all(feature = "std", feature = "tokio"))]
+pub mod async_io;
+/// This module contains human-readable descriptions of serialized messages, for debugging.
+pub mod inspect;
+/// This module contains a self-test of the whole request and response flow, for a responder to run at startup.
+pub mod self_test;
 
 #[cfg(test)]
 mod tests {
+    // Tests with literal bytes or offsets assume the 8-byte identifier, the `uuid` feature moves every field
+    // after it. See `test_uuid_identifier` for that layout.
+
     // Emulates what the C side would do
+    #[cfg(not(feature = "uuid"))]
     #[test]
     fn test_request_header_c() {
         let header_size = crate::request::get_serialized_request_header_size();
@@ -46,19 +499,101 @@ mod tests {
                 1331,
                 crate::request::Algorithm::FRODO976__ECDHp384,
                 crate::request::Operation::Encapsulation,
+                1_600_000_000_000,
             );
-            assert!(status == 0);
+            assert_eq!(status, header_size as i64);
 
             let slice: &[u8] = &*std::ptr::slice_from_raw_parts(buffer, header_size as usize);
             assert_eq!(
                 slice,
-                vec![crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 51, 5, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0].as_slice()
+                vec![crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 51, 5, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0, 0, 128, 110, 135, 116, 1, 0, 0, 0].as_slice()
             );
 
             libc::free(buffer as *mut libc::c_void);
         }
     }
 
+    // Emulates what a responder written in C would do with what a C requester wrote
+    #[test]
+    fn test_deserialize_request_header_c() {
+        let body = [7; 5];
+        let mut request = vec![0; 128];
+        let written = unsafe {
+            crate::request::build_request(
+                request.as_mut_ptr(),
+                request.len(),
+                1234,
+                crate::request::Algorithm::KYBER_768,
+                crate::request::Operation::Encapsulation,
+                body.as_ptr(),
+                body.len(),
+            )
+        };
+        request.truncate(written as usize);
+
+        let mut request_header = crate::request::RequestHeader::default();
+        let status = unsafe {
+            crate::request::deserialize_request_header_c(
+                request.as_ptr(),
+                request.len(),
+                &mut request_header,
+            )
+        };
+        assert_eq!(status, 0);
+        assert!(request_header == crate::request::deserialize_request_header(&request).unwrap());
+        assert_eq!(request_header.data_len, 5);
+
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        let deserialize = |data: &[u8], request_header: &mut crate::request::RequestHeader| unsafe {
+            crate::request::deserialize_request_header_c(data.as_ptr(), data.len(), request_header)
+        };
+        assert_eq!(
+            deserialize(&request[..header_size - 1], &mut request_header),
+            -2
+        );
+        let status = unsafe {
+            crate::request::deserialize_request_header_c(std::ptr::null(), 0, &mut request_header)
+        };
+        assert_eq!(status, -1);
+        let status = unsafe {
+            crate::request::deserialize_request_header_c(
+                request.as_ptr(),
+                request.len(),
+                std::ptr::null_mut(),
+            )
+        };
+        assert_eq!(status, -1);
+
+        let mut unknown_algorithm = request.clone();
+        unknown_algorithm[crate::layout::REQUEST_ALGORITHM_OFFSET] = 0xFF;
+        assert_eq!(deserialize(&unknown_algorithm, &mut request_header), -3);
+
+        // The header is still set for a version that is not accepted
+        let mut newer_version = request.clone();
+        newer_version[crate::layout::REQUEST_VERSION_OFFSET] = crate::FORMAT_VERSION + 1;
+        assert_eq!(deserialize(&newer_version, &mut request_header), -4);
+        assert_eq!(request_header.version(), crate::FORMAT_VERSION + 1);
+
+        #[cfg(feature = "checksum")]
+        {
+            let mut corrupt = request.clone();
+            corrupt[header_size] ^= 1;
+            assert_eq!(deserialize(&corrupt, &mut request_header), -5);
+            assert_eq!(
+                deserialize(&request[..request.len() - 1], &mut request_header),
+                -2
+            );
+
+            // The largest data_len describes a message that does not fit in the buffer, on a 32-bit
+            // target not even in a usize
+            let mut largest_data_len = request.clone();
+            let offset = crate::layout::REQUEST_DATA_LEN_OFFSET;
+            largest_data_len[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            assert_eq!(deserialize(&largest_data_len, &mut request_header), -2);
+        }
+    }
+
+    #[cfg(not(feature = "uuid"))]
     #[test]
     fn test_request_header_rust() {
         let header_size = crate::request::get_serialized_request_header_size();
@@ -66,6 +601,7 @@ mod tests {
         // Pretend we've read these bytes from somewhere
         let buffer: Vec<u8> = vec![
             crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 51, 5, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0,
+            0, 128, 110, 135, 116, 1, 0, 0, 0,
         ];
         assert_eq!(header_size as usize, buffer.len());
 
@@ -78,10 +614,166 @@ mod tests {
                 data_len: 1331,
                 algorithm: crate::request::Algorithm::FRODO976__ECDHp384,
                 operation: crate::request::Operation::Encapsulation,
+                deadline_ms: 1_600_000_000_000,
+                flags: 0,
             };
         assert!(equal);
     }
 
+    #[test]
+    fn test_request_header_at_c() {
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        let offset = 7;
+        let mut buffer = vec![0xAA; offset + header_size + 4];
+        let end = unsafe {
+            crate::request::serialize_request_header_at(
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                offset,
+                1234,
+                4,
+                crate::request::Algorithm::KYBER_512,
+                crate::request::Operation::Decapsulation,
+                0,
+            )
+        };
+        assert_eq!(end, (offset + header_size) as i64);
+
+        let expected = crate::request::serialize_request_header_vec(
+            1234,
+            4,
+            crate::request::Algorithm::KYBER_512,
+            crate::request::Operation::Decapsulation,
+            0,
+        )
+        .unwrap();
+        assert_eq!(&buffer[offset..end as usize], &expected[..]);
+        assert_eq!(&buffer[..offset], &[0xAA; 7]);
+        assert_eq!(&buffer[end as usize..], &[0xAA; 4]);
+
+        let header = crate::request::deserialize_request_header(&buffer[offset..]).unwrap();
+        assert_eq!(header.identifier, 1234);
+        assert_eq!(header.data_len, 4);
+
+        for offset in [offset + 5, usize::MAX] {
+            let status = unsafe {
+                crate::request::serialize_request_header_at(
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                    offset,
+                    1234,
+                    4,
+                    crate::request::Algorithm::KYBER_512,
+                    crate::request::Operation::Decapsulation,
+                    0,
+                )
+            };
+            assert_eq!(status, -1);
+        }
+        let status = unsafe {
+            crate::request::serialize_request_header_at(
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                offset,
+                1234,
+                4,
+                crate::request::Algorithm::NoAlgorithm,
+                crate::request::Operation::Decapsulation,
+                0,
+            )
+        };
+        assert_eq!(status, -3);
+    }
+
+    // Emulates what the C side would do, against the individual functions
+    #[test]
+    fn test_build_request_c() {
+        let body = [0x5A; 12];
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        #[cfg(not(feature = "checksum"))]
+        let request_size = header_size + body.len();
+        #[cfg(feature = "checksum")]
+        let request_size = header_size + body.len() + crate::checksum::get_checksum_size();
+
+        let mut expected = vec![0; request_size];
+        unsafe {
+            let status = crate::request::serialize_request_header(
+                expected.as_mut_ptr(),
+                expected.len(),
+                1234,
+                body.len() as u32,
+                crate::request::Algorithm::KYBER_512,
+                crate::request::Operation::Encapsulation,
+                0,
+            );
+            assert_eq!(status, header_size as i64);
+            expected[header_size..header_size + body.len()].copy_from_slice(&body);
+            #[cfg(feature = "checksum")]
+            assert_eq!(
+                crate::checksum::write_checksum(expected.as_mut_ptr(), expected.len()),
+                0
+            );
+        }
+
+        let mut buffer = vec![0xAA; request_size + 3];
+        let written = unsafe {
+            crate::request::build_request(
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                1234,
+                crate::request::Algorithm::KYBER_512,
+                crate::request::Operation::Encapsulation,
+                body.as_ptr(),
+                body.len(),
+            )
+        };
+        assert_eq!(written, request_size as i64);
+        assert_eq!(&buffer[..request_size], &expected[..]);
+        assert_eq!(&buffer[request_size..], &[0xAA; 3]);
+        let request = crate::request::Request::from_bytes(&buffer[..request_size]).unwrap();
+        assert_eq!(&request.body[..], &body);
+
+        let build = |out_len: usize, algorithm, body: *const u8, body_len: usize| {
+            let mut out = vec![0xAA; out_len];
+            let status = unsafe {
+                crate::request::build_request(
+                    out.as_mut_ptr(),
+                    out.len(),
+                    1234,
+                    algorithm,
+                    crate::request::Operation::Encapsulation,
+                    body,
+                    body_len,
+                )
+            };
+            (status, out)
+        };
+        let (status, out) = build(
+            request_size - 1,
+            crate::request::Algorithm::KYBER_512,
+            body.as_ptr(),
+            12,
+        );
+        assert_eq!(status, -4);
+        assert!(out.iter().all(|&byte| byte == 0xAA));
+        let (status, _) = build(
+            request_size,
+            crate::request::Algorithm::KYBER_512,
+            core::ptr::null(),
+            12,
+        );
+        assert_eq!(status, -1);
+        let (status, _) = build(
+            request_size,
+            crate::request::Algorithm::NoAlgorithm,
+            body.as_ptr(),
+            12,
+        );
+        assert_eq!(status, -3);
+    }
+
+    // The checksum trailer changes the exact bytes, see `test_checksum_round_trip` for that case.
+    #[cfg(not(any(feature = "checksum", feature = "uuid")))]
     #[test]
     fn test_response_header_c() {
         let header_size = crate::response::get_serialized_response_header_size();
@@ -92,7 +784,7 @@ mod tests {
             assert!(!buffer.is_null());
 
             // Pretend we've read these bytes from somewhere
-            let header = vec![crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0];
+            let header = vec![crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0];
             std::ptr::copy_nonoverlapping(header.as_ptr(), buffer, header_size as usize);
 
             let mut response_header: crate::response::ResponseHeader = Default::default();
@@ -104,6 +796,7 @@ mod tests {
                     identifier: 1234,
                     success: 0,
                     data_len: 6,
+                    flags: 0,
                 };
             assert!(equal);
 
@@ -111,238 +804,5019 @@ mod tests {
         }
     }
 
+    #[cfg(not(any(feature = "checksum", feature = "uuid")))]
     #[test]
     fn test_response_header_rust() {
-        let response = crate::response::serialize_response(1234, Some(&vec![0, 1, 2, 3, 4, 5]));
+        let response = crate::response::serialize_response(1234, &[0, 1, 2, 3, 4, 5]);
         assert!(response.is_ok());
         assert_eq!(
             response.unwrap(),
-            vec![crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 1, 2, 3, 4, 5]
+            vec![crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5]
         );
     }
 
+    #[cfg(not(any(feature = "checksum", feature = "uuid")))]
     #[test]
     fn test_response_header_failure_rust() {
-        let response = crate::response::serialize_response(1234, None);
+        let response = crate::response::serialize_failure_response(
+            1234,
+            crate::response::ResponseStatus::OperationFailed,
+        );
         assert!(response.is_ok());
         assert_eq!(
             response.unwrap(),
             vec![
                 crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 255, // -1 as u8
-                0, 0, 0, 0,
+                0, 0, 0, 0, 0,
             ]
         );
     }
 
+    #[cfg(not(feature = "uuid"))]
     #[test]
-    fn test_serialize_request_header_failure_c() {
-        // Deliberately create a buffer that is too small
-        let header_size = crate::request::get_serialized_request_header_size() - 10;
-
-        unsafe {
-            let buffer: *mut libc::c_uchar =
-                libc::malloc(header_size as usize) as *mut libc::c_uchar;
-            assert!(!buffer.is_null());
+    fn test_response_status() {
+        use std::convert::TryFrom;
 
-            let status = crate::request::serialize_request_header(
-                buffer,
-                header_size as usize,
-                1234,
-                1331,
-                crate::request::Algorithm::FRODO976__ECDHp384,
-                crate::request::Operation::Encapsulation,
-            );
-            assert!(status == -1);
+        for status in [
+            crate::response::ResponseStatus::OperationFailed,
+            crate::response::ResponseStatus::UnsupportedAlgorithm,
+            crate::response::ResponseStatus::InvalidInput,
+            crate::response::ResponseStatus::InternalError,
+            crate::response::ResponseStatus::UnsupportedOperation,
+        ] {
+            let response = crate::response::serialize_failure_response(1234, status).unwrap();
+            assert_eq!(response[9], status as u8);
 
-            libc::free(buffer as *mut libc::c_void);
+            let header = crate::response::ResponseHeader::from_bytes(&response).unwrap();
+            assert_eq!(header.status(), Some(status));
+            assert_eq!(header.raw_status(), status as i8);
+            assert_eq!(header.data_len, 0);
         }
+
+        assert!(matches!(
+            crate::response::serialize_failure_response(1234, crate::response::ResponseStatus::Ok),
+            Err(crate::MessageError::InvalidField { .. })
+        ));
+
+        // Only the header, with the status byte at offset 9 and data_len 0
+        let response = crate::response::serialize_unsupported_algorithm_response(1234).unwrap();
+        assert_eq!(response[9], 254);
+        assert_eq!(response[10..14], [0, 0, 0, 0]);
+        let response = crate::response::serialize_unsupported_operation_response(1234).unwrap();
+        assert_eq!(response[9], 251);
+        assert_eq!(response[10..14], [0, 0, 0, 0]);
+        let (header, body) = crate::response::parse_response(&response).unwrap();
+        assert_eq!(
+            header.status(),
+            Some(crate::response::ResponseStatus::UnsupportedOperation)
+        );
+        assert!(body.is_empty());
+
+        // A code of a newer responder is still a failure
+        let header = crate::response::ResponseHeader {
+            success: -100,
+            ..Default::default()
+        };
+        assert_eq!(header.status(), None);
+        assert_eq!(header.raw_status(), -100);
+        assert_eq!(crate::response::ResponseStatus::try_from(-100), Err(-100));
     }
 
+    #[cfg(not(feature = "uuid"))]
     #[test]
-    fn test_deserialize_response_header_failure_c() {
-        let header_size = crate::response::get_serialized_response_header_size();
+    fn test_parse_response() {
+        let data = [0, 1, 2, 3, 4, 5];
+        let mut serialized = crate::response::serialize_response(1234, &data).unwrap();
+        let (header, body) = crate::response::parse_response(&serialized).unwrap();
+        assert_eq!(header.identifier, 1234);
+        assert_eq!(header.status(), Some(crate::response::ResponseStatus::Ok));
+        assert_eq!(header.data_len, 6);
+        assert_eq!(body, &data);
 
-        unsafe {
-            let buffer: *mut libc::c_uchar =
-                libc::malloc(header_size as usize) as *mut libc::c_uchar;
-            assert!(!buffer.is_null());
+        let failure = crate::response::serialize_failure_response(
+            1235,
+            crate::response::ResponseStatus::InvalidInput,
+        )
+        .unwrap();
+        let (header, body) = crate::response::parse_response(&failure).unwrap();
+        assert_eq!(header.identifier, 1235);
+        assert_eq!(
+            header.status(),
+            Some(crate::response::ResponseStatus::InvalidInput)
+        );
+        assert!(body.is_empty());
 
-            let mut response_header: crate::response::ResponseHeader = Default::default();
-            let status = crate::response::deserialize_response_header(
-                0 as *mut libc::c_uchar, // Pass in null pointer
-                &mut response_header,
-            );
-            assert!(status == -1);
+        // The next response in the buffer is left alone
+        let response_len = serialized.len();
+        serialized.extend(&failure);
+        let (header, body) = crate::response::parse_response(&serialized).unwrap();
+        assert_eq!(header.identifier, 1234);
+        assert_eq!(body, &data);
+        serialized.truncate(response_len);
 
-            // Pretend we've read these bytes from somewhere
-            let header = vec![
-                crate::FORMAT_VERSION + 1, // Deliberately get format version wrong
-                210,
-                4,
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-                0,
-                6,
-                0,
-                0,
-                0,
-            ];
-            std::ptr::copy_nonoverlapping(header.as_ptr(), buffer, header_size as usize);
-            let status = crate::response::deserialize_response_header(buffer, &mut response_header);
-            assert!(status == -4);
+        assert!(matches!(
+            crate::response::parse_response(&serialized[..response_len - 1]),
+            Err(crate::MessageError::BufferTooShort { needed, got })
+                if needed == response_len && got == response_len - 1
+        ));
+        assert!(matches!(
+            crate::response::parse_response(&serialized[..10]),
+            Err(crate::MessageError::BufferTooShort {
+                needed: 15,
+                got: 10
+            })
+        ));
 
-            libc::free(buffer as *mut libc::c_void);
-        }
+        // The largest data_len is cut off as well, the size of the response cannot wrap around
+        let header = crate::response::ResponseHeader::new(
+            1234,
+            crate::response::ResponseStatus::Ok,
+            u32::MAX,
+        )
+        .to_bytes();
+        assert!(matches!(
+            crate::response::parse_response(&header),
+            Err(crate::MessageError::BufferTooShort { got, .. }) if got == header.len()
+        ));
+
+        serialized[0] = crate::FORMAT_VERSION + 1;
+        assert!(matches!(
+            crate::response::parse_response(&serialized),
+            Err(crate::MessageError::VersionMismatch { .. })
+        ));
     }
 
     #[test]
-    fn test_structuring_entries_rust() {
-        let pub_key: Vec<u8> = vec![0, 1, 2, 4, 5, 6];
-        let priv_key: Vec<u8> = vec![12, 13, 14];
+    fn test_failure_response_with_data() {
+        let header = crate::response::ResponseHeader {
+            success: crate::response::ResponseStatus::OperationFailed as i8,
+            data_len: 3,
+            ..Default::default()
+        };
+        let mut response = header.to_bytes();
+        response.extend(&[1, 2, 3]);
+        #[cfg(feature = "checksum")]
+        crate::checksum::append_checksum(&mut response);
 
-        let structured_keys = crate::response::structure_two_entries(&pub_key, &priv_key);
+        let rejected = Some(crate::MessageError::ProtocolViolation {
+            reason: "a failure response carries no data",
+        });
+        assert_eq!(header.validate().err(), rejected);
+        assert_eq!(crate::response::parse_response(&response).err(), rejected);
         assert_eq!(
-            structured_keys,
-            vec![6, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 4, 5, 6, 3, 0, 0, 0, 0, 0, 0, 0, 12, 13, 14]
+            crate::response::deserialize_response_header_bounded(&response, 16).err(),
+            rejected
+        );
+        assert_eq!(
+            crate::response::ResponseReader::new(&response[..]).err(),
+            rejected
+        );
+
+        let mut deserialized = crate::response::ResponseHeader::default();
+        let status = unsafe {
+            crate::response::deserialize_response_header(response.as_ptr(), &mut deserialized)
+        };
+        assert_eq!(status, -7);
+        assert_eq!(deserialized, header);
+        assert_eq!(
+            header.validate().unwrap_err().to_string(),
+            "protocol violation: a failure response carries no data"
+        );
+
+        // Failures without data and successes with data are fine
+        let failure = crate::response::serialize_failure_response(
+            7,
+            crate::response::ResponseStatus::OperationFailed,
+        )
+        .unwrap();
+        assert!(crate::response::parse_response(&failure).is_ok());
+        assert!(
+            crate::response::ResponseHeader::new(7, crate::response::ResponseStatus::Ok, 3)
+                .validate()
+                .is_ok()
         );
     }
 
     #[test]
-    fn test_destructuring_two_entries_c() {
-        // Pretend this is the buffer we received in C
-        let mut keys: Vec<u8> = vec![
-            6, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 4, 5, 6, 3, 0, 0, 0, 0, 0, 0, 0, 12, 13, 14,
-        ];
-        let keys_buffer_len = keys.len();
+    fn test_response_with_error() {
+        let response = crate::response::serialize_response_with_error(
+            7,
+            crate::response::ResponseStatus::UnsupportedAlgorithm,
+            "FRODO1344 not compiled in",
+        )
+        .unwrap();
+        let view = crate::response::ResponseView::from_bytes(&response).unwrap();
+        assert_eq!(
+            view.header().status(),
+            Some(crate::response::ResponseStatus::UnsupportedAlgorithm)
+        );
+        assert!(view.header().is_diagnostic());
+        assert_eq!(view.error_message(), Some("FRODO1344 not compiled in"));
 
-        let mut pub_key_length: libc::size_t = 0;
-        let mut priv_key_length: libc::size_t = 0;
+        // Neither result data nor a failure without a message has one
+        let response = crate::response::serialize_response(7, &[1, 2, 3]).unwrap();
+        let view = crate::response::ResponseView::from_bytes(&response).unwrap();
+        assert_eq!(view.error_message(), None);
+        let response = crate::response::serialize_failure_response(
+            7,
+            crate::response::ResponseStatus::OperationFailed,
+        )
+        .unwrap();
+        let view = crate::response::ResponseView::from_bytes(&response).unwrap();
+        assert_eq!(view.error_message(), None);
 
-        let mut pub_key: *const libc::c_uchar = std::ptr::null();
-        let mut priv_key: *const libc::c_uchar = std::ptr::null();
+        assert!(crate::response::serialize_response_with_error(
+            7,
+            crate::response::ResponseStatus::Ok,
+            "fine"
+        )
+        .is_err());
+        let mut header =
+            crate::response::ResponseHeader::new(7, crate::response::ResponseStatus::Ok, 4);
+        header.flags = crate::response::RESPONSE_FLAG_DIAGNOSTIC;
+        assert_eq!(
+            header.validate().err(),
+            Some(crate::MessageError::InvalidField {
+                field: "flags",
+                reason: "only a failure response carries a diagnostic message",
+            })
+        );
+    }
 
-        unsafe {
-            let status = crate::response::destructure_two_entries(
-                keys.as_ptr(),
-                keys_buffer_len,
-                &mut pub_key_length,
-                &mut priv_key_length,
-                &mut pub_key,
-                &mut priv_key,
-            );
-            assert_eq!(status, 0);
-        }
+    #[test]
+    fn test_serialize_response_into() {
+        let data: Vec<u8> = (0..200).map(|i| (i % 7) as u8).collect();
+        let mut buf = [0xEE; 512];
 
-        assert_eq!(pub_key_length, 6);
-        assert_eq!(priv_key_length, 3);
+        let expected = crate::response::serialize_response(42, &data).unwrap();
+        let written = crate::response::serialize_response_into(&mut buf, 42, Ok(&data)).unwrap();
+        assert_eq!(&buf[..written], &expected[..]);
+        assert!(buf[written..].iter().all(|&byte| byte == 0xEE));
+
+        let expected = crate::response::serialize_failure_response(
+            42,
+            crate::response::ResponseStatus::OperationFailed,
+        )
+        .unwrap();
+        let written = crate::response::serialize_response_into(
+            &mut buf,
+            42,
+            Err(crate::response::ResponseStatus::OperationFailed),
+        )
+        .unwrap();
+        assert_eq!(&buf[..written], &expected[..]);
+
+        // Nothing is written when the response does not fit
+        let expected = crate::response::serialize_response(42, &data).unwrap();
+        let mut short = vec![0xEE; expected.len() - 1];
         assert_eq!(
-            unsafe { std::slice::from_raw_parts(pub_key, pub_key_length) },
-            vec![0, 1, 2, 4, 5, 6].as_slice()
+            crate::response::serialize_response_into(&mut short, 42, Ok(&data)).err(),
+            Some(crate::MessageError::BufferTooShort {
+                needed: expected.len(),
+                got: expected.len() - 1
+            })
+        );
+        assert!(short.iter().all(|&byte| byte == 0xEE));
+        assert!(crate::response::serialize_response_into(
+            &mut buf,
+            42,
+            Err(crate::response::ResponseStatus::Ok)
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_response_view() {
+        let mut response = crate::response::serialize_response(1234, &[1, 2, 3, 4, 5]).unwrap();
+        // The next response in the same buffer is not part of the view
+        response.extend(crate::response::serialize_response(1235, &[6]).unwrap());
+
+        let view = crate::response::ResponseView::from_bytes(&response).unwrap();
+        assert_eq!(view.header().identifier, 1234);
+        assert_eq!(view.body().len(), view.header().data_len as usize);
+        assert_eq!(view.body(), &[1, 2, 3, 4, 5]);
+        let header_size = crate::response::get_serialized_response_header_size() as usize;
+        assert!(core::ptr::eq(
+            view.body().as_ptr(),
+            response[header_size..].as_ptr()
+        ));
+
+        assert!(matches!(
+            crate::response::ResponseView::from_bytes(&response[..header_size + 2]),
+            Err(crate::MessageError::BufferTooShort { .. })
+        ));
+    }
+
+    #[test]
+    fn test_validate_body_len() {
+        let header = crate::request::RequestHeader::builder()
+            .identifier(1234)
+            .data_len(4)
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::Decapsulation)
+            .build()
+            .unwrap();
+        assert!(crate::request::validate_body_len(&header, &[1, 2, 3, 4]).is_ok());
+        assert_eq!(
+            crate::request::validate_body_len(&header, &[1, 2, 3]),
+            Err(crate::MessageError::LengthMismatch {
+                expected: 4,
+                found: 3
+            })
         );
         assert_eq!(
-            unsafe { std::slice::from_raw_parts(priv_key, priv_key_length) },
-            vec![12, 13, 14].as_slice()
+            crate::request::validate_body_len(&header, &[1, 2, 3, 4, 5]),
+            Err(crate::MessageError::LengthMismatch {
+                expected: 4,
+                found: 5
+            })
         );
 
-        keys[0] = 255;
-        unsafe {
-            let status = crate::response::destructure_two_entries(
-                keys.as_ptr(),
-                keys_buffer_len,
-                &mut pub_key_length,
-                &mut priv_key_length,
-                &mut pub_key,
-                &mut priv_key,
+        let response_header = crate::response::ResponseHeader {
+            data_len: 4,
+            ..Default::default()
+        };
+        assert!(crate::response::validate_body_len(&response_header, &[1, 2, 3, 4]).is_ok());
+        assert_eq!(
+            crate::response::validate_body_len(&response_header, &[]),
+            Err(crate::MessageError::LengthMismatch {
+                expected: 4,
+                found: 0
+            })
+        );
+        assert_eq!(
+            crate::response::validate_body_len(&response_header, &[0; 8]),
+            Err(crate::MessageError::LengthMismatch {
+                expected: 4,
+                found: 8
+            })
+        );
+
+        // A request whose body runs into the next frame is not read past its data_len
+        let request = crate::request::Request {
+            header,
+            body: vec![1, 2, 3, 4].into(),
+        };
+        let mut serialized = request.to_bytes().unwrap();
+        let request_len = serialized.len();
+        serialized.push(5);
+        assert_eq!(
+            crate::request::Request::from_bytes(&serialized).err(),
+            Some(crate::MessageError::LengthMismatch {
+                expected: request_len,
+                found: request_len + 1
+            })
+        );
+        assert!(matches!(
+            crate::request::Request::from_bytes(&serialized[..request_len - 1]),
+            Err(crate::MessageError::BufferTooShort { .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_request() {
+        let header = crate::request::RequestHeader::builder()
+            .identifier(1234)
+            .data_len(4)
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::Decapsulation)
+            .build()
+            .unwrap();
+        let request = crate::request::Request {
+            header,
+            body: vec![1, 2, 3, 4].into(),
+        };
+        let serialized = request.to_bytes().unwrap();
+
+        // Two requests back-to-back are read one at a time
+        let mut stream = serialized.repeat(2);
+        let mut reader = std::io::Cursor::new(&mut stream);
+        for _ in 0..2 {
+            let read = crate::request::read_request(&mut reader).unwrap();
+            assert!(read.header == request.header);
+            assert_eq!(*read.body, [1, 2, 3, 4]);
+        }
+        assert_eq!(
+            crate::request::read_request(&mut reader).err(),
+            Some(crate::MessageError::UnexpectedEof)
+        );
+
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        let mut reader = std::io::Cursor::new(&serialized[..header_size - 1]);
+        assert_eq!(
+            crate::request::read_request(&mut reader).err(),
+            Some(crate::MessageError::UnexpectedEof)
+        );
+
+        let mut reader = std::io::Cursor::new(&serialized[..header_size + 3]);
+        assert_eq!(
+            crate::request::read_request(&mut reader).err(),
+            Some(crate::MessageError::LengthMismatch {
+                expected: 4,
+                found: 3
+            })
+        );
+
+        let mut serialized = serialized;
+        serialized[0] = crate::FORMAT_VERSION + 1;
+        let mut reader = std::io::Cursor::new(&serialized);
+        assert!(matches!(
+            crate::request::read_request(&mut reader),
+            Err(crate::MessageError::VersionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        // Hands out its bytes a few at a time, then behaves like a socket with a read timeout that sees
+        // no more data
+        struct Trickle<'a> {
+            data: &'a [u8],
+        }
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                if self.data.is_empty() {
+                    std::thread::sleep(std::time::Duration::from_millis(1));
+                    return Err(std::io::ErrorKind::WouldBlock.into());
+                }
+                let len = buf.len().min(self.data.len()).min(3);
+                buf[..len].copy_from_slice(&self.data[..len]);
+                self.data = &self.data[len..];
+                Ok(len)
+            }
+        }
+
+        let timeout = std::time::Duration::from_millis(20);
+        let header = crate::request::RequestHeader::builder()
+            .identifier(1234)
+            .data_len(4)
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::Decapsulation)
+            .build()
+            .unwrap();
+        let request = crate::request::Request {
+            header,
+            body: vec![1, 2, 3, 4].into(),
+        };
+        let serialized = request.to_bytes().unwrap();
+
+        let mut reader = Trickle { data: &serialized };
+        let read = crate::request::read_request_timeout(&mut reader, timeout).unwrap();
+        assert!(read.header == request.header);
+        assert_eq!(*read.body, [1, 2, 3, 4]);
+
+        let mut reader = Trickle { data: &[] };
+        assert_eq!(
+            crate::request::read_request_timeout(&mut reader, timeout).err(),
+            Some(crate::MessageError::Timeout { consumed: 0 })
+        );
+        let mut reader = Trickle {
+            data: &serialized[..5],
+        };
+        assert_eq!(
+            crate::request::read_request_timeout(&mut reader, timeout).err(),
+            Some(crate::MessageError::Timeout { consumed: 5 })
+        );
+        let mut reader = Trickle {
+            data: &serialized[..serialized.len() - 1],
+        };
+        assert_eq!(
+            crate::request::read_request_timeout(&mut reader, timeout).err(),
+            Some(crate::MessageError::Timeout {
+                consumed: serialized.len() - 1
+            })
+        );
+        // The end of the stream is not a timeout
+        let mut reader = std::io::Cursor::new(&serialized[..5]);
+        assert_eq!(
+            crate::request::read_request_timeout(&mut reader, timeout).err(),
+            Some(crate::MessageError::UnexpectedEof)
+        );
+
+        let response = crate::response::serialize_response(1234, &[5, 6, 7]).unwrap();
+        let mut reader = Trickle { data: &response };
+        let (response_header, data) =
+            crate::response::read_response_timeout(&mut reader, timeout).unwrap();
+        assert_eq!(response_header.identifier, 1234);
+        assert_eq!(data, [5, 6, 7]);
+
+        let mut reader = Trickle {
+            data: &response[..response.len() - 1],
+        };
+        assert_eq!(
+            crate::response::read_response_timeout(&mut reader, timeout).err(),
+            Some(crate::MessageError::Timeout {
+                consumed: response.len() - 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_frame_decoder() {
+        let mut writer = crate::request::RequestStreamWriter::new();
+        for (identifier, body) in [(1, vec![1, 2, 3, 4]), (2, vec![]), (3, vec![5; 300])] {
+            let header = crate::request::RequestHeader::builder()
+                .identifier(identifier)
+                .data_len(body.len() as u32)
+                .algorithm(crate::request::Algorithm::KYBER_512)
+                .operation(crate::request::Operation::Decapsulation)
+                .build()
+                .unwrap();
+            writer.push(&header, &body).unwrap();
+        }
+        let stream = writer.into_bytes();
+
+        // One byte at a time, so the headers and the data are split across many pushes
+        let mut decoder = crate::request::FrameDecoder::new();
+        let mut requests = Vec::new();
+        for byte in &stream {
+            if let Some(request) = decoder.push(&[*byte]).unwrap() {
+                requests.push(request);
+            }
+        }
+        assert_eq!(requests.len(), 3);
+        assert_eq!(decoder.buffered(), 0);
+        let identifiers: Vec<_> = requests.iter().map(|r| r.header.identifier).collect();
+        assert_eq!(identifiers, [1, 2, 3]);
+        assert_eq!(*requests[0].body, [1, 2, 3, 4]);
+        assert!(requests[1].body.is_empty());
+        assert_eq!(*requests[2].body, vec![5; 300]);
+
+        // A header split in two, after which everything else arrives at once
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        let mut decoder = crate::request::FrameDecoder::new();
+        assert!(decoder.push(&stream[..10]).unwrap().is_none());
+        assert!(decoder.push(&stream[10..header_size]).unwrap().is_none());
+        assert_eq!(decoder.buffered(), header_size);
+        let first = decoder.push(&stream[header_size..]).unwrap().unwrap();
+        assert_eq!(first.header.identifier, 1);
+        let second = decoder.next_request().unwrap().unwrap();
+        assert_eq!(second.header.identifier, 2);
+        let third = decoder.next_request().unwrap().unwrap();
+        assert_eq!(third.header.identifier, 3);
+        assert!(decoder.next_request().unwrap().is_none());
+
+        // Errors discard what was buffered
+        let mut decoder = crate::request::FrameDecoder::new();
+        decoder.set_max_data_len(100);
+        assert!(decoder.push(&stream[..stream.len() - 1]).unwrap().is_some());
+        assert!(decoder.next_request().unwrap().is_some());
+        assert_eq!(
+            decoder.next_request().err(),
+            Some(crate::MessageError::BodyTooLarge {
+                max: 100,
+                found: 300
+            })
+        );
+        assert_eq!(decoder.buffered(), 0);
+
+        let mut corrupted = stream.clone();
+        corrupted[0] = crate::FORMAT_VERSION + 1;
+        let mut decoder = crate::request::FrameDecoder::new();
+        assert!(matches!(
+            decoder.push(&corrupted),
+            Err(crate::MessageError::VersionMismatch { .. })
+        ));
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn test_max_message_size() {
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        #[cfg(not(feature = "checksum"))]
+        let trailer_size = 0;
+        #[cfg(feature = "checksum")]
+        let trailer_size = crate::checksum::CHECKSUM_SIZE;
+        assert_eq!(
+            crate::request::MAX_MESSAGE_SIZE,
+            header_size + crate::request::RECOMMENDED_MAX_DATA_LEN as usize + trailer_size
+        );
+        assert!(
+            crate::request::MAX_MESSAGE_SIZE
+                >= crate::response::get_serialized_response_header_size() as usize
+                    + crate::request::RECOMMENDED_MAX_DATA_LEN as usize
+                    + trailer_size
+        );
+
+        let request = |data_len: usize| {
+            let header = crate::request::RequestHeader::builder()
+                .identifier(1)
+                .data_len(data_len as u32)
+                .algorithm(crate::request::Algorithm::FRODO1344__ECDHp521)
+                .operation(crate::request::Operation::Decapsulation)
+                .build()
+                .unwrap();
+            let mut writer = crate::request::RequestStreamWriter::new();
+            writer.push(&header, &vec![7; data_len]).unwrap();
+            writer.into_bytes()
+        };
+
+        // The largest request accepted by default is exactly MAX_MESSAGE_SIZE bytes
+        let largest = request(crate::request::RECOMMENDED_MAX_DATA_LEN as usize);
+        assert_eq!(largest.len(), crate::request::MAX_MESSAGE_SIZE);
+        let mut decoder = crate::request::FrameDecoder::new();
+        assert!(decoder.push(&largest).unwrap().is_some());
+
+        // With a larger max_data_len the message size still caps the request
+        let mut decoder = crate::request::FrameDecoder::new();
+        decoder.set_max_data_len(u32::MAX);
+        let too_large = request(crate::request::RECOMMENDED_MAX_DATA_LEN as usize + 1);
+        assert_eq!(
+            decoder.push(&too_large[..header_size]).err(),
+            Some(crate::MessageError::MessageTooLarge {
+                max: crate::request::MAX_MESSAGE_SIZE,
+                found: crate::request::MAX_MESSAGE_SIZE + 1
+            })
+        );
+        assert_eq!(decoder.buffered(), 0);
+
+        // The cap can be moved either way
+        let mut decoder = crate::request::FrameDecoder::new();
+        decoder.set_max_data_len(u32::MAX);
+        decoder.set_max_message_size(crate::request::MAX_MESSAGE_SIZE + 1);
+        assert!(decoder.push(&too_large).unwrap().is_some());
+        let small = request(10);
+        let mut decoder = crate::request::FrameDecoder::new();
+        decoder.set_max_message_size(small.len() - 1);
+        assert_eq!(
+            decoder.push(&small).err(),
+            Some(crate::MessageError::MessageTooLarge {
+                max: small.len() - 1,
+                found: small.len()
+            })
+        );
+
+        // The blocking readers apply the same cap, before reading the data
+        let timeout = std::time::Duration::from_secs(1);
+        let default_too_large = Some(crate::MessageError::MessageTooLarge {
+            max: crate::request::MAX_MESSAGE_SIZE,
+            found: crate::request::MAX_MESSAGE_SIZE + 1,
+        });
+        let small_too_large = Some(crate::MessageError::MessageTooLarge {
+            max: small.len() - 1,
+            found: small.len(),
+        });
+        assert!(crate::request::read_request(&mut &largest[..]).is_ok());
+        assert_eq!(
+            crate::request::read_request(&mut &too_large[..header_size]).err(),
+            default_too_large
+        );
+        assert!(crate::request::read_request_bounded(&mut &small[..], small.len()).is_ok());
+        assert_eq!(
+            crate::request::read_request_bounded(&mut &small[..], small.len() - 1).err(),
+            small_too_large
+        );
+        assert!(crate::request::read_request_timeout(&mut &largest[..], timeout).is_ok());
+        assert_eq!(
+            crate::request::read_request_timeout(&mut &too_large[..header_size], timeout).err(),
+            default_too_large
+        );
+        let read = crate::request::read_request_timeout_bounded(
+            &mut &too_large[..],
+            timeout,
+            too_large.len(),
+        );
+        assert!(read.is_ok());
+        assert_eq!(
+            crate::request::read_request_timeout_bounded(&mut &small[..], timeout, small.len() - 1)
+                .err(),
+            small_too_large
+        );
+
+        let response = crate::response::serialize_response(1, &[7; 10]).unwrap();
+        let read = crate::response::read_response_timeout_bounded(
+            &mut &response[..],
+            timeout,
+            response.len(),
+        );
+        assert!(read.is_ok());
+        assert_eq!(
+            crate::response::read_response_timeout_bounded(
+                &mut &response[..],
+                timeout,
+                response.len() - 1
+            )
+            .err(),
+            Some(crate::MessageError::MessageTooLarge {
+                max: response.len() - 1,
+                found: response.len()
+            })
+        );
+        let response_header =
+            crate::response::ResponseHeader::new(1, crate::response::ResponseStatus::Ok, u32::MAX)
+                .to_bytes();
+        assert!(matches!(
+            crate::response::read_response_timeout(&mut &response_header[..], timeout),
+            Err(crate::MessageError::MessageTooLarge {
+                max: crate::request::MAX_MESSAGE_SIZE,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_self_test() {
+        assert_eq!(crate::self_test::self_test(), Ok(()));
+        assert_eq!(crate::self_test::pq_message_self_test(), 0);
+
+        assert_eq!(
+            crate::MessageError::SelfTestFailed { step: "entries" }.to_string(),
+            "self-test failed at step entries"
+        );
+    }
+
+    #[cfg(not(feature = "uuid"))]
+    #[test]
+    fn test_test_vectors() {
+        use std::convert::TryFrom;
+
+        let path = std::path::Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/test_vectors.hex"));
+        assert_eq!(crate::self_test::verify_test_vectors(path), Ok(()));
+
+        // Every operation has a request header among the vectors, which this crate would send as they are
+        let requests: Vec<crate::request::RequestHeader> = crate::self_test::test_vectors()
+            .iter()
+            .filter(|(name, _)| name.starts_with("request_"))
+            .map(|(_, bytes)| crate::request::RequestHeader::from_bytes(bytes).unwrap())
+            .collect();
+        for discriminant in 1..crate::request::OPERATION_COUNT {
+            let operation = crate::request::Operation::try_from(discriminant).unwrap();
+            assert!(requests.iter().any(|header| header.operation == operation));
+        }
+        for header in requests.iter() {
+            assert_eq!(header.validate(), Ok(()), "{}", header.operation);
+        }
+
+        // and every reader of this crate accepts with its default limit
+        let responses = crate::self_test::test_vectors()
+            .into_iter()
+            .filter(|(name, _)| name.starts_with("response_"))
+            .map(|(_, bytes)| crate::response::ResponseHeader::from_bytes(&bytes).unwrap());
+        let request_header_size = crate::request::get_serialized_request_header_size() as usize;
+        let response_header_size = crate::response::get_serialized_response_header_size() as usize;
+        let message_sizes = requests
+            .iter()
+            .map(|header| (request_header_size, header.data_len))
+            .chain(responses.map(|header| (response_header_size, header.data_len)));
+        for (header_size, data_len) in message_sizes {
+            let max = crate::request::MAX_MESSAGE_SIZE;
+            assert!(crate::check_message_size(header_size, data_len, max).is_ok());
+        }
+
+        // A single changed byte, a missing vector and an unknown one are all caught
+        let recorded = std::fs::read_to_string(path).unwrap();
+        let copy = std::env::temp_dir().join(format!("test_vectors_{}.hex", std::process::id()));
+        let check = |contents: &str| {
+            std::fs::write(&copy, contents).unwrap();
+            crate::self_test::verify_test_vectors(&copy).err()
+        };
+        assert_eq!(
+            check(&recorded.replace("a7fd0000", "a8fd0000")),
+            Some(crate::MessageError::SelfTestFailed {
+                step: "request_decapsulation"
+            })
+        );
+        let without_cancel: String = recorded
+            .lines()
+            .filter(|line| !line.starts_with("request_cancel"))
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(
+            check(&without_cancel),
+            Some(crate::MessageError::SelfTestFailed {
+                step: "request_cancel"
+            })
+        );
+        assert_eq!(
+            check(&format!("{}request_unknown 09\n", recorded)),
+            Some(crate::MessageError::SelfTestFailed {
+                step: "test vectors"
+            })
+        );
+        assert_eq!(
+            check(&format!("{}entries_two 0\n", recorded)),
+            Some(crate::MessageError::SelfTestFailed {
+                step: "test vectors"
+            })
+        );
+        assert_eq!(
+            check(&recorded.replace("entries_two 03", "entries_two +3")),
+            Some(crate::MessageError::SelfTestFailed {
+                step: "test vectors"
+            })
+        );
+        std::fs::remove_file(&copy).unwrap();
+        assert_eq!(
+            crate::self_test::verify_test_vectors(&copy),
+            Err(crate::MessageError::Io(std::io::ErrorKind::NotFound))
+        );
+    }
+
+    #[test]
+    fn test_serialize_request_header_failure_c() {
+        // Deliberately create a buffer that is too small
+        let header_size = crate::request::get_serialized_request_header_size() - 10;
+
+        unsafe {
+            let buffer: *mut libc::c_uchar =
+                libc::malloc(header_size as usize) as *mut libc::c_uchar;
+            assert!(!buffer.is_null());
+
+            let status = crate::request::serialize_request_header(
+                buffer,
+                header_size as usize,
+                1234,
+                1331,
+                crate::request::Algorithm::FRODO976__ECDHp384,
+                crate::request::Operation::Encapsulation,
+                0,
+            );
+            assert!(status == -1);
+
+            libc::free(buffer as *mut libc::c_void);
+        }
+
+        // Right at the boundary
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        let mut buffer = vec![0xAA; header_size];
+        for (len, expected) in [
+            (0, -1),
+            (header_size - 1, -1),
+            (header_size, header_size as i64),
+        ] {
+            let status = unsafe {
+                crate::request::serialize_request_header(
+                    buffer.as_mut_ptr(),
+                    len,
+                    1234,
+                    1331,
+                    crate::request::Algorithm::FRODO976__ECDHp384,
+                    crate::request::Operation::Encapsulation,
+                    0,
+                )
+            };
+            assert_eq!(status, expected, "buffer of {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_response_header_failure_c() {
+        let header_size = crate::response::get_serialized_response_header_size();
+
+        unsafe {
+            let buffer: *mut libc::c_uchar =
+                libc::malloc(header_size as usize) as *mut libc::c_uchar;
+            assert!(!buffer.is_null());
+
+            let mut response_header: crate::response::ResponseHeader = Default::default();
+            let status = crate::response::deserialize_response_header(
+                std::ptr::null_mut(), // Pass in null pointer
+                &mut response_header,
+            );
+            assert!(status == -1);
+
+            // Pretend we've read these bytes from somewhere
+            let header = vec![
+                crate::FORMAT_VERSION + 1, // Deliberately get format version wrong
+                210,
+                4,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                0,
+                6,
+                0,
+                0,
+                0,
+            ];
+            std::ptr::copy_nonoverlapping(header.as_ptr(), buffer, header_size as usize);
+            let status = crate::response::deserialize_response_header(buffer, &mut response_header);
+            assert!(status == -4);
+
+            libc::free(buffer as *mut libc::c_void);
+        }
+    }
+
+    // Emulates what the C side would do
+    #[test]
+    fn test_entry_prefix_c() {
+        assert_eq!(crate::request::entry_prefix_size(), 8);
+
+        let mut buffer = [0xAA; 10];
+        for &len in [0, 300, u64::from(u32::MAX) + 1, u64::MAX].iter() {
+            let mut read = 0;
+            unsafe {
+                assert_eq!(
+                    crate::request::write_entry_prefix(buffer.as_mut_ptr(), buffer.len(), len),
+                    8
+                );
+                assert_eq!(
+                    crate::request::read_entry_prefix(buffer.as_ptr(), buffer.len(), &mut read),
+                    8
+                );
+            }
+            assert_eq!(read, len);
+            assert_eq!(&buffer[8..], &[0xAA; 2]);
+        }
+
+        // The same framing as structure_two_entries
+        let structured = crate::response::structure_two_entries(&[1, 2, 3], &[]);
+        unsafe {
+            crate::request::write_entry_prefix(buffer.as_mut_ptr(), buffer.len(), 3);
+        }
+        assert_eq!(&buffer[..8], &structured[..8]);
+
+        let mut read = 7;
+        unsafe {
+            assert_eq!(
+                crate::request::write_entry_prefix(std::ptr::null_mut(), 8, 3),
+                -1
+            );
+            assert_eq!(
+                crate::request::write_entry_prefix(buffer.as_mut_ptr(), 7, 3),
+                -2
+            );
+            assert_eq!(
+                crate::request::read_entry_prefix(buffer.as_ptr(), 8, std::ptr::null_mut()),
+                -1
+            );
+            assert_eq!(
+                crate::request::read_entry_prefix(buffer.as_ptr(), 7, &mut read),
+                -2
+            );
+        }
+        assert_eq!(read, 7);
+    }
+
+    #[test]
+    fn test_structuring_entries_rust() {
+        let pub_key: Vec<u8> = vec![0, 1, 2, 4, 5, 6];
+        let priv_key: Vec<u8> = vec![12, 13, 14];
+
+        let structured_keys = crate::response::structure_two_entries(&pub_key, &priv_key);
+        assert_eq!(
+            structured_keys,
+            vec![6, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 4, 5, 6, 3, 0, 0, 0, 0, 0, 0, 0, 12, 13, 14]
+        );
+    }
+
+    #[test]
+    fn test_destructuring_two_entries_c() {
+        // Pretend this is the buffer we received in C
+        let mut keys: Vec<u8> = vec![
+            6, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 4, 5, 6, 3, 0, 0, 0, 0, 0, 0, 0, 12, 13, 14,
+        ];
+        let keys_buffer_len = keys.len();
+
+        let mut pub_key_length: libc::size_t = 0;
+        let mut priv_key_length: libc::size_t = 0;
+
+        let mut pub_key: *const libc::c_uchar = std::ptr::null();
+        let mut priv_key: *const libc::c_uchar = std::ptr::null();
+
+        unsafe {
+            let status = crate::response::destructure_two_entries(
+                keys.as_ptr(),
+                keys_buffer_len,
+                &mut pub_key_length,
+                &mut priv_key_length,
+                &mut pub_key,
+                &mut priv_key,
+            );
+            assert_eq!(status, 0);
+        }
+
+        assert_eq!(pub_key_length, 6);
+        assert_eq!(priv_key_length, 3);
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(pub_key, pub_key_length) },
+            vec![0, 1, 2, 4, 5, 6].as_slice()
+        );
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(priv_key, priv_key_length) },
+            vec![12, 13, 14].as_slice()
+        );
+
+        keys[0] = 255;
+        unsafe {
+            let status = crate::response::destructure_two_entries(
+                keys.as_ptr(),
+                keys_buffer_len,
+                &mut pub_key_length,
+                &mut priv_key_length,
+                &mut pub_key,
+                &mut priv_key,
+            );
+            assert_ne!(status, 0);
+        }
+        keys[0] = 6;
+
+        keys[14] = 255;
+        unsafe {
+            let status = crate::response::destructure_two_entries(
+                keys.as_ptr(),
+                keys_buffer_len,
+                &mut pub_key_length,
+                &mut priv_key_length,
+                &mut pub_key,
+                &mut priv_key,
+            );
+            assert_ne!(status, 0);
+        }
+
+        let buffer: [u8; 0] = [];
+        unsafe {
+            let status = crate::response::destructure_two_entries(
+                buffer.as_ptr(),
+                buffer.len(),
+                &mut pub_key_length,
+                &mut priv_key_length,
+                &mut pub_key,
+                &mut priv_key,
+            );
+            assert_ne!(status, 0);
+        }
+    }
+
+    #[test]
+    fn test_destructuring_two_entries_overflow_c() {
+        fn destructure(data: &[u8]) -> i16 {
+            let mut entry1_length: libc::size_t = 0;
+            let mut entry2_length: libc::size_t = 0;
+            let mut entry1: *const libc::c_uchar = std::ptr::null();
+            let mut entry2: *const libc::c_uchar = std::ptr::null();
+
+            unsafe {
+                crate::response::destructure_two_entries(
+                    data.as_ptr(),
+                    data.len(),
+                    &mut entry1_length,
+                    &mut entry2_length,
+                    &mut entry1,
+                    &mut entry2,
+                )
+            }
+        }
+
+        // Lengths that wrap around when the prefix or the other entry is added to them
+        let valid_entry = [3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3];
+        for length in [
+            usize::MAX,
+            usize::MAX - crate::LENGTH_PREFIX_SIZE + 1,
+            usize::MAX - 2 * crate::LENGTH_PREFIX_SIZE - 2,
+            usize::MAX / 2 + 1,
+        ] {
+            let mut data = (length as u64).to_le_bytes().to_vec();
+            data.extend(&valid_entry);
+            assert_eq!(destructure(&data), -8);
+
+            let mut data = valid_entry.to_vec();
+            data.extend(&(length as u64).to_le_bytes());
+            data.extend(&[1, 2, 3]);
+            assert_eq!(destructure(&data), -8);
+        }
+
+        // Lengths that do not even fit in a size_t
+        #[cfg(target_pointer_width = "32")]
+        for length in [u64::from(u32::MAX) + 1, u64::MAX] {
+            let mut data = length.to_le_bytes().to_vec();
+            data.extend(&valid_entry);
+            assert_eq!(destructure(&data), -6);
+
+            let mut data = valid_entry.to_vec();
+            data.extend(&length.to_le_bytes());
+            assert_eq!(destructure(&data), -7);
+        }
+    }
+
+    // The prefix is always 8 bytes, a buffer from a peer that used `usize` prefixes of another width must
+    // fail cleanly in Rust and C alike instead of being misparsed
+    #[test]
+    fn test_destructuring_prefix_width_mismatch() {
+        use core::convert::TryFrom;
+
+        fn destructure_c(data: &[u8]) -> i16 {
+            let mut entry1_length: libc::size_t = 0;
+            let mut entry2_length: libc::size_t = 0;
+            let mut entry1: *const libc::c_uchar = std::ptr::null();
+            let mut entry2: *const libc::c_uchar = std::ptr::null();
+
+            unsafe {
+                crate::response::destructure_two_entries(
+                    data.as_ptr(),
+                    data.len(),
+                    &mut entry1_length,
+                    &mut entry2_length,
+                    &mut entry1,
+                    &mut entry2,
+                )
+            }
+        }
+
+        // Written with 4-byte prefixes: the first 8-byte prefix takes in the entry and the next prefix
+        let narrow = [3, 0, 0, 0, 1, 2, 3, 2, 0, 0, 0, 4, 5];
+        let narrow_length = u64::from_le_bytes([3, 0, 0, 0, 1, 2, 3, 2]);
+        // A length with its upper half set must not be cut down to its lower half, which is 3 here
+        let wide_length: u64 = (1 << 32) + 3;
+        let mut wide = wide_length.to_le_bytes().to_vec();
+        wide.extend(&[1, 2, 3, 2, 0, 0, 0, 0, 0, 0, 0, 4, 5]);
+
+        for (data, length) in [(&narrow[..], narrow_length), (&wide[..], wide_length)] {
+            let rust = crate::request::destructure_two_entries(data).err();
+            if usize::try_from(length).is_ok() {
+                assert!(matches!(
+                    rust,
+                    Some(crate::MessageError::BufferTooShort { .. })
+                ));
+                assert_eq!(destructure_c(data), -8);
+            } else {
+                assert_eq!(rust, Some(crate::MessageError::LengthOverflow));
+                assert_eq!(destructure_c(data), -6);
+            }
+        }
+
+        // Written with 8-byte prefixes the same entries parse on every pointer width
+        let structured = crate::response::structure_two_entries(&[1, 2, 3], &[4, 5]);
+        assert_eq!(
+            structured,
+            [3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 2, 0, 0, 0, 0, 0, 0, 0, 4, 5]
+        );
+        let (entry1, entry2) = crate::request::destructure_two_entries(&structured).unwrap();
+        assert_eq!((entry1, entry2), (&[1, 2, 3][..], &[4, 5][..]));
+        assert_eq!(destructure_c(&structured), 0);
+    }
+
+    #[test]
+    fn test_destructuring_two_entries_checked_c() {
+        let mut keys: Vec<u8> = vec![
+            6, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 4, 5, 6, 3, 0, 0, 0, 0, 0, 0, 0, 12, 13, 14,
+        ];
+
+        let mut pub_key_length: libc::size_t = 0;
+        let mut priv_key_length: libc::size_t = 0;
+        let mut pub_key: *const libc::c_uchar = std::ptr::null();
+        let mut priv_key: *const libc::c_uchar = std::ptr::null();
+        let mut consumed: libc::size_t = 0;
+
+        unsafe {
+            let status = crate::response::destructure_two_entries_checked(
+                keys.as_ptr(),
+                keys.len(),
+                &mut pub_key_length,
+                &mut priv_key_length,
+                &mut pub_key,
+                &mut priv_key,
+                &mut consumed,
+            );
+            assert_eq!(status, 0);
+        }
+        assert_eq!(consumed, keys.len());
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(priv_key, priv_key_length) },
+            vec![12, 13, 14].as_slice()
+        );
+
+        // A third entry goes unnoticed by `destructure_two_entries`
+        keys.extend(&[1, 0, 0, 0, 0, 0, 0, 0, 99]);
+        unsafe {
+            let status = crate::response::destructure_two_entries_checked(
+                keys.as_ptr(),
+                keys.len(),
+                &mut pub_key_length,
+                &mut priv_key_length,
+                &mut pub_key,
+                &mut priv_key,
+                &mut consumed,
+            );
+            assert_eq!(status, -10);
+        }
+        assert_eq!(consumed, 25);
+        assert_eq!(pub_key_length, 6);
+
+        unsafe {
+            let status = crate::response::destructure_two_entries_checked(
+                keys.as_ptr(),
+                keys.len(),
+                &mut pub_key_length,
+                &mut priv_key_length,
+                &mut pub_key,
+                &mut priv_key,
+                std::ptr::null_mut(),
+            );
+            assert_eq!(status, -9);
+
+            let status = crate::response::destructure_two_entries_checked(
+                keys.as_ptr(),
+                10,
+                &mut pub_key_length,
+                &mut priv_key_length,
+                &mut pub_key,
+                &mut priv_key,
+                &mut consumed,
+            );
+            assert_eq!(status, -8);
+        }
+    }
+
+    #[test]
+    fn test_error_str_c() {
+        let describe = |code| {
+            let description = crate::response::pq_message_error_str(code);
+            assert!(!description.is_null());
+            unsafe { std::ffi::CStr::from_ptr(description) }
+                .to_str()
+                .unwrap()
+        };
+
+        assert_eq!(describe(0), "success");
+        let mut descriptions = Vec::new();
+        for code in -10..=-1 {
+            let description = describe(code);
+            assert!(!description.is_empty());
+            assert_ne!(description, "unknown error");
+            descriptions.push(description);
+        }
+        descriptions.sort_unstable();
+        descriptions.dedup();
+        assert_eq!(descriptions.len(), 10);
+
+        for code in [-11, 1, i16::MIN, i16::MAX] {
+            assert_eq!(describe(code), "unknown error");
+        }
+    }
+
+    #[test]
+    fn test_structuring_two_entries_c() {
+        let priv_key = [13, 12, 18, 33];
+        let ciphertext = [0, 0, 2, 3, 1];
+
+        unsafe {
+            let total_length =
+                crate::request::structure_two_entries_length(priv_key.len(), ciphertext.len());
+            assert!(total_length > 0);
+            let total_length = total_length as usize;
+
+            let buffer_c: *mut libc::c_uchar = libc::malloc(total_length) as *mut libc::c_uchar;
+            assert!(!buffer_c.is_null());
+
+            crate::request::structure_two_entries(
+                buffer_c,
+                priv_key.len(),
+                ciphertext.len(),
+                priv_key.as_ptr(),
+                ciphertext.as_ptr(),
+            );
+
+            let buffer = &*std::ptr::slice_from_raw_parts(buffer_c, total_length);
+            assert_eq!(
+                buffer,
+                vec![4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 1]
+                    .as_slice()
+            );
+
+            libc::free(buffer_c as *mut libc::c_void);
+        }
+    }
+
+    #[test]
+    fn test_destructuring_two_entries_rust() {
+        let mut priv_key_ct: Vec<u8> = vec![
+            4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 1,
+        ];
+
+        assert_eq!(
+            crate::request::destructure_two_entries(&priv_key_ct).unwrap(),
+            (
+                vec![13, 12, 18, 33].as_slice(),
+                vec![0, 0, 2, 3, 1].as_slice()
+            )
+        );
+
+        // Test with an empty input
+        assert!(crate::request::destructure_two_entries(&[]).is_err());
+
+        // Tests where the length are modified but the body does not match those lengths
+        priv_key_ct[0] = 255;
+        assert!(crate::request::destructure_two_entries(&priv_key_ct).is_err());
+        priv_key_ct[0] = 4;
+
+        priv_key_ct[12] = 255;
+        assert!(crate::request::destructure_two_entries(&priv_key_ct).is_err());
+    }
+
+    #[test]
+    fn test_destructuring_concatenated_two_entries_rust() {
+        let mut data = crate::response::structure_two_entries(&[1, 2, 3], &[4]);
+        let first_len = data.len();
+        data.extend(crate::response::structure_two_entries(&[], &[5, 6]));
+
+        let (entry1, entry2, consumed) =
+            crate::request::destructure_two_entries_with_offset(&data).unwrap();
+        assert_eq!((entry1, entry2), (&[1, 2, 3][..], &[4][..]));
+        assert_eq!(consumed, first_len);
+
+        let rest = &data[consumed..];
+        let (entry1, entry2, consumed) =
+            crate::request::destructure_two_entries_with_offset(rest).unwrap();
+        assert_eq!((entry1, entry2), (&[][..], &[5, 6][..]));
+        assert_eq!(consumed, rest.len());
+
+        // The C version reports the same, along with the bytes left over
+        let mut entry1_length: libc::size_t = 0;
+        let mut entry2_length: libc::size_t = 0;
+        let mut entry1: *const libc::c_uchar = std::ptr::null();
+        let mut entry2: *const libc::c_uchar = std::ptr::null();
+        let mut c_consumed: libc::size_t = 0;
+        let status = unsafe {
+            crate::response::destructure_two_entries_checked(
+                data.as_ptr(),
+                data.len(),
+                &mut entry1_length,
+                &mut entry2_length,
+                &mut entry1,
+                &mut entry2,
+                &mut c_consumed,
+            )
+        };
+        assert_eq!(status, -10);
+        assert_eq!(c_consumed, first_len);
+    }
+
+    #[test]
+    fn test_destructuring_two_entries_owned_rust() {
+        let mut priv_key_ct: Vec<u8> = vec![
+            4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 1,
+        ];
+
+        let (entry1, entry2) = crate::request::destructure_two_entries(&priv_key_ct).unwrap();
+        let owned = crate::request::destructure_two_entries_owned(&priv_key_ct).unwrap();
+        assert_eq!(owned, (entry1.to_vec(), entry2.to_vec()));
+
+        assert!(crate::request::destructure_two_entries_owned(&[]).is_err());
+
+        priv_key_ct[0] = 255;
+        assert_eq!(
+            crate::request::destructure_two_entries_owned(&priv_key_ct),
+            crate::request::destructure_two_entries(&priv_key_ct)
+                .map(|(entry1, entry2)| (entry1.to_vec(), entry2.to_vec()))
+        );
+        assert!(crate::request::destructure_two_entries_owned(&priv_key_ct).is_err());
+        priv_key_ct[0] = 4;
+
+        priv_key_ct[12] = 255;
+        assert!(crate::request::destructure_two_entries_owned(&priv_key_ct).is_err());
+
+        // The entries remain usable after the buffer is gone
+        drop(priv_key_ct);
+        assert_eq!(owned.0, vec![13, 12, 18, 33]);
+        assert_eq!(owned.1, vec![0, 0, 2, 3, 1]);
+    }
+
+    #[test]
+    fn test_structuring_three_entries_rust() {
+        let shared_secret: Vec<u8> = vec![0, 1, 2, 4, 5, 6];
+        let metadata: Vec<u8> = vec![];
+        let ciphertext: Vec<u8> = vec![12, 13, 14];
+
+        let structured =
+            crate::response::structure_three_entries(&shared_secret, &metadata, &ciphertext);
+        assert_eq!(
+            structured,
+            vec![
+                6, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 4, 5, 6, 0, 0, 0, 0, 0, 0, 0, 0, 3, 0, 0, 0, 0, 0,
+                0, 0, 12, 13, 14
+            ]
+        );
+        assert_eq!(
+            structured,
+            crate::response::structure_entries(&[&shared_secret, &metadata, &ciphertext]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_destructuring_three_entries_c() {
+        // Pretend this is the buffer we received in C
+        let mut entries: Vec<u8> = vec![
+            6, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 4, 5, 6, 1, 0, 0, 0, 0, 0, 0, 0, 7, 3, 0, 0, 0, 0, 0,
+            0, 0, 12, 13, 14,
+        ];
+
+        let mut lengths: [libc::size_t; 3] = [0; 3];
+        let mut pointers: [*const libc::c_uchar; 3] = [std::ptr::null(); 3];
+        let destructure = |entries: &[u8],
+                           lengths: &mut [libc::size_t; 3],
+                           pointers: &mut [*const libc::c_uchar; 3]| unsafe {
+            let (length1, rest) = lengths.split_at_mut(1);
+            let (length2, length3) = rest.split_at_mut(1);
+            let (pointer1, rest) = pointers.split_at_mut(1);
+            let (pointer2, pointer3) = rest.split_at_mut(1);
+            crate::response::destructure_three_entries(
+                entries.as_ptr(),
+                entries.len(),
+                &mut length1[0],
+                &mut length2[0],
+                &mut length3[0],
+                &mut pointer1[0],
+                &mut pointer2[0],
+                &mut pointer3[0],
+            )
+        };
+
+        assert_eq!(destructure(&entries, &mut lengths, &mut pointers), 0);
+        assert_eq!(lengths, [6, 1, 3]);
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(pointers[0], lengths[0]) },
+            vec![0, 1, 2, 4, 5, 6].as_slice()
+        );
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(pointers[1], lengths[1]) },
+            vec![7].as_slice()
+        );
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(pointers[2], lengths[2]) },
+            vec![12, 13, 14].as_slice()
+        );
+
+        // Each malformed entry has its own status
+        entries[0] = 255;
+        assert_eq!(destructure(&entries, &mut lengths, &mut pointers), -11);
+        entries[0] = 6;
+
+        entries[14] = 255;
+        assert_eq!(destructure(&entries, &mut lengths, &mut pointers), -12);
+        entries[14] = 1;
+
+        entries[23] = 255;
+        assert_eq!(destructure(&entries, &mut lengths, &mut pointers), -13);
+        entries[23] = 3;
+
+        // The length prefix of the third entry is cut off
+        let truncated = &entries[..26];
+        assert_eq!(destructure(truncated, &mut lengths, &mut pointers), -13);
+        assert_ne!(destructure(&[], &mut lengths, &mut pointers), 0);
+
+        if core::mem::size_of::<libc::size_t>() < core::mem::size_of::<u64>() {
+            entries[21] = 1;
+            assert_eq!(destructure(&entries, &mut lengths, &mut pointers), -9);
+            entries[21] = 0;
+        }
+
+        unsafe {
+            let status = crate::response::destructure_three_entries(
+                entries.as_ptr(),
+                entries.len(),
+                &mut lengths[0],
+                std::ptr::null_mut(),
+                &mut lengths[2],
+                &mut pointers[0],
+                &mut pointers[1],
+                &mut pointers[2],
+            );
+            assert_eq!(status, -3);
+
+            let status = crate::response::destructure_three_entries(
+                entries.as_ptr(),
+                entries.len(),
+                &mut lengths[0],
+                &mut lengths[1],
+                &mut lengths[2],
+                &mut pointers[0],
+                &mut pointers[1],
+                std::ptr::null_mut(),
+            );
+            assert_eq!(status, -7);
+        }
+    }
+
+    #[test]
+    fn test_structuring_three_entries_c() {
+        let shared_secret = [13, 12, 18, 33];
+        let metadata = [7, 7];
+        let ciphertext = [0, 0, 2, 3, 1];
+
+        unsafe {
+            let total_length = crate::request::structure_three_entries_length(
+                shared_secret.len(),
+                metadata.len(),
+                ciphertext.len(),
+            );
+            assert!(total_length > 0);
+            let total_length = total_length as usize;
+
+            let buffer_c: *mut libc::c_uchar = libc::malloc(total_length) as *mut libc::c_uchar;
+            assert!(!buffer_c.is_null());
+
+            let status = crate::request::structure_three_entries(
+                buffer_c,
+                shared_secret.len(),
+                metadata.len(),
+                ciphertext.len(),
+                shared_secret.as_ptr(),
+                metadata.as_ptr(),
+                ciphertext.as_ptr(),
+            );
+            assert_eq!(status, 0);
+
+            let buffer = &*std::ptr::slice_from_raw_parts(buffer_c, total_length);
+            assert_eq!(
+                buffer,
+                vec![
+                    4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 2, 0, 0, 0, 0, 0, 0, 0, 7, 7, 5, 0, 0,
+                    0, 0, 0, 0, 0, 0, 0, 2, 3, 1
+                ]
+                .as_slice()
+            );
+
+            let status = crate::request::structure_three_entries(
+                buffer_c,
+                shared_secret.len(),
+                metadata.len(),
+                ciphertext.len(),
+                shared_secret.as_ptr(),
+                metadata.as_ptr(),
+                std::ptr::null(),
+            );
+            assert_eq!(status, -4);
+
+            let status = crate::request::structure_three_entries(
+                buffer_c,
+                usize::MAX / 2,
+                usize::MAX / 2,
+                2,
+                shared_secret.as_ptr(),
+                metadata.as_ptr(),
+                ciphertext.as_ptr(),
+            );
+            assert_eq!(status, -5);
+
+            libc::free(buffer_c as *mut libc::c_void);
+        }
+
+        assert_eq!(
+            crate::request::structure_three_entries_length(usize::MAX, 1, 0),
+            -1
+        );
+    }
+
+    #[test]
+    fn test_destructuring_three_entries_rust() {
+        let mut entries: Vec<u8> = vec![
+            4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 2, 0, 0, 0, 0, 0, 0, 0, 7, 7, 5, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 2, 3, 1,
+        ];
+
+        assert_eq!(
+            crate::request::destructure_three_entries(&entries).unwrap(),
+            (
+                vec![13, 12, 18, 33].as_slice(),
+                vec![7, 7].as_slice(),
+                vec![0, 0, 2, 3, 1].as_slice()
+            )
+        );
+
+        // Test with an empty input
+        assert!(crate::request::destructure_three_entries(&[]).is_err());
+
+        // Tests where the length are modified but the body does not match those lengths
+        entries[0] = 255;
+        assert!(crate::request::destructure_three_entries(&entries).is_err());
+        entries[0] = 4;
+
+        entries[12] = 255;
+        assert!(crate::request::destructure_three_entries(&entries).is_err());
+        entries[12] = 2;
+
+        entries[22] = 255;
+        assert!(crate::request::destructure_three_entries(&entries).is_err());
+        entries[22] = 5;
+
+        // Only two entries
+        assert!(crate::request::destructure_three_entries(&entries[..22]).is_err());
+    }
+
+    #[test]
+    fn test_structuring_n_entries_rust() {
+        let pub_key: Vec<u8> = vec![0, 1, 2, 4, 5, 6];
+        let priv_key: Vec<u8> = vec![12, 13, 14];
+        let ciphertext: Vec<u8> = vec![7];
+
+        // Must be compatible with structure_two_entries
+        assert_eq!(
+            crate::response::structure_entries(&[&pub_key, &priv_key]).unwrap(),
+            crate::response::structure_two_entries(&pub_key, &priv_key)
+        );
+
+        let structured =
+            crate::response::structure_entries(&[&pub_key, &priv_key, &ciphertext]).unwrap();
+        assert_eq!(
+            structured,
+            vec![
+                6, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 4, 5, 6, 3, 0, 0, 0, 0, 0, 0, 0, 12, 13, 14, 1, 0,
+                0, 0, 0, 0, 0, 0, 7,
+            ]
+        );
+        assert_eq!(
+            crate::response::structure_entries(&[]),
+            Err(crate::MessageError::InvalidField {
+                field: "entries",
+                reason: "at least one entry has to be structured"
+            })
+        );
+    }
+
+    #[test]
+    fn test_destructuring_n_entries_c() {
+        // Pretend this is the buffer we received in C
+        let mut data: Vec<u8> = vec![
+            6, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 4, 5, 6, 3, 0, 0, 0, 0, 0, 0, 0, 12, 13, 14, 1, 0, 0,
+            0, 0, 0, 0, 0, 7,
+        ];
+
+        let mut entry_lengths: [libc::size_t; 3] = [0; 3];
+        let mut entries: [*const libc::c_uchar; 3] = [std::ptr::null(); 3];
+        let mut entry_count: libc::size_t = 0;
+
+        unsafe {
+            let status = crate::response::destructure_n_entries(
+                data.as_ptr(),
+                data.len(),
+                3,
+                entry_lengths.as_mut_ptr(),
+                entries.as_mut_ptr(),
+                &mut entry_count,
+            );
+            assert_eq!(status, 0);
+        }
+
+        assert_eq!(entry_count, 3);
+        assert_eq!(entry_lengths, [6, 3, 1]);
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(entries[0], entry_lengths[0]) },
+            vec![0, 1, 2, 4, 5, 6].as_slice()
+        );
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(entries[1], entry_lengths[1]) },
+            vec![12, 13, 14].as_slice()
+        );
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(entries[2], entry_lengths[2]) },
+            vec![7].as_slice()
+        );
+
+        // Not enough capacity for all entries
+        unsafe {
+            let status = crate::response::destructure_n_entries(
+                data.as_ptr(),
+                data.len(),
+                2,
+                entry_lengths.as_mut_ptr(),
+                entries.as_mut_ptr(),
+                &mut entry_count,
+            );
+            assert_eq!(status, -6);
+        }
+
+        // Length of the last entry runs past the end of the buffer
+        data[25] = 2;
+        unsafe {
+            let status = crate::response::destructure_n_entries(
+                data.as_ptr(),
+                data.len(),
+                3,
+                entry_lengths.as_mut_ptr(),
+                entries.as_mut_ptr(),
+                &mut entry_count,
+            );
+            assert_eq!(status, -8);
+        }
+
+        // No entries at all
+        let buffer: [u8; 0] = [];
+        unsafe {
+            let status = crate::response::destructure_n_entries(
+                buffer.as_ptr(),
+                buffer.len(),
+                3,
+                entry_lengths.as_mut_ptr(),
+                entries.as_mut_ptr(),
+                &mut entry_count,
+            );
+            assert_eq!(status, -5);
+        }
+    }
+
+    #[test]
+    fn test_count_entries_c() {
+        let count =
+            |data: &[u8]| unsafe { crate::response::count_entries(data.as_ptr(), data.len()) };
+        assert_eq!(count(&[]), 0);
+        assert_eq!(
+            count(&crate::response::structure_entries(&[&[1, 2]]).unwrap()),
+            1
+        );
+        let structured = crate::response::structure_three_entries(&[1], &[], &[2, 3]);
+        assert_eq!(count(&structured), 3);
+
+        // The count is the capacity destructure_n_entries needs
+        let mut entry_lengths = [0; 3];
+        let mut entries: [*const libc::c_uchar; 3] = [std::ptr::null(); 3];
+        let mut entry_count = 0;
+        let status = unsafe {
+            crate::response::destructure_n_entries(
+                structured.as_ptr(),
+                structured.len(),
+                count(&structured) as usize,
+                entry_lengths.as_mut_ptr(),
+                entries.as_mut_ptr(),
+                &mut entry_count,
+            )
+        };
+        assert_eq!(status, 0);
+        assert_eq!(entry_count, 3);
+
+        // A truncated prefix, a length past the end and a length that wraps around
+        assert_eq!(count(&structured[..structured.len() - 11]), -3);
+        assert_eq!(count(&structured[..structured.len() - 1]), -3);
+        assert_eq!(count(&[0xFF; 8]), -2);
+        assert_eq!(
+            unsafe { crate::response::count_entries(std::ptr::null(), 0) },
+            -1
+        );
+    }
+
+    #[test]
+    fn test_structuring_n_entries_c() {
+        let priv_key = [13, 12, 18, 33];
+        let ciphertext = [0, 0, 2, 3, 1];
+        let metadata = [9, 9];
+
+        let entry_lengths = [priv_key.len(), ciphertext.len(), metadata.len()];
+        let entries = [priv_key.as_ptr(), ciphertext.as_ptr(), metadata.as_ptr()];
+
+        unsafe {
+            let total_length =
+                crate::request::structure_n_entries_length(entry_lengths.as_ptr(), entries.len());
+            assert_eq!(total_length, 35);
+            assert_eq!(
+                crate::request::structure_n_entries_length(entry_lengths.as_ptr(), 0),
+                0
+            );
+
+            let total_length = total_length as usize;
+            let buffer_c: *mut libc::c_uchar = libc::malloc(total_length) as *mut libc::c_uchar;
+            assert!(!buffer_c.is_null());
+
+            let status = crate::request::structure_n_entries(
+                buffer_c,
+                entry_lengths.as_ptr(),
+                entries.as_ptr(),
+                entries.len(),
+            );
+            assert_eq!(status, 0);
+
+            let buffer = &*std::ptr::slice_from_raw_parts(buffer_c, total_length);
+            assert_eq!(
+                buffer,
+                vec![
+                    4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 1,
+                    2, 0, 0, 0, 0, 0, 0, 0, 9, 9
+                ]
+                .as_slice()
+            );
+
+            let status = crate::request::structure_n_entries(
+                buffer_c,
+                entry_lengths.as_ptr(),
+                entries.as_ptr(),
+                0,
+            );
+            assert_eq!(status, -5);
+
+            let null_entries = [priv_key.as_ptr(), std::ptr::null()];
+            let status = crate::request::structure_n_entries(
+                buffer_c,
+                entry_lengths.as_ptr(),
+                null_entries.as_ptr(),
+                null_entries.len(),
+            );
+            assert_eq!(status, -4);
+
+            // The lengths sum past usize::MAX, nothing may be written for them
+            let overflowing_lengths = [usize::MAX / 2, usize::MAX / 2, 2];
+            assert_eq!(
+                crate::request::structure_n_entries_length(overflowing_lengths.as_ptr(), 3),
+                -1
+            );
+            let status = crate::request::structure_n_entries(
+                buffer_c,
+                overflowing_lengths.as_ptr(),
+                entries.as_ptr(),
+                overflowing_lengths.len(),
+            );
+            assert_eq!(status, -6);
+            assert_eq!(
+                crate::request::structure_n_entries_length([usize::MAX - 8].as_ptr(), 1),
+                -1
+            );
+
+            libc::free(buffer_c as *mut libc::c_void);
+        }
+    }
+
+    #[test]
+    fn test_zero_length_entries() {
+        let cases: [(&[u8], &[u8]); 3] = [(&[], &[1, 2, 3]), (&[1], &[]), (&[], &[])];
+        for &(entry1, entry2) in cases.iter() {
+            let structured = crate::response::structure_two_entries(entry1, entry2);
+            assert_eq!(
+                structured.len(),
+                2 * crate::LENGTH_PREFIX_SIZE + entry1.len() + entry2.len()
+            );
+            assert_eq!(
+                crate::request::destructure_two_entries(&structured),
+                Ok((entry1, entry2))
+            );
+            assert_eq!(
+                crate::request::destructure_entries(&structured),
+                Ok(vec![entry1, entry2])
+            );
+
+            // C callers may pass a null pointer for an empty entry
+            let pointer = |entry: &[u8]| {
+                if entry.is_empty() {
+                    std::ptr::null()
+                } else {
+                    entry.as_ptr()
+                }
+            };
+            let mut buffer = vec![0xEE; structured.len()];
+            let status = unsafe {
+                crate::request::structure_two_entries(
+                    buffer.as_mut_ptr(),
+                    entry1.len(),
+                    entry2.len(),
+                    pointer(entry1),
+                    pointer(entry2),
+                )
+            };
+            assert_eq!(status, 0);
+            assert_eq!(buffer, structured);
+            let entry_lengths = [entry1.len(), entry2.len()];
+            let entries = [pointer(entry1), pointer(entry2)];
+            let status = unsafe {
+                crate::request::structure_n_entries(
+                    buffer.as_mut_ptr(),
+                    entry_lengths.as_ptr(),
+                    entries.as_ptr(),
+                    entries.len(),
+                )
+            };
+            assert_eq!(status, 0);
+            assert_eq!(buffer, structured);
+
+            let mut lengths = [usize::MAX; 2];
+            let mut parsed: [*const libc::c_uchar; 2] = [std::ptr::null(); 2];
+            let status = unsafe {
+                crate::response::destructure_two_entries(
+                    structured.as_ptr(),
+                    structured.len(),
+                    &mut lengths[0],
+                    &mut lengths[1],
+                    &mut parsed[0],
+                    &mut parsed[1],
+                )
+            };
+            assert_eq!(status, 0);
+            assert_eq!(lengths, entry_lengths);
+        }
+    }
+
+    #[test]
+    fn test_destructuring_entries_rust() {
+        let mut data: Vec<u8> = vec![
+            4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 1, 2, 0, 0,
+            0, 0, 0, 0, 0, 9, 9,
+        ];
+
+        assert_eq!(
+            crate::request::destructure_entries(&data).unwrap(),
+            vec![
+                vec![13, 12, 18, 33].as_slice(),
+                vec![0, 0, 2, 3, 1].as_slice(),
+                vec![9, 9].as_slice()
+            ]
+        );
+
+        // The first two entries on their own are a valid two-entry structure
+        let (entry1, entry2) = crate::request::destructure_two_entries(&data[..25]).unwrap();
+        assert_eq!(
+            crate::request::destructure_entries(&data[..25]).unwrap(),
+            vec![entry1, entry2]
+        );
+
+        // Test with an empty input
+        assert!(crate::request::destructure_entries(&[]).is_err());
+
+        // Tests where the length are modified but the body does not match those lengths
+        data[25] = 255;
+        assert!(crate::request::destructure_entries(&data).is_err());
+        data[25] = 2;
+
+        // Trailing bytes which are too short to be a length
+        data.push(0);
+        assert!(crate::request::destructure_entries(&data).is_err());
+    }
+
+    #[test]
+    fn test_destructuring_too_many_entries_rust() {
+        let max = crate::request::DEFAULT_MAX_ENTRIES;
+        let empty_entries = |count: usize| vec![0; count * crate::LENGTH_PREFIX_SIZE];
+
+        assert_eq!(
+            crate::request::destructure_entries(&empty_entries(max))
+                .unwrap()
+                .len(),
+            max
+        );
+        // A buffer claiming a million empty entries is rejected after the cap, not collected
+        for count in [max + 1, 1_000_000] {
+            assert_eq!(
+                crate::request::destructure_entries(&empty_entries(count)).err(),
+                Some(crate::MessageError::TooManyEntries { max })
+            );
+        }
+
+        let structured = crate::response::structure_three_entries(&[1], &[2, 3], &[]);
+        assert_eq!(
+            crate::request::destructure_entries_bounded(&structured, 3)
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(
+            crate::request::destructure_entries_bounded(&structured, 2).err(),
+            Some(crate::MessageError::TooManyEntries { max: 2 })
+        );
+        assert_eq!(
+            crate::request::destructure_entries_bounded(&structured, max + 1).unwrap(),
+            crate::request::destructure_entries(&structured).unwrap()
+        );
+    }
+
+    // Both modules frame entries the same way: each entry is preceded by its length as a little-endian
+    // `u64`, also where `usize` is only 32 bits wide, so whatever one side structures the other can destructure.
+    proptest::proptest! {
+        #[test]
+        fn test_two_entries_round_trip(
+            entry1 in proptest::collection::vec(proptest::num::u8::ANY, 0..300),
+            entry2 in proptest::collection::vec(proptest::num::u8::ANY, 0..300),
+        ) {
+            let structured = crate::response::structure_two_entries(&entry1, &entry2);
+            proptest::prop_assert_eq!(&structured[..8], &(entry1.len() as u64).to_le_bytes());
+            proptest::prop_assert_eq!(
+                crate::request::destructure_two_entries(&structured),
+                Ok((entry1.as_slice(), entry2.as_slice()))
+            );
+
+            let length = crate::request::structure_two_entries_length(entry1.len(), entry2.len());
+            let mut structured_c = vec![0; length as usize];
+            let status = unsafe {
+                crate::request::structure_two_entries(
+                    structured_c.as_mut_ptr(),
+                    entry1.len(),
+                    entry2.len(),
+                    entry1.as_ptr(),
+                    entry2.as_ptr(),
+                )
+            };
+            proptest::prop_assert_eq!(status, 0);
+            proptest::prop_assert_eq!(&structured_c, &structured);
+
+            let mut lengths: [libc::size_t; 2] = [0; 2];
+            let mut pointers: [*const libc::c_uchar; 2] = [std::ptr::null(); 2];
+            let (length1, length2) = lengths.split_at_mut(1);
+            let (pointer1, pointer2) = pointers.split_at_mut(1);
+            let status = unsafe {
+                crate::response::destructure_two_entries(
+                    structured.as_ptr(),
+                    structured.len(),
+                    &mut length1[0],
+                    &mut length2[0],
+                    &mut pointer1[0],
+                    &mut pointer2[0],
+                )
+            };
+            proptest::prop_assert_eq!(status, 0);
+            proptest::prop_assert_eq!(
+                unsafe { std::slice::from_raw_parts(pointers[0], lengths[0]) },
+                entry1.as_slice()
+            );
+            proptest::prop_assert_eq!(
+                unsafe { std::slice::from_raw_parts(pointers[1], lengths[1]) },
+                entry2.as_slice()
+            );
+        }
+
+        #[test]
+        fn test_entries_round_trip(
+            entries in proptest::collection::vec(
+                proptest::collection::vec(proptest::num::u8::ANY, 0..100),
+                1..6,
+            ),
+        ) {
+            let entries: Vec<&[u8]> = entries.iter().map(Vec::as_slice).collect();
+            let structured = crate::response::structure_entries(&entries).unwrap();
+            proptest::prop_assert_eq!(
+                crate::request::destructure_entries(&structured),
+                Ok(entries.clone())
+            );
+
+            let lengths: Vec<libc::size_t> = entries.iter().map(|entry| entry.len()).collect();
+            let pointers: Vec<*const libc::c_uchar> =
+                entries.iter().map(|entry| entry.as_ptr()).collect();
+            let length = unsafe {
+                crate::request::structure_n_entries_length(lengths.as_ptr(), lengths.len())
+            };
+            let mut structured_c = vec![0; length as usize];
+            let status = unsafe {
+                crate::request::structure_n_entries(
+                    structured_c.as_mut_ptr(),
+                    lengths.as_ptr(),
+                    pointers.as_ptr(),
+                    entries.len(),
+                )
+            };
+            proptest::prop_assert_eq!(status, 0);
+            proptest::prop_assert_eq!(&structured_c, &structured);
+
+            if let [entry1, entry2, entry3] = entries[..] {
+                proptest::prop_assert_eq!(
+                    &crate::response::structure_three_entries(entry1, entry2, entry3),
+                    &structured
+                );
+                proptest::prop_assert_eq!(
+                    crate::request::destructure_three_entries(&structured),
+                    Ok((entry1, entry2, entry3))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_entry_iter_rust() {
+        // Test with an empty input
+        assert!(crate::request::EntryIter::new(&[]).next().is_none());
+
+        let one_entry = crate::response::structure_entries(&[&[13, 12, 18, 33]]).unwrap();
+        let entries: Result<Vec<&[u8]>, _> = crate::request::EntryIter::new(&one_entry).collect();
+        assert_eq!(entries.unwrap(), vec![vec![13, 12, 18, 33].as_slice()]);
+
+        let data = crate::response::structure_entries(&[&[1, 2], &[], &[3, 4, 5]]).unwrap();
+        let mut entries = crate::request::EntryIter::new(&data);
+        assert_eq!(entries.next(), Some(Ok(vec![1, 2].as_slice())));
+        assert_eq!(entries.next(), Some(Ok(vec![].as_slice())));
+        assert_eq!(entries.next(), Some(Ok(vec![3, 4, 5].as_slice())));
+        assert_eq!(entries.next(), None);
+
+        // Stopping early never looks at the entries after it
+        let mut truncated = data.clone();
+        truncated.pop();
+        assert_eq!(
+            crate::request::EntryIter::new(&truncated).next(),
+            Some(Ok(vec![1, 2].as_slice()))
+        );
+
+        // A truncated final entry is an error after which the iteration ends
+        let mut entries = crate::request::EntryIter::new(&truncated);
+        assert!(entries.next().unwrap().is_ok());
+        assert!(entries.next().unwrap().is_ok());
+        assert_eq!(
+            entries.next(),
+            Some(Err(crate::MessageError::BufferTooShort {
+                needed: data.len(),
+                got: truncated.len()
+            }))
+        );
+        assert_eq!(entries.next(), None);
+    }
+
+    #[test]
+    fn test_zeroize_buffer_c() {
+        let mut priv_key: Vec<u8> = vec![12, 13, 14, 15];
+
+        unsafe {
+            let status = crate::secure::zeroize_buffer(priv_key.as_mut_ptr(), priv_key.len());
+            assert_eq!(status, 0);
+
+            let status = crate::secure::zeroize_buffer(std::ptr::null_mut(), 4);
+            assert_eq!(status, -1);
+        }
+
+        assert_eq!(priv_key, vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_ct_eq() {
+        let cases: [(&[u8], &[u8]); 5] = [
+            (&[1, 2, 3], &[1, 2, 3]),
+            (&[1, 2, 3], &[1, 2, 4]),
+            (&[1, 2, 3], &[1, 2]),
+            (&[], &[]),
+            (&[], &[0]),
+        ];
+        for (a, b) in cases.iter() {
+            assert_eq!(crate::secure::ct_eq(a, b), a == b);
+
+            let status =
+                unsafe { crate::secure::ct_eq_c(a.as_ptr(), a.len(), b.as_ptr(), b.len()) };
+            assert_eq!(status, (a == b) as i16);
+        }
+
+        let status = unsafe { crate::secure::ct_eq_c(std::ptr::null(), 0, [0].as_ptr(), 1) };
+        assert_eq!(status, -1);
+    }
+
+    #[cfg(not(any(feature = "checksum", feature = "uuid")))]
+    #[test]
+    fn test_zeroizing_response_rust() {
+        let response = crate::response::serialize_response(1234, &[0, 1, 2, 3, 4, 5]);
+        let response = crate::response::ZeroizingResponse::from(response.unwrap());
+        assert_eq!(
+            &*response,
+            vec![crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 0, 6, 0, 0, 0, 0, 0, 1, 2, 3, 4, 5]
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn test_zeroizing_request_rust() {
+        use zeroize::Zeroize;
+
+        let header = crate::request::RequestHeader::builder()
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::Decapsulation)
+            .data_len(32)
+            .build()
+            .unwrap();
+        let mut body = Vec::with_capacity(64);
+        body.extend(&[0xA5; 32]);
+        let mut request = crate::request::deserialize_request(header, body);
+        let pointer = request.body.as_ptr();
+        let capacity = request.body.capacity();
+        assert_eq!(
+            unsafe { std::slice::from_raw_parts(pointer, 32) },
+            &[0xA5; 32]
+        );
+
+        // What dropping the body does, after which the allocation is still alive so it can be inspected
+        request.body.zeroize();
+        assert!(request.body.is_empty());
+        assert_eq!(request.body.as_ptr(), pointer);
+        assert!(unsafe { std::slice::from_raw_parts(pointer, capacity) }
+            .iter()
+            .all(|&byte| byte == 0));
+        drop(request);
+    }
+
+    #[cfg(not(feature = "uuid"))]
+    #[test]
+    fn test_deserialize_request_header_errors_rust() {
+        let mut buffer: Vec<u8> = vec![
+            crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 51, 5, 0, 0, 3, 0, 0, 0, 2, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+
+        assert_eq!(
+            crate::request::deserialize_request_header(&buffer[..10]).err(),
+            Some(crate::MessageError::BufferTooShort {
+                needed: buffer.len(),
+                got: 10
+            })
+        );
+
+        buffer[0] = crate::FORMAT_VERSION + 1;
+        assert_eq!(
+            crate::request::deserialize_request_header(&buffer).err(),
+            Some(crate::MessageError::VersionMismatch {
+                expected: crate::FORMAT_VERSION,
+                found: crate::FORMAT_VERSION + 1
+            })
+        );
+        buffer[0] = crate::FORMAT_VERSION;
+
+        buffer[13] = 255;
+        assert!(matches!(
+            crate::request::deserialize_request_header(&buffer),
+            Err(crate::MessageError::UnknownAlgorithm(255))
+        ));
+        buffer[13] = 3;
+
+        buffer[17] = 8;
+        assert!(matches!(
+            crate::request::deserialize_request_header(&buffer),
+            Err(crate::MessageError::UnknownOperation(8))
+        ));
+    }
+
+    #[test]
+    fn test_destructure_errors_rust() {
+        let priv_key_ct: Vec<u8> = vec![
+            4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 1,
+        ];
+
+        assert_eq!(
+            crate::request::destructure_two_entries(&priv_key_ct[..20]).err(),
+            Some(crate::MessageError::BufferTooShort {
+                needed: 25,
+                got: 20
+            })
+        );
+        assert_eq!(
+            crate::MessageError::BufferTooShort {
+                needed: 25,
+                got: 20
+            }
+            .to_string(),
+            "buffer too short: needed 25 bytes but got 20"
+        );
+    }
+
+    #[test]
+    fn test_signature_operations_match_c_header() {
+        assert_eq!(crate::request::Operation::Sign as u32, 4);
+        assert_eq!(crate::request::Operation::Verify as u32, 5);
+
+        // The discriminants in C are spelled out in the header
+        let header = include_str!("../pq_message_lib.h");
+        let operations = header
+            .split("typedef enum {")
+            .find(|block| block.contains("} Operation;"))
+            .and_then(|block| block.split("} Operation;").next())
+            .unwrap();
+        let operations: Vec<&str> = operations
+            .lines()
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter(|line| line.contains(" = "))
+            .collect();
+        assert!(operations.contains(&"Sign = 4"));
+        assert!(operations.contains(&"Verify = 5"));
+    }
+
+    #[cfg(not(feature = "uuid"))]
+    #[test]
+    fn test_signature_operations_round_trip() {
+        fn round_trip(operation: crate::request::Operation, discriminant: u8) {
+            // None of the algorithms support signatures so serializing must be rejected
+            let header_size = crate::request::get_serialized_request_header_size() as usize;
+            let mut buffer = vec![0; header_size];
+
+            let status = unsafe {
+                crate::request::serialize_request_header(
+                    buffer.as_mut_ptr(),
+                    buffer.len(),
+                    1234,
+                    1331,
+                    crate::request::Algorithm::FRODO976__ECDHp384,
+                    operation,
+                    0,
+                )
+            };
+            assert_eq!(status, -2);
+
+            // Pretend we've read these bytes from somewhere
+            let buffer = vec![
+                crate::FORMAT_VERSION, 210, 4, 0, 0, 0, 0, 0, 0, 51, 5, 0, 0, 3, 0, 0, 0,
+                discriminant, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            ];
+            let request_header = crate::request::deserialize_request_header(&buffer).unwrap();
+            assert_eq!(request_header.operation as u8, discriminant);
+        }
+
+        round_trip(crate::request::Operation::Sign, 4);
+        round_trip(crate::request::Operation::Verify, 5);
+    }
+
+    #[test]
+    fn test_request_header_builder() {
+        let built = crate::request::RequestHeader::builder()
+            .identifier(1234)
+            .data_len(1331)
+            .algorithm(crate::request::Algorithm::FRODO976__ECDHp384)
+            .operation(crate::request::Operation::Encapsulation)
+            .build()
+            .unwrap();
+        let equal = built
+            == crate::request::RequestHeader {
+                version: crate::FORMAT_VERSION,
+                identifier: 1234,
+                data_len: 1331,
+                algorithm: crate::request::Algorithm::FRODO976__ECDHp384,
+                operation: crate::request::Operation::Encapsulation,
+                deadline_ms: 0,
+                flags: 0,
+            };
+        assert!(equal);
+
+        let built = crate::request::RequestHeader::builder()
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::Decapsulation)
+            .deadline_ms(1_600_000_000_000)
+            .build()
+            .unwrap();
+        assert_eq!(built.deadline_ms, 1_600_000_000_000);
+
+        assert!(matches!(
+            crate::request::RequestHeader::builder()
+                .algorithm(crate::request::Algorithm::KYBER_512)
+                .operation(crate::request::Operation::Sign)
+                .build(),
+            Err(crate::MessageError::InvalidField { .. })
+        ));
+        assert!(crate::request::RequestHeader::builder().build().is_err());
+    }
+
+    #[test]
+    fn test_request_header_rust_matches_c() {
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        let mut buffer_c = vec![0; header_size];
+
+        let status = unsafe {
+            crate::request::serialize_request_header(
+                buffer_c.as_mut_ptr(),
+                buffer_c.len(),
+                1234,
+                1331,
+                crate::request::Algorithm::FRODO976__ECDHp384,
+                crate::request::Operation::Encapsulation,
+                0,
+            )
+        };
+        assert_eq!(status, buffer_c.len() as i64);
+
+        let buffer_rust = crate::request::serialize_request_header_vec(
+            1234,
+            1331,
+            crate::request::Algorithm::FRODO976__ECDHp384,
+            crate::request::Operation::Encapsulation,
+            0,
+        );
+        assert_eq!(buffer_rust.unwrap(), buffer_c);
+    }
+
+    #[test]
+    fn test_algorithm_names() {
+        use std::convert::TryFrom;
+
+        let algorithms: Vec<crate::request::Algorithm> = (0..)
+            .map_while(|discriminant| crate::request::Algorithm::try_from(discriminant).ok())
+            .collect();
+        assert_eq!(
+            algorithms.len(),
+            crate::request::Algorithm::ML_DSA_87 as usize + 1
+        );
+
+        for (discriminant, algorithm) in algorithms.into_iter().enumerate() {
+            assert_eq!(algorithm as usize, discriminant);
+
+            let name = algorithm.as_str();
+            let parsed: crate::request::Algorithm = name.parse().unwrap();
+            assert!(parsed == algorithm);
+            assert_eq!(parsed.as_str(), name);
+            assert_eq!(parsed.to_string(), name);
+            let canonical: crate::request::Algorithm = algorithm.canonical_name().parse().unwrap();
+            assert!(canonical == algorithm);
+        }
+
+        // The draft and standardized names of Kyber are distinct algorithms of the same sizes
+        use crate::request::Algorithm;
+        assert!("ML-KEM-768".parse::<Algorithm>().unwrap() == Algorithm::ML_KEM_768);
+        assert!("ML_KEM_768".parse::<Algorithm>().unwrap() == Algorithm::ML_KEM_768);
+        assert!(
+            "ML-KEM-1024__ECDHp521".parse::<Algorithm>().unwrap()
+                == Algorithm::ML_KEM_1024__ECDHp521
+        );
+        assert!("KYBER_768".parse::<Algorithm>().unwrap() == Algorithm::KYBER_768);
+        assert_eq!(Algorithm::KYBER_768.canonical_name(), "KYBER_768");
+        assert_eq!(Algorithm::ML_KEM_768.canonical_name(), "ML-KEM-768");
+        assert_eq!(Algorithm::ML_KEM_768.sizes(), Algorithm::KYBER_768.sizes());
+        assert!("ML-DSA-65".parse::<Algorithm>().unwrap() == Algorithm::ML_DSA_65);
+
+        assert!(
+            "KYBER_768__ECDHp384"
+                .parse::<crate::request::Algorithm>()
+                .unwrap()
+                == crate::request::Algorithm::KYBER_768__ECDHp384
+        );
+        assert_eq!(
+            "KYBER_768__ECDHP384"
+                .parse::<crate::request::Algorithm>()
+                .err(),
+            Some(crate::MessageError::UnknownAlgorithmName(
+                "KYBER_768__ECDHP384".to_string()
+            ))
+        );
+        assert_eq!(
+            crate::request::Algorithm::try_from(38).err(),
+            Some(crate::MessageError::UnknownAlgorithm(38))
+        );
+    }
+
+    #[test]
+    fn test_operation_from_str() {
+        use std::convert::TryFrom;
+
+        for discriminant in 0..crate::request::OPERATION_COUNT {
+            let operation = crate::request::Operation::try_from(discriminant).unwrap();
+            let parsed: crate::request::Operation = operation.as_str().parse().unwrap();
+            assert!(parsed == operation);
+        }
+        assert_eq!(
+            "decapsulation".parse::<crate::request::Operation>().err(),
+            Some(crate::MessageError::UnknownOperationName(
+                "decapsulation".to_string()
+            ))
+        );
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_request_header_json() {
+        let json = format!(
+            "{{\"version\":{},\"identifier\":1234,\"data_len\":42,\"algorithm\":\"FRODO976__ECDHp384\",\
+             \"operation\":\"Encapsulation\",\"deadline_ms\":5000,\"flags\":0}}",
+            crate::FORMAT_VERSION
+        );
+        let header = crate::json::request_header_from_json(&json).unwrap();
+        assert!(
+            header
+                == crate::request::RequestHeader::builder()
+                    .identifier(1234)
+                    .data_len(42)
+                    .algorithm(crate::request::Algorithm::FRODO976__ECDHp384)
+                    .operation(crate::request::Operation::Encapsulation)
+                    .deadline_ms(5000)
+                    .build()
+                    .unwrap()
+        );
+        assert_eq!(crate::json::request_header_to_json(&header), json);
+
+        // The canonical name is accepted as well, but written as the variant
+        let header = crate::json::request_header_from_json(
+            &json.replace("FRODO976__ECDHp384", "ML-KEM-768"),
+        )
+        .unwrap();
+        assert!(header.algorithm == crate::request::Algorithm::ML_KEM_768);
+        let json_ml_kem = crate::json::request_header_to_json(&header);
+        assert!(json_ml_kem.contains("\"algorithm\":\"ML_KEM_768\""));
+
+        assert_eq!(
+            crate::json::request_header_from_json(&json.replace("Encapsulation", "Encaps")).err(),
+            Some(crate::MessageError::UnknownOperationName(
+                "Encaps".to_string()
+            ))
+        );
+        assert!(matches!(
+            crate::json::request_header_from_json("{\"version\":8}"),
+            Err(crate::MessageError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn test_algorithm_sizes() {
+        let kyber = crate::request::Algorithm::KYBER_768.sizes();
+        assert_eq!(kyber.public_key_len, 1184);
+        assert_eq!(kyber.secret_key_len, 2400);
+        assert_eq!(kyber.ciphertext_len, 1088);
+        assert_eq!(kyber.shared_secret_len, 32);
+
+        // Hybrids combine the sizes of both parts
+        let frodo = crate::request::Algorithm::FRODO976.sizes();
+        let hybrid = crate::request::Algorithm::FRODO976__ECDHp384.sizes();
+        assert_eq!(hybrid.public_key_len, frodo.public_key_len + 97);
+        assert_eq!(hybrid.secret_key_len, frodo.secret_key_len + 48);
+        assert_eq!(hybrid.ciphertext_len, frodo.ciphertext_len + 97);
+        assert_eq!(hybrid.shared_secret_len, frodo.shared_secret_len + 48);
+
+        // Signature schemes have signatures instead of ciphertexts and shared secrets
+        let ml_dsa = crate::request::Algorithm::ML_DSA_65.sizes();
+        assert_eq!(ml_dsa.public_key_len, 1952);
+        assert_eq!(ml_dsa.secret_key_len, 4032);
+        assert_eq!(ml_dsa.signature_len, 3309);
+        assert_eq!(ml_dsa.ciphertext_len + ml_dsa.shared_secret_len, 0);
+        assert_eq!(kyber.signature_len, 0);
+
+        assert_eq!(
+            crate::request::Algorithm::NoAlgorithm.sizes(),
+            crate::request::AlgorithmSizes::default()
+        );
+    }
+
+    #[test]
+    fn test_algorithm_sizes_c() {
+        let mut sizes = crate::request::AlgorithmSizes::default();
+
+        unsafe {
+            let status = crate::request::algorithm_sizes(
+                crate::request::Algorithm::SABER__ECDHp384,
+                &mut sizes,
+            );
+            assert_eq!(status, 0);
+            assert_eq!(sizes, crate::request::Algorithm::SABER__ECDHp384.sizes());
+
+            let status = crate::request::algorithm_sizes(
+                crate::request::Algorithm::SABER,
+                std::ptr::null_mut(),
+            );
+            assert_eq!(status, -1);
+
+            let status =
+                crate::request::algorithm_sizes(crate::request::Algorithm::NoAlgorithm, &mut sizes);
+            assert_eq!(status, -2);
+        }
+    }
+
+    #[test]
+    fn test_validate_request_header() {
+        let mut request_header = crate::request::RequestHeader {
+            version: crate::FORMAT_VERSION,
+            identifier: 1234,
+            data_len: 1331,
+            algorithm: crate::request::Algorithm::NoAlgorithm,
+            operation: crate::request::Operation::Encapsulation,
+            deadline_ms: 0,
+            flags: 0,
+        };
+        assert!(matches!(
+            request_header.validate(),
+            Err(crate::MessageError::Placeholder { field: "algorithm" })
+        ));
+
+        request_header.algorithm = crate::request::Algorithm::KYBER_512;
+        request_header.operation = crate::request::Operation::NoOperation;
+        assert!(matches!(
+            request_header.validate(),
+            Err(crate::MessageError::Placeholder { field: "operation" })
+        ));
+
+        request_header.operation = crate::request::Operation::Sign;
+        assert!(matches!(
+            request_header.validate(),
+            Err(crate::MessageError::InvalidField {
+                field: "operation",
+                ..
+            })
+        ));
+
+        request_header.operation = crate::request::Operation::Decapsulation;
+        assert!(request_header.validate().is_ok());
+
+        // Signature schemes sign and verify but do not encapsulate
+        request_header.algorithm = crate::request::Algorithm::ML_DSA_44;
+        assert!(matches!(
+            request_header.validate(),
+            Err(crate::MessageError::InvalidField {
+                field: "operation",
+                ..
+            })
+        ));
+        for operation in [
+            crate::request::Operation::KeypairGeneration,
+            crate::request::Operation::Sign,
+            crate::request::Operation::Verify,
+        ]
+        .iter()
+        {
+            request_header.operation = *operation;
+            assert!(request_header.validate().is_ok());
+        }
+        assert!(crate::request::serialize_request_header_vec(
+            1234,
+            1331,
+            crate::request::Algorithm::ML_DSA_87,
+            crate::request::Operation::Sign,
+            0,
+        )
+        .is_ok());
+
+        // Invalid headers must never be serialized
+        assert!(crate::request::serialize_request_header_vec(
+            1234,
+            1331,
+            crate::request::Algorithm::NoAlgorithm,
+            crate::request::Operation::Encapsulation,
+            0,
+        )
+        .is_err());
+        let mut buffer = vec![0; crate::request::get_serialized_request_header_size() as usize];
+        let status = unsafe {
+            crate::request::serialize_request_header(
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                1234,
+                1331,
+                crate::request::Algorithm::KYBER_512,
+                crate::request::Operation::NoOperation,
+                0,
+            )
+        };
+        assert_eq!(status, -3);
+        let status = unsafe {
+            crate::request::serialize_request_header(
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                1234,
+                1331,
+                crate::request::Algorithm::KYBER_512,
+                crate::request::Operation::Sign,
+                0,
+            )
+        };
+        assert_eq!(status, -2);
+
+        // A default header is fine for computing sizes but is never sent
+        let request_header = crate::request::RequestHeader::default();
+        assert_eq!(
+            request_header.to_bytes().len(),
+            crate::request::get_serialized_request_header_size() as usize
+        );
+        let mut writer = crate::request::RequestStreamWriter::new();
+        assert!(matches!(
+            writer.push(&request_header, &[]),
+            Err(crate::MessageError::Placeholder { field: "algorithm" })
+        ));
+        assert!(matches!(
+            crate::request::RequestHeader::builder()
+                .algorithm(crate::request::Algorithm::KYBER_512)
+                .build(),
+            Err(crate::MessageError::Placeholder { field: "operation" })
+        ));
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn test_checksum_round_trip() {
+        assert_eq!(crate::checksum::crc32c(b"123456789"), 0xE306_9283);
+
+        let mut response = crate::response::serialize_response(1234, &[0, 1, 2, 3, 4, 5]).unwrap();
+        let header_size = crate::response::get_serialized_response_header_size() as usize;
+        assert_eq!(
+            response.len(),
+            header_size + 6 + crate::checksum::CHECKSUM_SIZE
+        );
+
+        let mut response_header: crate::response::ResponseHeader = Default::default();
+        let status = unsafe {
+            crate::response::deserialize_response_header(response.as_ptr(), &mut response_header)
+        };
+        assert_eq!(status, 0);
+
+        // Flip a byte in the body
+        response[header_size + 2] ^= 1;
+        let status = unsafe {
+            crate::response::deserialize_response_header(response.as_ptr(), &mut response_header)
+        };
+        assert_eq!(status, -5);
+        assert!(matches!(
+            crate::checksum::verify_checksum(&response),
+            Err(crate::MessageError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "checksum")]
+    #[test]
+    fn test_checksum_request_c() {
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        let body = [13, 12, 18, 33];
+        let mut message = vec![0; header_size + body.len() + crate::checksum::get_checksum_size()];
+
+        unsafe {
+            let status = crate::request::serialize_request_header(
+                message.as_mut_ptr(),
+                message.len(),
+                1234,
+                body.len() as u32,
+                crate::request::Algorithm::KYBER_512,
+                crate::request::Operation::Encapsulation,
+                0,
+            );
+            assert_eq!(status, header_size as i64);
+            message[header_size..header_size + body.len()].copy_from_slice(&body);
+
+            let status = crate::checksum::write_checksum(message.as_mut_ptr(), message.len());
+            assert_eq!(status, 0);
+        }
+
+        let contents = crate::checksum::verify_checksum(&message).unwrap();
+        assert_eq!(&contents[header_size..], &body);
+
+        message[header_size] ^= 1;
+        assert!(crate::checksum::verify_checksum(&message).is_err());
+    }
+
+    #[cfg(all(feature = "serde", not(feature = "uuid")))]
+    #[test]
+    fn test_bincode_config_little_endian() {
+        let header = crate::request::RequestHeader {
+            version: crate::FORMAT_VERSION,
+            identifier: 0x0102_0304_0506_0708,
+            data_len: 0x0A0B_0C0D,
+            algorithm: crate::request::Algorithm::KYBER_768,
+            operation: crate::request::Operation::Decapsulation,
+            deadline_ms: 0,
+            flags: 0,
+        };
+        let serialized = header.to_bytes();
+        assert_eq!(
+            serialized,
+            vec![
+                crate::FORMAT_VERSION, 8, 7, 6, 5, 4, 3, 2, 1, 0x0D, 0x0C, 0x0B, 0x0A, 19, 0, 0, 0,
+                3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            ]
+        );
+
+        // The big-endian encoding of the same header must not be understood as the same header
+        let big_endian =
+            bincode::serde::encode_to_vec(&header, crate::BINCODE_CONFIG.with_big_endian())
+                .unwrap();
+        assert_ne!(big_endian, serialized);
+        assert!(crate::request::deserialize_request_header(&big_endian).is_err());
+
+        let deserialized = crate::request::deserialize_request_header(&serialized).unwrap();
+        assert!(deserialized == header);
+
+        let response = crate::response::serialize_response(0x0102_0304_0506_0708, &[]).unwrap();
+        assert_eq!(
+            &response[..9],
+            &[crate::FORMAT_VERSION, 8, 7, 6, 5, 4, 3, 2, 1]
+        );
+        assert_eq!(&response[9..15], &[0; 6]);
+    }
+
+    #[test]
+    fn test_reserved_flags_round_trip() {
+        let header = crate::request::RequestHeader::builder()
+            .identifier(7)
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::KeypairGeneration)
+            .flags(0xA5)
+            .build()
+            .unwrap();
+        let deserialized = crate::request::deserialize_request_header(&header.to_bytes()).unwrap();
+        assert_eq!(deserialized.flags, 0xA5);
+
+        let mut flags = crate::response::ResponseFlags::default();
+        assert!(!flags.contains(crate::response::ResponseFlags::COMPRESSED));
+        flags.insert(crate::response::ResponseFlags::COMPRESSED);
+        assert_eq!(flags.bits(), crate::response::RESPONSE_FLAG_COMPRESSED);
+        flags.insert(crate::response::ResponseFlags::from_bits(0x80));
+        flags.remove(crate::response::ResponseFlags::COMPRESSED);
+        assert_eq!(flags.bits(), 0x80);
+        assert!(crate::response::ResponseFlags::RESERVED.contains(flags));
+
+        // Bits that this version does not know are kept
+        let mut header = crate::response::ResponseHeader::default();
+        header.set_response_flags(crate::response::ResponseFlags::from_bits(0xFC));
+        let deserialized = crate::response::ResponseHeader::from_bytes(&header.to_bytes()).unwrap();
+        assert_eq!(deserialized.flags, 0xFC);
+        assert!(!deserialized.is_compressed());
+        assert_eq!(
+            deserialized.response_flags(),
+            crate::response::ResponseFlags::RESERVED
+        );
+    }
+
+    #[test]
+    fn test_layout_offsets() {
+        use crate::layout::*;
+        use std::convert::TryInto;
+
+        let identifier: crate::Identifier = 0x0102_0304_0506_0708;
+        let header = crate::request::RequestHeader::builder()
+            .identifier(identifier)
+            .data_len(0x0A0B_0C0D)
+            .algorithm(crate::request::Algorithm::SABER)
+            .operation(crate::request::Operation::Decapsulation)
+            .deadline_ms(0x1112_1314_1516_1718)
+            .flags(0x19)
+            .build()
+            .unwrap();
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), REQUEST_HEADER_SIZE);
+        assert_eq!(
+            REQUEST_HEADER_SIZE as u64,
+            crate::request::get_serialized_request_header_size()
+        );
+        assert_eq!(bytes[REQUEST_VERSION_OFFSET], crate::FORMAT_VERSION);
+        assert_eq!(
+            &bytes[REQUEST_IDENTIFIER_OFFSET..REQUEST_DATA_LEN_OFFSET],
+            &identifier.to_le_bytes()
+        );
+        let u32_at =
+            |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        assert_eq!(u32_at(REQUEST_DATA_LEN_OFFSET), 0x0A0B_0C0D);
+        assert_eq!(
+            u32_at(REQUEST_ALGORITHM_OFFSET),
+            crate::request::Algorithm::SABER as u32
+        );
+        assert_eq!(
+            u32_at(REQUEST_OPERATION_OFFSET),
+            crate::request::Operation::Decapsulation as u32
+        );
+        assert_eq!(
+            &bytes[REQUEST_DEADLINE_MS_OFFSET..REQUEST_FLAGS_OFFSET],
+            &0x1112_1314_1516_1718u64.to_le_bytes()
+        );
+        assert_eq!(bytes[REQUEST_FLAGS_OFFSET], 0x19);
+
+        let mut header = crate::response::ResponseHeader::new(
+            identifier,
+            crate::response::ResponseStatus::Ok,
+            0x0A0B_0C0D,
+        );
+        header.flags = 0x19;
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), RESPONSE_HEADER_SIZE);
+        assert_eq!(
+            RESPONSE_HEADER_SIZE as u64,
+            crate::response::get_serialized_response_header_size()
+        );
+        assert_eq!(bytes[RESPONSE_VERSION_OFFSET], crate::FORMAT_VERSION);
+        assert_eq!(
+            &bytes[RESPONSE_IDENTIFIER_OFFSET..RESPONSE_SUCCESS_OFFSET],
+            &identifier.to_le_bytes()
+        );
+        assert_eq!(bytes[RESPONSE_SUCCESS_OFFSET], 0);
+        assert_eq!(
+            &bytes[RESPONSE_DATA_LEN_OFFSET..RESPONSE_FLAGS_OFFSET],
+            &0x0A0B_0C0Du32.to_le_bytes()
+        );
+        assert_eq!(bytes[RESPONSE_FLAGS_OFFSET], 0x19);
+    }
+
+    // The layout documented in the README, which implementations in other languages rely on
+    #[cfg(not(feature = "uuid"))]
+    #[test]
+    fn test_wire_layout() {
+        let header = crate::request::RequestHeader {
+            version: crate::FORMAT_VERSION,
+            identifier: 0x0102_0304_0506_0708,
+            data_len: 0x0A0B_0C0D,
+            algorithm: crate::request::Algorithm::SABER_FIRE__ECDHp521,
+            operation: crate::request::Operation::Hello,
+            deadline_ms: 0x1112_1314_1516_1718,
+            flags: 0x19,
+        };
+        assert_eq!(
+            header.to_bytes(),
+            vec![
+                10, 8, 7, 6, 5, 4, 3, 2, 1, 0x0D, 0x0C, 0x0B, 0x0A, 28, 0, 0, 0, 6, 0, 0, 0, 0x18,
+                0x17, 0x16, 0x15, 0x14, 0x13, 0x12, 0x11, 0x19,
+            ]
+        );
+
+        let header = crate::response::ResponseHeader {
+            version: crate::FORMAT_VERSION,
+            identifier: 0x0102_0304_0506_0708,
+            success: crate::response::ResponseStatus::InvalidInput as i8,
+            data_len: 0x0A0B_0C0D,
+            flags: crate::response::RESPONSE_FLAG_COMPRESSED,
+        };
+        assert_eq!(
+            header.to_bytes(),
+            vec![10, 8, 7, 6, 5, 4, 3, 2, 1, 0xFD, 0x0D, 0x0C, 0x0B, 0x0A, 1]
+        );
+
+        // Length prefixes are fixed-width, even for an entry of 300 bytes which a varint would fit in 2 bytes
+        let entry = [0xAA; 300];
+        let structured = crate::response::structure_two_entries(&entry, &[]);
+        assert_eq!(&structured[..8], &[0x2C, 0x01, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&structured[308..], &[0; 8]);
+    }
+
+    // The wire format used to be whatever bincode produced, so the hand-written serialization must not deviate
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_bytes_matches_bincode() {
+        use std::convert::TryFrom;
+
+        for discriminant in 0..=28 {
+            for operation in 0..=6 {
+                let header = crate::request::RequestHeader {
+                    version: crate::FORMAT_VERSION,
+                    identifier: 0x0102_0304_0506_0708 * crate::Identifier::from(discriminant),
+                    data_len: 0xFFFF_FF00 | discriminant,
+                    algorithm: crate::request::Algorithm::try_from(discriminant).unwrap(),
+                    operation: crate::request::Operation::try_from(operation).unwrap(),
+                    deadline_ms: u64::MAX - u64::from(operation),
+                    flags: 0x80 | operation as u8,
+                };
+                let serialized = header.to_bytes();
+                assert_eq!(serialized, crate::encode(&header).unwrap());
+
+                let decoded: crate::request::RequestHeader = crate::decode(&serialized).unwrap();
+                assert!(crate::request::RequestHeader::from_bytes(&serialized).unwrap() == decoded);
+            }
+        }
+
+        for (success, flags) in [(0, 0), (-1, 0), (-128, 1), (127, 255)] {
+            let header = crate::response::ResponseHeader {
+                version: crate::FORMAT_VERSION,
+                identifier: crate::Identifier::MAX,
+                success,
+                data_len: 0x0A0B_0C0D,
+                flags,
+            };
+            let serialized = header.to_bytes();
+            assert_eq!(serialized, crate::encode(&header).unwrap());
+            assert_eq!(
+                crate::response::ResponseHeader::from_bytes(&serialized).unwrap(),
+                crate::decode(&serialized).unwrap()
+            );
+        }
+
+        let header_size = crate::response::get_serialized_response_header_size() as usize;
+        assert!(matches!(
+            crate::response::ResponseHeader::from_bytes(&vec![0; header_size - 1]),
+            Err(crate::MessageError::BufferTooShort { needed, got })
+                if needed == header_size && got == header_size - 1
+        ));
+    }
+
+    #[test]
+    fn test_length_prefix_is_u64() {
+        assert_eq!(crate::request::structure_two_entries_length(3, 2), 21);
+        assert_eq!(
+            crate::request::structure_two_entries_length(usize::MAX - 8, 0),
+            -1
+        );
+        assert_eq!(
+            crate::request::structure_two_entries_length(usize::MAX / 2, usize::MAX / 2),
+            -1
+        );
+
+        let structured = crate::response::structure_two_entries(&[1, 2, 3], &[4, 5]);
+        assert_eq!(
+            structured,
+            vec![3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3, 2, 0, 0, 0, 0, 0, 0, 0, 4, 5]
+        );
+
+        // A peer with 4-byte length prefixes would have produced this buffer for the same entries
+        let four_byte_prefixed: Vec<u8> = vec![3, 0, 0, 0, 1, 2, 3, 2, 0, 0, 0, 4, 5];
+        assert!(crate::request::destructure_two_entries(&four_byte_prefixed).is_err());
+        assert!(crate::request::destructure_entries(&four_byte_prefixed).is_err());
+
+        let mut entry1_length: libc::size_t = 0;
+        let mut entry2_length: libc::size_t = 0;
+        let mut entry1: *const libc::c_uchar = std::ptr::null();
+        let mut entry2: *const libc::c_uchar = std::ptr::null();
+        let status = unsafe {
+            crate::response::destructure_two_entries(
+                four_byte_prefixed.as_ptr(),
+                four_byte_prefixed.len(),
+                &mut entry1_length,
+                &mut entry2_length,
+                &mut entry1,
+                &mut entry2,
+            )
+        };
+        assert_eq!(status, -8);
+    }
+
+    #[test]
+    fn test_encode_data_len() {
+        assert_eq!(crate::encode_data_len(0), Ok(0));
+        assert_eq!(crate::encode_data_len(u32::MAX as usize), Ok(u32::MAX));
+        // A 32-bit target cannot even represent one byte more than fits
+        if let Some(too_long) = (u32::MAX as usize).checked_add(1) {
+            assert_eq!(
+                crate::encode_data_len(too_long),
+                Err(crate::MessageError::LengthOverflow)
             );
-            assert_ne!(status, 0);
         }
-        keys[0] = 6;
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_response_writer_reader_rust() {
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+
+        let mut writer =
+            crate::response::ResponseWriter::new(Vec::new(), 1234, data.len() as u32).unwrap();
+        for chunk in data.chunks(300) {
+            writer.write_chunk(chunk).unwrap();
+        }
+        assert_eq!(
+            writer.write_chunk(&[0]),
+            Err(crate::MessageError::LengthOverflow)
+        );
+        let streamed = writer.finish().unwrap();
+        // `serialize_response` would compress this data
+        #[cfg(not(feature = "compress"))]
+        assert_eq!(
+            streamed,
+            crate::response::serialize_response(1234, &data).unwrap()
+        );
+
+        let mut reader = crate::response::ResponseReader::new(streamed.as_slice()).unwrap();
+        assert_eq!(reader.header().identifier, 1234);
+        assert_eq!(reader.header().success, 0);
+        assert_eq!(reader.remaining(), data.len());
+
+        let mut read = Vec::new();
+        let mut buffer = [0; 128];
+        loop {
+            let chunk_len = reader.read_chunk(&mut buffer).unwrap();
+            if chunk_len == 0 {
+                break;
+            }
+            read.extend_from_slice(&buffer[..chunk_len]);
+        }
+        assert_eq!(read, data);
+        assert_eq!(reader.remaining(), 0);
+        assert!(reader.into_inner().is_empty());
+
+        // Not all data announced in the header was written
+        let mut writer = crate::response::ResponseWriter::new(Vec::new(), 1234, 6).unwrap();
+        writer.write_chunk(&[0, 1, 2]).unwrap();
+        assert!(writer.finish().is_err());
+
+        // Failure response without any data
+        let failure = crate::response::serialize_failure_response(
+            1234,
+            crate::response::ResponseStatus::OperationFailed,
+        )
+        .unwrap();
+        let mut reader = crate::response::ResponseReader::new(failure.as_slice()).unwrap();
+        assert_eq!(
+            reader.header().status(),
+            Some(crate::response::ResponseStatus::OperationFailed)
+        );
+        assert_eq!(reader.read_chunk(&mut buffer), Ok(0));
+
+        // Truncated data
+        let mut reader =
+            crate::response::ResponseReader::new(&streamed[..streamed.len() - 10]).unwrap();
+        let mut buffer = vec![0; data.len()];
+        assert_eq!(
+            reader.read_chunk(&mut buffer),
+            Err(crate::MessageError::Io(std::io::ErrorKind::UnexpectedEof))
+        );
+
+        // Truncated header
+        assert!(matches!(
+            crate::response::ResponseReader::new(&streamed[..5]),
+            Err(crate::MessageError::Io(std::io::ErrorKind::UnexpectedEof))
+        ));
+    }
+
+    #[test]
+    fn test_response_debug() {
+        let body = [0xAB; 6];
+        let response = crate::response::Response {
+            header: crate::response::ResponseHeader {
+                version: crate::FORMAT_VERSION,
+                identifier: 1234,
+                success: 0,
+                data_len: body.len() as u32,
+                flags: 0,
+            },
+            body: body.as_ptr(),
+        };
+
+        let formatted = format!("{:?}", response);
+        assert!(formatted.contains("identifier: 1234"));
+        assert!(formatted.contains("body_len: 6"));
+        assert!(!formatted.contains("171"));
+        assert_eq!(
+            format!("{:?}", response.header),
+            format!(
+                "ResponseHeader {{ version: {}, identifier: 1234, success: 0, data_len: 6, flags: 0 }}",
+                crate::FORMAT_VERSION
+            )
+        );
+    }
+
+    #[test]
+    fn test_hello() {
+        let request = crate::request::build_hello_request(1234);
+        let header = crate::request::deserialize_request_header(&request).unwrap();
+        assert!(header.operation == crate::request::Operation::Hello);
+        assert!(header.algorithm == crate::request::Algorithm::NoAlgorithm);
+        assert_eq!(header.data_len, 0);
+
+        // A Hello is accepted from any version, other requests are not
+        let mut request = request;
+        request[0] = crate::FORMAT_VERSION + 1;
+        assert!(crate::request::deserialize_request_header(&request).is_ok());
+        let mut encapsulation = crate::request::serialize_request_header_vec(
+            1234,
+            0,
+            crate::request::Algorithm::KYBER_512,
+            crate::request::Operation::Encapsulation,
+            0,
+        )
+        .unwrap();
+        encapsulation[0] = crate::FORMAT_VERSION + 1;
+        assert!(crate::request::deserialize_request_header(&encapsulation).is_err());
+
+        assert!(crate::request::serialize_request_header_vec(
+            1234,
+            0,
+            crate::request::Algorithm::KYBER_512,
+            crate::request::Operation::Hello,
+            0
+        )
+        .is_err());
+        assert!(crate::request::serialize_request_header_vec(
+            1234,
+            4,
+            crate::request::Algorithm::NoAlgorithm,
+            crate::request::Operation::Hello,
+            0
+        )
+        .is_err());
+
+        // The requester can read the response of a responder with another version
+        let mut response = crate::response::build_hello_response(1234).unwrap();
+        response[0] = crate::FORMAT_VERSION + 1;
+        let mut response_header: crate::response::ResponseHeader = Default::default();
+        let status = unsafe {
+            crate::response::deserialize_response_header(response.as_ptr(), &mut response_header)
+        };
+        assert_eq!(status, -4);
+        assert_eq!(response_header.identifier, 1234);
+
+        let header_size = crate::response::get_serialized_response_header_size() as usize;
+        let response_data = &response[header_size..header_size + response_header.data_len as usize];
+        let versions = crate::request::parse_hello_response(response_data).unwrap();
+        assert!(versions.contains(&crate::FORMAT_VERSION));
+
+        assert!(crate::request::parse_hello_response(&[]).is_err());
+    }
+
+    #[test]
+    fn test_cancel() {
+        use crate::request::{Algorithm, Operation};
+
+        // The identifier is that of the request being cancelled
+        let request = crate::request::build_cancel_request(1234);
+        let header = crate::request::deserialize_request_header(&request).unwrap();
+        assert!(header == crate::request::RequestHeader::cancel(1234));
+        assert!(header.operation == Operation::Cancel);
+        assert!(header.algorithm == Algorithm::NoAlgorithm);
+        assert_eq!(header.identifier, 1234);
+        assert_eq!(header.data_len, 0);
+        assert_eq!(header.version(), crate::FORMAT_VERSION);
+        assert!(header.validate().is_ok());
+        assert_eq!(
+            Operation::Cancel.request_body_shape(),
+            crate::request::BodyShape::Empty
+        );
+        assert_eq!(
+            Operation::Cancel.response_body_shape(),
+            crate::request::BodyShape::Empty
+        );
+
+        assert!(crate::request::serialize_request_header_vec(
+            1234,
+            0,
+            Algorithm::FRODO1344,
+            Operation::Cancel,
+            0
+        )
+        .is_err());
+        assert_eq!(
+            crate::request::serialize_request_header_vec(
+                1234,
+                4,
+                Algorithm::NoAlgorithm,
+                Operation::Cancel,
+                0
+            )
+            .err(),
+            Some(crate::MessageError::InvalidField {
+                field: "data_len",
+                reason: "Cancel has an empty body",
+            })
+        );
+
+        // The responder answers the cancelled request itself
+        let response = crate::response::serialize_failure_response(
+            1234,
+            crate::response::ResponseStatus::Cancelled,
+        )
+        .unwrap();
+        let (response_header, body) = crate::response::parse_response(&response).unwrap();
+        assert_eq!(response_header.identifier, 1234);
+        assert_eq!(
+            response_header.status(),
+            Some(crate::response::ResponseStatus::Cancelled)
+        );
+        assert_eq!(response_header.raw_status(), -6);
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn test_serialize_response_c() {
+        let data = [0, 1, 2, 3, 4, 5];
+        let expected = crate::response::serialize_response(1234, &data).unwrap();
+        let mut buffer = vec![0; 64];
+
+        unsafe {
+            let written = crate::response::serialize_response_c(
+                1234,
+                data.as_ptr(),
+                data.len(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            );
+            assert_eq!(written, expected.len() as i64);
+            assert_eq!(&buffer[..expected.len()], expected.as_slice());
+
+            // Failure response
+            let expected = crate::response::serialize_failure_response(
+                1234,
+                crate::response::ResponseStatus::OperationFailed,
+            )
+            .unwrap();
+            let written = crate::response::serialize_response_c(
+                1234,
+                std::ptr::null(),
+                data.len(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            );
+            assert_eq!(written, expected.len() as i64);
+            assert_eq!(&buffer[..expected.len()], expected.as_slice());
+
+            let status = crate::response::serialize_response_c(
+                1234,
+                data.as_ptr(),
+                data.len(),
+                std::ptr::null_mut(),
+                buffer.len(),
+            );
+            assert_eq!(status, -1);
+
+            let status = crate::response::serialize_response_c(
+                1234,
+                data.as_ptr(),
+                data.len(),
+                buffer.as_mut_ptr(),
+                expected.len(),
+            );
+            assert_eq!(status, -2);
+        }
+    }
+
+    // If this fails a field was added to or removed from a header. Update the size and increase FORMAT_VERSION!
+    #[test]
+    fn test_header_sizes() {
+        let request_header_size = crate::request::get_serialized_request_header_size();
+        assert_eq!(
+            crate::request::RequestHeader::default().to_bytes().len() as u64,
+            request_header_size
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            crate::encode(&crate::request::RequestHeader::default())
+                .unwrap()
+                .len() as u64,
+            request_header_size
+        );
+        #[cfg(not(feature = "uuid"))]
+        assert_eq!(request_header_size, 30);
+        #[cfg(feature = "uuid")]
+        assert_eq!(request_header_size, 38);
+
+        let response_header_size = crate::response::get_serialized_response_header_size();
+        assert_eq!(
+            crate::response::ResponseHeader::default().to_bytes().len() as u64,
+            response_header_size
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            crate::encode(&crate::response::ResponseHeader::default())
+                .unwrap()
+                .len() as u64,
+            response_header_size
+        );
+        #[cfg(not(feature = "uuid"))]
+        assert_eq!(response_header_size, 15);
+        #[cfg(feature = "uuid")]
+        assert_eq!(response_header_size, 23);
+    }
+
+    // The size functions only return the layout constants, so calling them is always safe from C
+    #[test]
+    fn test_header_size_functions() {
+        let request_header_size = crate::request::get_serialized_request_header_size();
+        assert_eq!(
+            request_header_size,
+            crate::layout::REQUEST_HEADER_SIZE as u64
+        );
+        assert_eq!(
+            request_header_size,
+            crate::request::RequestHeader::default().to_bytes().len() as u64
+        );
+
+        let response_header_size = crate::response::get_serialized_response_header_size();
+        assert_eq!(
+            response_header_size,
+            crate::layout::RESPONSE_HEADER_SIZE as u64
+        );
+        assert_eq!(
+            response_header_size,
+            crate::response::ResponseHeader::default().to_bytes().len() as u64
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn test_uuid_identifier() {
+        let uuid = uuid::Uuid::from_u128(0x0011_2233_4455_6677_8899_AABB_CCDD_EEFF);
+        let header = crate::request::RequestHeader::builder()
+            .uuid(uuid)
+            .data_len(1331)
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::Decapsulation)
+            .build()
+            .unwrap();
+        assert_eq!(header.identifier, uuid.as_u128());
+        assert!(header.uuid() == uuid);
+
+        let serialized = header.to_bytes();
+        assert_eq!(serialized.len(), 38);
+        assert_eq!(
+            serialized[0] & crate::UUID_FORMAT_VERSION_FLAG,
+            crate::UUID_FORMAT_VERSION_FLAG
+        );
+        assert_eq!(&serialized[1..17], &uuid.as_u128().to_le_bytes());
+        assert_eq!(&serialized[17..21], &1331u32.to_le_bytes());
+        assert_eq!(serialized[21], crate::request::Algorithm::KYBER_512 as u8);
+        assert_eq!(
+            serialized[25],
+            crate::request::Operation::Decapsulation as u8
+        );
+        let deserialized = crate::request::deserialize_request_header(&serialized).unwrap();
+        assert!(deserialized.uuid() == uuid);
+
+        // A header with a 64-bit identifier is rejected instead of misread
+        let mut narrow = serialized.clone();
+        narrow[0] &= !crate::UUID_FORMAT_VERSION_FLAG;
+        assert!(matches!(
+            crate::request::deserialize_request_header(&narrow),
+            Err(crate::MessageError::VersionMismatch { .. })
+        ));
+
+        let response = crate::response::serialize_response(header.identifier, &[1, 2, 3]).unwrap();
+        let (response_header, body) = crate::response::parse_response(&response).unwrap();
+        assert_eq!(&response[1..17], &uuid.as_u128().to_le_bytes());
+        assert!(response_header.uuid() == uuid);
+        assert_eq!(body, &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_free_response_body_c() {
+        let body = vec![13, 12, 18, 33];
+        let response = crate::response::Response::new(1234, body.clone()).unwrap();
+        assert_eq!(response.header.success, 0);
+        assert_eq!(response.header.data_len, 4);
+
+        unsafe {
+            let received =
+                std::slice::from_raw_parts(response.body, response.header.data_len as usize);
+            assert_eq!(received, body.as_slice());
+
+            crate::response::free_response_body(
+                response.body as *mut libc::c_uchar,
+                response.header.data_len as usize,
+            );
+
+            // Empty bodies and null pointers are fine as well
+            let empty = crate::response::Response::new(1234, Vec::new()).unwrap();
+            assert!(!empty.body.is_null());
+            crate::response::free_response_body(empty.body as *mut libc::c_uchar, 0);
+            crate::response::free_response_body(std::ptr::null_mut(), 0);
+        }
+    }
+
+    #[test]
+    fn test_response_from_parts_c() {
+        let body = [13, 12, 18, 33];
+        let header =
+            crate::response::ResponseHeader::new(1234, crate::response::ResponseStatus::Ok, 4);
+        let mut response = std::mem::MaybeUninit::<crate::response::Response>::uninit();
+
+        unsafe {
+            let status = crate::response::response_from_parts(
+                header.clone(),
+                body.as_ptr(),
+                body.len(),
+                response.as_mut_ptr(),
+            );
+            assert_eq!(status, 0);
+            let response = response.assume_init();
+            assert_eq!(response.header, header);
+            assert_eq!(response.body, body.as_ptr());
+
+            let mut response = crate::response::Response {
+                header: Default::default(),
+                body: std::ptr::null(),
+            };
+            for body_len in [3, 5] {
+                let status = crate::response::response_from_parts(
+                    header.clone(),
+                    body.as_ptr(),
+                    body_len,
+                    &mut response,
+                );
+                assert_eq!(status, -3);
+                assert_eq!(response.header.data_len, 0);
+                assert!(response.body.is_null());
+            }
+
+            // An empty body may be a null pointer, a non-empty one may not
+            let empty =
+                crate::response::ResponseHeader::new(1234, crate::response::ResponseStatus::Ok, 0);
+            assert_eq!(
+                crate::response::response_from_parts(empty, std::ptr::null(), 0, &mut response),
+                0
+            );
+            assert_eq!(
+                crate::response::response_from_parts(
+                    header.clone(),
+                    std::ptr::null(),
+                    4,
+                    &mut response
+                ),
+                -2
+            );
+            assert_eq!(
+                crate::response::response_from_parts(
+                    header,
+                    body.as_ptr(),
+                    4,
+                    std::ptr::null_mut()
+                ),
+                -1
+            );
+        }
+    }
+
+    #[test]
+    fn test_request_stream() {
+        let requests = [
+            (
+                crate::request::Algorithm::KYBER_512,
+                crate::request::Operation::KeypairGeneration,
+                vec![],
+            ),
+            (
+                crate::request::Algorithm::KYBER_768,
+                crate::request::Operation::Encapsulation,
+                vec![13, 12, 18, 33],
+            ),
+            (
+                crate::request::Algorithm::FRODO976__ECDHp384,
+                crate::request::Operation::Decapsulation,
+                vec![0, 0, 2, 3, 1],
+            ),
+        ];
+
+        let mut writer = crate::request::RequestStreamWriter::new();
+        for (identifier, (algorithm, operation, body)) in requests.iter().enumerate() {
+            let header = crate::request::RequestHeader {
+                version: crate::FORMAT_VERSION,
+                identifier: identifier as crate::Identifier,
+                data_len: body.len() as u32,
+                algorithm: *algorithm,
+                operation: *operation,
+                deadline_ms: 0,
+                flags: 0,
+            };
+            writer.push(&header, body).unwrap();
+        }
+
+        // Nothing is appended for a header which does not describe its body
+        let header = crate::request::RequestHeader {
+            version: crate::FORMAT_VERSION,
+            identifier: 3,
+            data_len: 6,
+            algorithm: crate::request::Algorithm::KYBER_512,
+            operation: crate::request::Operation::Encapsulation,
+            deadline_ms: 0,
+            flags: 0,
+        };
+        assert_eq!(
+            writer.push(&header, &[1, 2, 3]),
+            Err(crate::MessageError::LengthMismatch {
+                expected: 6,
+                found: 3
+            })
+        );
+        let stream = writer.into_bytes();
+
+        let read: Vec<crate::request::Request> = crate::request::RequestStreamReader::new(&stream)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(read.len(), requests.len());
+        for (identifier, (request, (algorithm, operation, body))) in
+            read.iter().zip(requests.iter()).enumerate()
+        {
+            assert_eq!(request.header.identifier, identifier as crate::Identifier);
+            assert!(request.header.algorithm == *algorithm);
+            assert!(request.header.operation == *operation);
+            assert_eq!(*request.body, *body);
+        }
+
+        assert!(crate::request::RequestStreamReader::new(&[])
+            .next()
+            .is_none());
+
+        // A truncated final body is an error instead of a partial request
+        let mut reader = crate::request::RequestStreamReader::new(&stream[..stream.len() - 1]);
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(matches!(
+            reader.next(),
+            Some(Err(crate::MessageError::BufferTooShort { .. }))
+        ));
+        assert!(reader.next().is_none());
+
+        // The largest data_len is an error as well, the size of the request cannot wrap around
+        let header = crate::request::serialize_request_header_vec(
+            1,
+            u32::MAX,
+            crate::request::Algorithm::FRODO1344__ECDHp521,
+            crate::request::Operation::Decapsulation,
+            0,
+        )
+        .unwrap();
+        let mut reader = crate::request::RequestStreamReader::new(&header);
+        assert!(matches!(
+            reader.next(),
+            Some(Err(crate::MessageError::BufferTooShort { got, .. })) if got == header.len()
+        ));
+    }
+
+    #[test]
+    fn test_request_deadline() {
+        let mut header = crate::request::RequestHeader::default();
+        assert!(!header.is_expired(0));
+        assert!(!header.is_expired(u64::MAX));
+
+        header.deadline_ms = 1_600_000_000_000;
+        assert!(!header.is_expired(1_599_999_999_999));
+        assert!(!header.is_expired(1_600_000_000_000));
+        assert!(header.is_expired(1_600_000_000_001));
+
+        header.deadline_ms = u64::MAX;
+        assert!(!header.is_expired(u64::MAX));
+
+        let serialized = crate::request::serialize_request_header_vec(
+            1,
+            0,
+            crate::request::Algorithm::KYBER_768,
+            crate::request::Operation::KeypairGeneration,
+            1_600_000_000_000,
+        )
+        .unwrap();
+        let deserialized = crate::request::deserialize_request_header(&serialized).unwrap();
+        assert_eq!(deserialized.deadline_ms, 1_600_000_000_000);
+    }
+
+    // Goes through every algorithm and operation by their discriminants so a new or reordered variant
+    // cannot slip by, with identifiers, data lengths and deadlines drawn from a seeded xorshift generator.
+    #[test]
+    fn test_request_header_round_trip_all_combinations() {
+        use std::convert::TryFrom;
+
+        // Deterministic so that a failure can be reproduced
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        let algorithms: Vec<crate::request::Algorithm> = (0u32..)
+            .map_while(|discriminant| crate::request::Algorithm::try_from(discriminant).ok())
+            .collect();
+        let operations: Vec<crate::request::Operation> = (0u32..)
+            .map_while(|discriminant| crate::request::Operation::try_from(discriminant).ok())
+            .collect();
+        assert!(algorithms.last() == Some(&crate::request::Algorithm::ML_DSA_87));
+        assert!(operations.last() == Some(&crate::request::Operation::Cancel));
+
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        for (algorithm_index, &algorithm) in algorithms.iter().enumerate() {
+            assert_eq!(algorithm as usize, algorithm_index);
+            for (operation_index, &operation) in operations.iter().enumerate() {
+                assert_eq!(operation as usize, operation_index);
+                for _ in 0..8 {
+                    let mut header = crate::request::RequestHeader {
+                        version: crate::FORMAT_VERSION,
+                        identifier: next() as crate::Identifier,
+                        data_len: next() as u32,
+                        algorithm,
+                        operation,
+                        deadline_ms: next(),
+                        // serialize_request_header always writes 0
+                        flags: 0,
+                    };
+                    if operation == crate::request::Operation::Hello {
+                        header.data_len = 0;
+                    }
+
+                    let mut buffer = vec![0; header_size];
+                    let status = unsafe {
+                        crate::request::serialize_request_header(
+                            buffer.as_mut_ptr(),
+                            buffer.len(),
+                            header.identifier,
+                            header.data_len,
+                            header.algorithm,
+                            header.operation,
+                            header.deadline_ms,
+                        )
+                    };
+                    match header.validate() {
+                        Err(crate::MessageError::Placeholder { .. }) => {
+                            assert_eq!(status, -3, "{} {}", algorithm, operation);
+                            continue;
+                        }
+                        Err(_) => {
+                            assert_eq!(status, -2, "{} {}", algorithm, operation);
+                            continue;
+                        }
+                        Ok(()) => {}
+                    }
+                    assert_eq!(status, header_size as i64, "{} {}", algorithm, operation);
+
+                    let serialized = crate::request::serialize_request_header_vec(
+                        header.identifier,
+                        header.data_len,
+                        header.algorithm,
+                        header.operation,
+                        header.deadline_ms,
+                    )
+                    .unwrap();
+                    assert_eq!(serialized.len(), header_size);
+                    assert_eq!(serialized, buffer);
+
+                    let deserialized = crate::request::deserialize_request_header(&buffer).unwrap();
+                    assert!(deserialized == header, "{} {}", algorithm, operation);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "auth")]
+    #[test]
+    fn test_request_header_authenticated() {
+        let key = b"shared secret between both sides";
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        let mut serialized = crate::request::serialize_request_header_authenticated(
+            1234,
+            1331,
+            crate::request::Algorithm::KYBER_768,
+            crate::request::Operation::KeypairGeneration,
+            0,
+            key,
+        )
+        .unwrap();
+        assert_eq!(serialized.len(), header_size + crate::auth::AUTH_TAG_SIZE);
+
+        let header =
+            crate::request::deserialize_request_header_authenticated(&serialized, key).unwrap();
+        assert!(header.operation == crate::request::Operation::KeypairGeneration);
+        assert!(matches!(
+            crate::request::deserialize_request_header_authenticated(&serialized, b"other key"),
+            Err(crate::MessageError::AuthFailed)
+        ));
+        assert!(matches!(
+            crate::request::deserialize_request_header_authenticated(
+                &serialized[..header_size],
+                key
+            ),
+            Err(crate::MessageError::BufferTooShort { .. })
+        ));
+
+        // Turn the keypair generation into a decapsulation
+        serialized[17] = crate::request::Operation::Decapsulation as u8;
+        assert!(crate::request::deserialize_request_header(&serialized).is_ok());
+        assert!(matches!(
+            crate::request::deserialize_request_header_authenticated(&serialized, key),
+            Err(crate::MessageError::AuthFailed)
+        ));
+
+        // RFC 4231 test case 2
+        assert_eq!(
+            crate::auth::compute_tag(b"Jefe", b"what do ya want for nothing?")[..4],
+            [0x5b, 0xdc, 0xc1, 0x46]
+        );
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn test_compressed_response() {
+        let header_size = crate::response::get_serialized_response_header_size() as usize;
+        let data: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let response = crate::response::serialize_response(1234, &data).unwrap();
+
+        let header = crate::response::ResponseHeader::from_bytes(&response).unwrap();
+        assert!(header.is_compressed());
+        assert!((header.data_len as usize) < data.len());
+        let body = &response[header_size..];
+        assert_eq!(
+            crate::response::decompress_response_body(&header, body).unwrap(),
+            data
+        );
+
+        unsafe {
+            let size = crate::response::get_decompressed_response_body_size(&header, body.as_ptr());
+            assert_eq!(size, data.len() as i64);
+
+            let mut buffer = vec![0; data.len()];
+            let written = crate::response::decompress_response_body_c(
+                &header,
+                body.as_ptr(),
+                buffer.as_mut_ptr(),
+                buffer.len() - 1,
+            );
+            assert_eq!(written, -2);
+            let written = crate::response::decompress_response_body_c(
+                &header,
+                body.as_ptr(),
+                buffer.as_mut_ptr(),
+                buffer.len(),
+            );
+            assert_eq!(written, data.len() as i64);
+            assert_eq!(buffer, data);
+        }
+
+        // Corrupt compressed data must not decompress
+        let mut corrupt = body[..header.data_len as usize].to_vec();
+        corrupt[0] ^= 0xFF;
+        assert!(crate::response::decompress_response_body(&header, &corrupt).is_err());
+
+        // Data which does not get smaller is sent as is
+        let response = crate::response::serialize_response(1234, &[0, 1, 2, 3, 4, 5]).unwrap();
+        let header = crate::response::ResponseHeader::from_bytes(&response).unwrap();
+        assert!(!header.is_compressed());
+        assert_eq!(
+            crate::response::decompress_response_body(&header, &response[header_size..]).unwrap(),
+            vec![0, 1, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_pending_requests() {
+        let mut pending = crate::request::PendingRequests::new();
+        for identifier in 1..=3 {
+            let header = crate::request::RequestHeader::builder()
+                .identifier(identifier)
+                .algorithm(crate::request::Algorithm::KYBER_768)
+                .operation(crate::request::Operation::KeypairGeneration)
+                .build()
+                .unwrap();
+            pending.register(header).unwrap();
+        }
+        assert_eq!(pending.len(), 3);
+
+        // The identifier of an outstanding request cannot be used again
+        let duplicate = crate::request::RequestHeader::builder()
+            .identifier(2)
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::KeypairGeneration)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            pending.register(duplicate),
+            Err(crate::MessageError::InvalidField { .. })
+        ));
+
+        let response = crate::response::ResponseHeader {
+            identifier: 2,
+            ..Default::default()
+        };
+        let request = pending.match_response(&response).unwrap();
+        assert_eq!(request.identifier, 2);
+        assert!(request.algorithm == crate::request::Algorithm::KYBER_768);
+        assert_eq!(pending.len(), 2);
+
+        // A second response to the same request and a response to an unknown request
+        assert!(pending.match_response(&response).is_none());
+        let response = crate::response::ResponseHeader {
+            identifier: 4,
+            ..Default::default()
+        };
+        assert!(pending.match_response(&response).is_none());
+
+        // Once answered the identifier can be reused
+        let reused = crate::request::RequestHeader::builder()
+            .identifier(2)
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::KeypairGeneration)
+            .build()
+            .unwrap();
+        pending.register(reused).unwrap();
+        assert!(!pending.is_empty());
+    }
+
+    #[test]
+    fn test_algorithm_components() {
+        use std::convert::TryFrom;
+        use std::str::FromStr;
+
+        let algorithms: Vec<crate::request::Algorithm> = (1u32..)
+            .map_while(|discriminant| crate::request::Algorithm::try_from(discriminant).ok())
+            .collect();
+        for algorithm in algorithms {
+            let name = algorithm.as_str();
+            assert_eq!(algorithm.is_hybrid(), name.contains("__ECDH"), "{}", name);
+
+            let (pq_scheme, classical_scheme) = algorithm.components().unwrap();
+            assert_eq!(classical_scheme.is_some(), algorithm.is_hybrid());
+            if let Some((pq_name, classical_name)) = name.split_once("__") {
+                // The bare variant has the same post-quantum scheme
+                let bare = crate::request::Algorithm::from_str(pq_name).unwrap();
+                assert_eq!(bare.components(), Some((pq_scheme, None)));
+                assert_eq!(format!("{:?}", classical_scheme.unwrap()), classical_name);
+            } else {
+                assert_eq!(format!("{:?}", pq_scheme), name);
+            }
+        }
+
+        assert_eq!(
+            crate::request::Algorithm::KYBER_768__ECDHp384.components(),
+            Some((
+                crate::request::PqScheme::KYBER_768,
+                Some(crate::request::ClassicalScheme::ECDHp384)
+            ))
+        );
+        assert_eq!(crate::request::Algorithm::NoAlgorithm.components(), None);
+        assert!(!crate::request::Algorithm::NoAlgorithm.is_hybrid());
+    }
+
+    #[test]
+    fn test_algorithm_security_level() {
+        use crate::request::Algorithm;
+        use std::convert::TryFrom;
+
+        let expected = [
+            (Algorithm::NoAlgorithm, None),
+            (Algorithm::FRODO640__ECDHp256, Some(1)),
+            (Algorithm::FRODO640, Some(1)),
+            (Algorithm::FRODO976__ECDHp384, Some(3)),
+            (Algorithm::FRODO976, Some(3)),
+            (Algorithm::FRODO1344__ECDHp521, Some(5)),
+            (Algorithm::FRODO1344, Some(5)),
+            (Algorithm::NTRU_HRSS_701, Some(3)),
+            (Algorithm::NTRU_HRSS_701__ECDHp256, Some(3)),
+            (Algorithm::NTRU_HPS_2048509, Some(1)),
+            (Algorithm::NTRU_HPS_2048509__ECDHp256, Some(1)),
+            (Algorithm::RND5_1CCA_5D, Some(1)),
+            (Algorithm::RND5_1CCA_5D__ECDHp256, Some(1)),
+            (Algorithm::RND5_3CCA_5D, Some(3)),
+            (Algorithm::RND5_3CCA_5D__ECDHp384, Some(3)),
+            (Algorithm::RND5_5CCA_5D, Some(5)),
+            (Algorithm::RND5_5CCA_5D__ECDHp521, Some(5)),
+            (Algorithm::KYBER_512, Some(1)),
+            (Algorithm::KYBER_512__ECDHp256, Some(1)),
+            (Algorithm::KYBER_768, Some(3)),
+            (Algorithm::KYBER_768__ECDHp384, Some(3)),
+            (Algorithm::KYBER_1024, Some(5)),
+            (Algorithm::KYBER_1024__ECDHp521, Some(5)),
+            (Algorithm::SABER_LIGHT, Some(1)),
+            (Algorithm::SABER_LIGHT__ECDHp256, Some(1)),
+            (Algorithm::SABER, Some(3)),
+            (Algorithm::SABER__ECDHp384, Some(3)),
+            (Algorithm::SABER_FIRE, Some(5)),
+            (Algorithm::SABER_FIRE__ECDHp521, Some(5)),
+            (Algorithm::ML_KEM_512, Some(1)),
+            (Algorithm::ML_KEM_512__ECDHp256, Some(1)),
+            (Algorithm::ML_KEM_768, Some(3)),
+            (Algorithm::ML_KEM_768__ECDHp384, Some(3)),
+            (Algorithm::ML_KEM_1024, Some(5)),
+            (Algorithm::ML_KEM_1024__ECDHp521, Some(5)),
+            (Algorithm::ML_DSA_44, Some(2)),
+            (Algorithm::ML_DSA_65, Some(3)),
+            (Algorithm::ML_DSA_87, Some(5)),
+        ];
+        // Every variant is listed, in the order of the discriminants
+        assert_eq!(expected.len() as u32, crate::request::ALGORITHM_COUNT);
+        for (discriminant, (algorithm, level)) in expected.iter().enumerate() {
+            assert!(Algorithm::try_from(discriminant as u32).unwrap() == *algorithm);
+            assert_eq!(algorithm.security_level(), *level, "{}", algorithm);
+        }
+
+        assert!(Algorithm::NoAlgorithm.security_level() < Some(3));
+        assert!(Algorithm::KYBER_512__ECDHp256.security_level() < Some(3));
+        assert!(Algorithm::KYBER_768.security_level() >= Some(3));
+    }
+
+    #[test]
+    fn test_bounded_header_deserialization() {
+        use std::convert::TryFrom;
+
+        let max = crate::request::RECOMMENDED_MAX_DATA_LEN;
+        // The cap fits the data of every operation of every algorithm
+        let algorithms: Vec<crate::request::Algorithm> = (1u32..)
+            .map_while(|discriminant| crate::request::Algorithm::try_from(discriminant).ok())
+            .collect();
+        for algorithm in algorithms {
+            let sizes = algorithm.sizes();
+            let prefixes = 2 * crate::LENGTH_PREFIX_SIZE;
+            assert!(sizes.secret_key_len + sizes.ciphertext_len + prefixes <= max as usize);
+            assert!(sizes.public_key_len + sizes.secret_key_len + prefixes <= max as usize);
+        }
+
+        let request = |data_len| {
+            crate::request::serialize_request_header_vec(
+                1,
+                data_len,
+                crate::request::Algorithm::FRODO1344__ECDHp521,
+                crate::request::Operation::Decapsulation,
+                0,
+            )
+            .unwrap()
+        };
+        let header =
+            crate::request::deserialize_request_header_bounded(&request(max), max).unwrap();
+        assert_eq!(header.data_len, max);
+        assert_eq!(
+            crate::request::deserialize_request_header_bounded(&request(u32::MAX), max).err(),
+            Some(crate::MessageError::BodyTooLarge {
+                max,
+                found: u32::MAX
+            })
+        );
+
+        let response = |data_len| {
+            crate::response::ResponseHeader {
+                version: crate::FORMAT_VERSION,
+                identifier: 1,
+                success: 0,
+                data_len,
+                flags: 0,
+            }
+            .to_bytes()
+        };
+        let header =
+            crate::response::deserialize_response_header_bounded(&response(1024), max).unwrap();
+        assert_eq!(header.data_len, 1024);
+        assert_eq!(
+            crate::response::deserialize_response_header_bounded(&response(max + 1), max).err(),
+            Some(crate::MessageError::BodyTooLarge {
+                max,
+                found: max + 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_body_shapes() {
+        use crate::request::{BodyShape, Operation};
+
+        assert_eq!(
+            Operation::Decapsulation.request_body_shape(),
+            BodyShape::TwoEntries
+        );
+        assert_eq!(
+            Operation::KeypairGeneration.request_body_shape(),
+            BodyShape::Empty
+        );
+        assert_eq!(
+            Operation::Encapsulation.response_body_shape(),
+            BodyShape::TwoEntries
+        );
+        assert_eq!(
+            Operation::Decapsulation.response_body_shape(),
+            BodyShape::Single
+        );
+
+        let two_entries = crate::response::structure_two_entries(&[1, 2, 3], &[4]);
+        assert!(BodyShape::TwoEntries.check(&two_entries).is_ok());
+        assert!(BodyShape::Single.check(&two_entries).is_ok());
+        assert_eq!(
+            BodyShape::Empty.check(&two_entries),
+            Err(crate::MessageError::LengthMismatch {
+                expected: 0,
+                found: two_entries.len()
+            })
+        );
+        assert!(BodyShape::Empty.check(&[]).is_ok());
+
+        let three_entries = crate::response::structure_entries(&[&[1], &[2], &[3]]).unwrap();
+        assert!(matches!(
+            BodyShape::TwoEntries.check(&three_entries),
+            Err(crate::MessageError::InvalidField { field: "data", .. })
+        ));
+        assert!(BodyShape::TwoEntries.check(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_request_bytes_round_trip() {
+        let request = crate::request::Request {
+            header: crate::request::RequestHeader::builder()
+                .identifier(9)
+                .data_len(3)
+                .algorithm(crate::request::Algorithm::KYBER_512)
+                .operation(crate::request::Operation::Encapsulation)
+                .build()
+                .unwrap(),
+            body: vec![1, 2, 3].into(),
+        };
+        let bytes = request.to_bytes().unwrap();
+        let mut expected = request.header.to_bytes();
+        expected.extend(request.body.iter());
+        assert_eq!(&bytes[..expected.len()], expected.as_slice());
+
+        let read = crate::request::Request::from_bytes(&bytes).unwrap();
+        assert!(read.header == request.header);
+        assert_eq!(read.body, request.body);
+
+        // Cut off in the body
+        let short = &bytes[..bytes.len() - 1];
+        assert_eq!(
+            crate::request::Request::from_bytes(short).err(),
+            Some(crate::MessageError::BufferTooShort {
+                needed: bytes.len(),
+                got: short.len()
+            })
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            crate::request::Request::from_bytes(&trailing).err(),
+            Some(crate::MessageError::LengthMismatch {
+                expected: bytes.len(),
+                found: trailing.len()
+            })
+        );
+
+        let mismatched = crate::request::Request {
+            header: request.header,
+            body: vec![1].into(),
+        };
+        assert!(matches!(
+            mismatched.to_bytes(),
+            Err(crate::MessageError::LengthMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_header_constructors_stamp_version() {
+        assert_eq!(
+            crate::request::RequestHeader::default().version(),
+            crate::FORMAT_VERSION
+        );
+        assert_eq!(
+            crate::response::ResponseHeader::default().version(),
+            crate::FORMAT_VERSION
+        );
+
+        let header = crate::response::ResponseHeader::new(
+            7,
+            crate::response::ResponseStatus::InvalidInput,
+            0,
+        );
+        assert_eq!(header.version(), crate::FORMAT_VERSION);
+        assert_eq!(
+            header.status(),
+            Some(crate::response::ResponseStatus::InvalidInput)
+        );
+        let header = crate::response::deserialize_response_header_bounded(&header.to_bytes(), 0);
+        assert_eq!(header.unwrap().identifier, 7);
+    }
+
+    #[cfg(feature = "std")]
+    struct MockStream {
+        input: std::io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    #[cfg(feature = "std")]
+    impl std::io::Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
 
-        keys[14] = 255;
-        unsafe {
-            let status = crate::response::destructure_two_entries(
-                keys.as_ptr(),
-                keys_buffer_len,
-                &mut pub_key_length,
-                &mut priv_key_length,
-                &mut pub_key,
-                &mut priv_key,
+    #[cfg(feature = "std")]
+    impl std::io::Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_channel() {
+        let header = |identifier| {
+            crate::request::RequestHeader::builder()
+                .identifier(identifier)
+                .data_len(3)
+                .algorithm(crate::request::Algorithm::KYBER_512)
+                .operation(crate::request::Operation::Encapsulation)
+                .build()
+                .unwrap()
+        };
+        let body = [1, 2, 3];
+
+        // The responses arrive out of order
+        let mut input = crate::response::serialize_response(2, &[2; 100]).unwrap();
+        input.extend(crate::response::serialize_response(1, &[1; 100]).unwrap());
+        input.extend(
+            crate::response::serialize_failure_response(
+                3,
+                crate::response::ResponseStatus::InvalidInput,
+            )
+            .unwrap(),
+        );
+        let stream = MockStream {
+            input: std::io::Cursor::new(input),
+            output: Vec::new(),
+        };
+
+        let mut channel = crate::channel::Channel::new(stream);
+        channel.send(header(1), &body).unwrap();
+        channel.send(header(2), &body).unwrap();
+        assert!(matches!(
+            channel.send(header(2), &body),
+            Err(crate::MessageError::InvalidField { .. })
+        ));
+        assert_eq!(channel.receive(1).unwrap(), vec![1; 100]);
+        assert_eq!(channel.receive(2).unwrap(), vec![2; 100]);
+        assert_eq!(
+            channel.request(header(3), &body).err(),
+            Some(crate::MessageError::RequestFailed(
+                crate::response::ResponseStatus::InvalidInput as i8
+            ))
+        );
+        assert!(matches!(
+            channel.receive(1),
+            Err(crate::MessageError::InvalidField { .. })
+        ));
+
+        let mut expected = Vec::new();
+        for identifier in [1, 2, 3].iter() {
+            let request = crate::request::Request {
+                header: header(*identifier),
+                body: body.to_vec().into(),
+            };
+            expected.extend(request.to_bytes().unwrap());
+        }
+        assert_eq!(channel.into_inner().output, expected);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_channel_cancel() {
+        let header = |identifier| {
+            crate::request::RequestHeader::builder()
+                .identifier(identifier)
+                .algorithm(crate::request::Algorithm::FRODO1344)
+                .operation(crate::request::Operation::KeypairGeneration)
+                .build()
+                .unwrap()
+        };
+
+        // The responder stops the first request in time, the second one not
+        let mut input = crate::response::serialize_failure_response(
+            1,
+            crate::response::ResponseStatus::Cancelled,
+        )
+        .unwrap();
+        input.extend(crate::response::serialize_response(2, &[2; 16]).unwrap());
+        let stream = MockStream {
+            input: std::io::Cursor::new(input),
+            output: Vec::new(),
+        };
+
+        let mut channel = crate::channel::Channel::new(stream);
+        channel.send(header(1), &[]).unwrap();
+        channel.send(header(2), &[]).unwrap();
+        channel.cancel(1).unwrap();
+        channel.cancel(2).unwrap();
+        assert!(matches!(
+            channel.cancel(3),
+            Err(crate::MessageError::InvalidField {
+                field: "identifier",
+                ..
+            })
+        ));
+        assert!(matches!(
+            channel.send(crate::request::RequestHeader::cancel(1), &[]),
+            Err(crate::MessageError::InvalidField {
+                field: "operation",
+                ..
+            })
+        ));
+
+        assert_eq!(
+            channel.receive(1).err(),
+            Some(crate::MessageError::RequestFailed(
+                crate::response::ResponseStatus::Cancelled as i8
+            ))
+        );
+        assert_eq!(channel.receive(2).unwrap(), vec![2; 16]);
+        // Both requests were answered, so there is nothing left to cancel
+        assert!(channel.cancel(1).is_err());
+        assert!(channel.cancel(2).is_err());
+
+        let request = |identifier| {
+            crate::request::Request::empty_body(header(identifier))
+                .unwrap()
+                .to_bytes()
+                .unwrap()
+        };
+        let mut expected = request(1);
+        expected.extend(request(2));
+        expected.extend(crate::request::build_cancel_request(1));
+        expected.extend(crate::request::build_cancel_request(2));
+        assert_eq!(channel.into_inner().output, expected);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_channel_unexpected_response() {
+        let header = crate::request::RequestHeader::builder()
+            .identifier(1)
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::KeypairGeneration)
+            .build()
+            .unwrap();
+
+        let input = crate::response::serialize_response(9, &[1, 2, 3]).unwrap();
+        let stream = MockStream {
+            input: std::io::Cursor::new(input),
+            output: Vec::new(),
+        };
+        let mut channel = crate::channel::Channel::new(stream);
+        assert!(matches!(
+            channel.request(header, &[]),
+            Err(crate::MessageError::InvalidField {
+                field: "identifier",
+                ..
+            })
+        ));
+
+        // A response of exactly the maximum size is accepted, one byte more is refused
+        let input = crate::response::serialize_response(1, &[0; 16]).unwrap();
+        let header = crate::request::RequestHeader::builder()
+            .identifier(1)
+            .algorithm(crate::request::Algorithm::KYBER_512)
+            .operation(crate::request::Operation::KeypairGeneration)
+            .build()
+            .unwrap();
+        let stream = MockStream {
+            input: std::io::Cursor::new(input.clone()),
+            output: Vec::new(),
+        };
+        let mut channel = crate::channel::Channel::new(stream);
+        channel.set_max_message_size(input.len());
+        assert!(channel.request(header.clone(), &[]).is_ok());
+
+        let stream = MockStream {
+            input: std::io::Cursor::new(input.clone()),
+            output: Vec::new(),
+        };
+        let mut channel = crate::channel::Channel::new(stream);
+        channel.set_max_message_size(input.len() - 1);
+        assert_eq!(
+            channel.request(header.clone(), &[]).err(),
+            Some(crate::MessageError::MessageTooLarge {
+                max: input.len() - 1,
+                found: input.len()
+            })
+        );
+
+        let input =
+            crate::response::ResponseHeader::new(1, crate::response::ResponseStatus::Ok, u32::MAX)
+                .to_bytes();
+        let stream = MockStream {
+            input: std::io::Cursor::new(input),
+            output: Vec::new(),
+        };
+        let mut channel = crate::channel::Channel::new(stream);
+        assert!(matches!(
+            channel.request(header, &[]),
+            Err(crate::MessageError::MessageTooLarge {
+                max: crate::request::MAX_MESSAGE_SIZE,
+                ..
+            })
+        ));
+    }
+
+    // What a downstream crate would do to test its requester without a real responder
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn test_loopback_channel() {
+        let keypair = crate::response::structure_two_entries(&[1; 32], &[2; 64]);
+        let canned = keypair.clone();
+        let mut received = Vec::new();
+        let stream = crate::loopback::LoopbackChannel::new(|request: crate::request::Request| {
+            received.push(request.header.clone());
+            if request.header.operation == crate::request::Operation::KeypairGeneration {
+                crate::response::serialize_response(request.header.identifier, &canned).unwrap()
+            } else {
+                crate::response::serialize_failure_response(
+                    request.header.identifier,
+                    crate::response::ResponseStatus::InvalidInput,
+                )
+                .unwrap()
+            }
+        });
+
+        let mut channel = crate::channel::Channel::new(stream);
+        let header = crate::request::RequestHeader::keypair_generation(
+            7,
+            crate::request::Algorithm::KYBER_512,
+        )
+        .unwrap();
+        let response = channel.request(header.clone(), &[]).unwrap();
+        let (public_key, secret_key) = crate::request::destructure_two_entries(&response).unwrap();
+        assert_eq!(public_key, &[1; 32][..]);
+        assert_eq!(secret_key, &[2; 64][..]);
+
+        let encapsulation = crate::request::RequestHeader::encapsulation(
+            8,
+            crate::request::Algorithm::KYBER_512,
+            &[3; 4],
+        )
+        .unwrap();
+        assert_eq!(
+            channel.request(encapsulation, &[3; 4]),
+            Err(crate::MessageError::RequestFailed(
+                crate::response::ResponseStatus::InvalidInput as i8
+            ))
+        );
+        assert_eq!(channel.into_inner().unread(), 0);
+        assert!(received.len() == 2 && received[0] == header);
+
+        // Bytes that are not a request are an error of the stream
+        let mut stream = crate::loopback::LoopbackChannel::new(|_| Vec::new());
+        assert_eq!(
+            std::io::Write::write(&mut stream, &[0xFF; 64])
+                .unwrap_err()
+                .kind(),
+            std::io::ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn test_deserialize_request_header_length() {
+        let header_size = crate::request::get_serialized_request_header_size() as usize;
+        let mut serialized = crate::request::serialize_request_header_vec(
+            5,
+            4,
+            crate::request::Algorithm::KYBER_512,
+            crate::request::Operation::Encapsulation,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(
+            crate::request::deserialize_request_header(&serialized[..header_size - 1]).err(),
+            Some(crate::MessageError::BufferTooShort {
+                needed: header_size,
+                got: header_size - 1
+            })
+        );
+
+        // Only the header part of a header followed by its data is read
+        serialized.extend(&[0xff; 4]);
+        let header = crate::request::deserialize_request_header(&serialized).unwrap();
+        assert_eq!(header.identifier, 5);
+        assert_eq!(header.data_len, 4);
+        assert!(header.operation == crate::request::Operation::Encapsulation);
+    }
+
+    #[test]
+    fn test_headers_as_map_keys() {
+        let header = crate::request::RequestHeader::hello(7);
+        let mut requests = std::collections::HashSet::new();
+        assert!(requests.insert(header.clone()));
+        assert!(!requests.insert(crate::request::RequestHeader::hello(7)));
+        assert!(requests.insert(crate::request::RequestHeader::hello(8)));
+        assert!(requests.contains(&header));
+
+        let mut algorithms = std::collections::HashSet::new();
+        algorithms.insert(crate::request::Algorithm::KYBER_512);
+        algorithms.insert(crate::request::Algorithm::KYBER_512);
+        assert_eq!(algorithms.len(), 1);
+
+        let response = crate::response::ResponseHeader::default();
+        let mut responses = std::collections::HashMap::new();
+        responses.insert(response.clone(), crate::request::Operation::Hello);
+        assert!(responses[&response] == crate::request::Operation::Hello);
+    }
+
+    #[test]
+    fn test_algorithms_in_sets() {
+        use std::convert::TryFrom;
+
+        let mut algorithms: std::collections::HashSet<crate::request::Algorithm> =
+            crate::request::Algorithm::iter().collect();
+        algorithms.insert(crate::request::Algorithm::NoAlgorithm);
+        assert_eq!(algorithms.len(), crate::request::ALGORITHM_COUNT as usize);
+
+        // Sorting follows the discriminants, not the order the variants are listed in
+        let mut sorted: Vec<crate::request::Algorithm> = algorithms.into_iter().collect();
+        sorted.sort();
+        for (discriminant, algorithm) in sorted.iter().enumerate() {
+            assert!(
+                crate::request::Algorithm::try_from(discriminant as u32).unwrap() == *algorithm
             );
-            assert_ne!(status, 0);
         }
+        assert!(crate::request::Algorithm::ML_KEM_512 > crate::request::Algorithm::KYBER_1024);
 
-        let buffer = vec![];
-        unsafe {
-            let status = crate::response::destructure_two_entries(
-                buffer.as_ptr(),
-                buffer.len(),
-                &mut pub_key_length,
-                &mut priv_key_length,
-                &mut pub_key,
-                &mut priv_key,
+        let operations: std::collections::BTreeSet<crate::request::Operation> = [
+            crate::request::Operation::Hello,
+            crate::request::Operation::KeypairGeneration,
+        ]
+        .iter()
+        .copied()
+        .collect();
+        assert!(operations.iter().next() == Some(&crate::request::Operation::KeypairGeneration));
+    }
+
+    // The discriminants go on the wire, so none of them may ever change
+    #[test]
+    fn test_discriminant_values() {
+        use crate::request::{Algorithm, Operation};
+
+        let algorithms = [
+            (Algorithm::NoAlgorithm, 0),
+            (Algorithm::FRODO640__ECDHp256, 1),
+            (Algorithm::FRODO640, 2),
+            (Algorithm::FRODO976__ECDHp384, 3),
+            (Algorithm::FRODO976, 4),
+            (Algorithm::FRODO1344__ECDHp521, 5),
+            (Algorithm::FRODO1344, 6),
+            (Algorithm::NTRU_HRSS_701, 7),
+            (Algorithm::NTRU_HRSS_701__ECDHp256, 8),
+            (Algorithm::NTRU_HPS_2048509, 9),
+            (Algorithm::NTRU_HPS_2048509__ECDHp256, 10),
+            (Algorithm::RND5_1CCA_5D, 11),
+            (Algorithm::RND5_1CCA_5D__ECDHp256, 12),
+            (Algorithm::RND5_3CCA_5D, 13),
+            (Algorithm::RND5_3CCA_5D__ECDHp384, 14),
+            (Algorithm::RND5_5CCA_5D, 15),
+            (Algorithm::RND5_5CCA_5D__ECDHp521, 16),
+            (Algorithm::KYBER_512, 17),
+            (Algorithm::KYBER_512__ECDHp256, 18),
+            (Algorithm::KYBER_768, 19),
+            (Algorithm::KYBER_768__ECDHp384, 20),
+            (Algorithm::KYBER_1024, 21),
+            (Algorithm::KYBER_1024__ECDHp521, 22),
+            (Algorithm::SABER_LIGHT, 23),
+            (Algorithm::SABER_LIGHT__ECDHp256, 24),
+            (Algorithm::SABER, 25),
+            (Algorithm::SABER__ECDHp384, 26),
+            (Algorithm::SABER_FIRE, 27),
+            (Algorithm::SABER_FIRE__ECDHp521, 28),
+            (Algorithm::ML_KEM_512, 29),
+            (Algorithm::ML_KEM_512__ECDHp256, 30),
+            (Algorithm::ML_KEM_768, 31),
+            (Algorithm::ML_KEM_768__ECDHp384, 32),
+            (Algorithm::ML_KEM_1024, 33),
+            (Algorithm::ML_KEM_1024__ECDHp521, 34),
+            (Algorithm::ML_DSA_44, 35),
+            (Algorithm::ML_DSA_65, 36),
+            (Algorithm::ML_DSA_87, 37),
+        ];
+        assert_eq!(algorithms.len() as u32, crate::request::ALGORITHM_COUNT);
+        for (algorithm, discriminant) in algorithms.iter() {
+            assert_eq!(*algorithm as u32, *discriminant, "{}", algorithm);
+        }
+
+        let operations = [
+            (Operation::NoOperation, 0),
+            (Operation::KeypairGeneration, 1),
+            (Operation::Encapsulation, 2),
+            (Operation::Decapsulation, 3),
+            (Operation::Sign, 4),
+            (Operation::Verify, 5),
+            (Operation::Hello, 6),
+            (Operation::Cancel, 7),
+        ];
+        assert_eq!(operations.len() as u32, crate::request::OPERATION_COUNT);
+        for (operation, discriminant) in operations.iter() {
+            assert_eq!(*operation as u32, *discriminant);
+        }
+    }
+
+    #[cfg(not(feature = "uuid"))]
+    #[test]
+    fn test_discriminant_bounds() {
+        use crate::request::{Algorithm, Operation, ALGORITHM_COUNT, OPERATION_COUNT};
+        use std::convert::TryFrom;
+
+        for discriminant in 0..ALGORITHM_COUNT {
+            assert_eq!(
+                Algorithm::try_from(discriminant).unwrap() as u32,
+                discriminant
+            );
+        }
+        for discriminant in 0..OPERATION_COUNT {
+            assert_eq!(
+                Operation::try_from(discriminant).unwrap() as u32,
+                discriminant
+            );
+        }
+        for discriminant in [ALGORITHM_COUNT, 0xff, u32::MAX].iter() {
+            assert_eq!(
+                Algorithm::try_from(*discriminant).err(),
+                Some(crate::MessageError::UnknownAlgorithm(*discriminant))
+            );
+        }
+        for discriminant in [OPERATION_COUNT, 0xff, u32::MAX].iter() {
+            assert_eq!(
+                Operation::try_from(*discriminant).err(),
+                Some(crate::MessageError::UnknownOperation(*discriminant))
             );
-            assert_ne!(status, 0);
         }
+
+        // A header with discriminant 0xff for the algorithm or the operation
+        let mut serialized = crate::request::serialize_request_header_vec(
+            1,
+            0,
+            Algorithm::KYBER_512,
+            Operation::KeypairGeneration,
+            0,
+        )
+        .unwrap();
+        serialized[13] = 0xff;
+        assert_eq!(
+            crate::request::deserialize_request_header(&serialized).err(),
+            Some(crate::MessageError::UnknownAlgorithm(0xff))
+        );
+        serialized[13] = Algorithm::KYBER_512 as u8;
+        serialized[17] = 0xff;
+        assert_eq!(
+            crate::request::deserialize_request_header(&serialized).err(),
+            Some(crate::MessageError::UnknownOperation(0xff))
+        );
     }
 
     #[test]
-    fn test_structuring_two_entries_c() {
-        let priv_key = vec![13, 12, 18, 33];
-        let ciphertext = vec![0, 0, 2, 3, 1];
+    fn test_request_header_constructors() {
+        use crate::request::{Algorithm, Operation, Request, RequestHeader};
 
-        unsafe {
-            let total_length =
-                crate::request::structure_two_entries_length(priv_key.len(), ciphertext.len());
+        let header = RequestHeader::keypair_generation(3, Algorithm::SABER).unwrap();
+        let manual = RequestHeader::builder()
+            .identifier(3)
+            .algorithm(Algorithm::SABER)
+            .operation(Operation::KeypairGeneration)
+            .build()
+            .unwrap();
+        assert!(header == manual);
+        let request = Request::empty_body(header).unwrap();
+        let expected = crate::request::serialize_request_header_vec(
+            3,
+            0,
+            Algorithm::SABER,
+            Operation::KeypairGeneration,
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            &request.to_bytes().unwrap()[..expected.len()],
+            expected.as_slice()
+        );
 
-            let buffer_c: *mut libc::c_uchar = libc::malloc(total_length) as *mut libc::c_uchar;
-            assert!(!buffer_c.is_null());
+        let public_key = [7; 992];
+        let header = RequestHeader::encapsulation(4, Algorithm::SABER, &public_key).unwrap();
+        assert_eq!(header.data_len, 992);
+        assert!(header.operation == Operation::Encapsulation);
+        assert!(matches!(
+            Request::empty_body(header),
+            Err(crate::MessageError::LengthMismatch {
+                expected: 992,
+                found: 0
+            })
+        ));
 
-            crate::request::structure_two_entries(
-                buffer_c,
-                priv_key.len(),
-                ciphertext.len(),
-                priv_key.as_ptr(),
-                ciphertext.as_ptr(),
+        let secret_key = [1; 2304];
+        let ciphertext = [2; 1088];
+        let header =
+            RequestHeader::decapsulation(5, Algorithm::SABER, &secret_key, &ciphertext).unwrap();
+        let body = crate::response::structure_two_entries(&secret_key, &ciphertext);
+        assert_eq!(header.data_len as usize, body.len());
+        let request = Request {
+            header,
+            body: body.into(),
+        };
+        assert!(request.to_bytes().is_ok());
+
+        let hello = RequestHeader::hello(6);
+        let request = Request::empty_body(hello.clone()).unwrap();
+        assert_eq!(
+            request.to_bytes().unwrap(),
+            crate::request::build_hello_request(6)
+        );
+        assert!(hello.validate().is_ok());
+
+        assert!(matches!(
+            RequestHeader::keypair_generation(7, Algorithm::NoAlgorithm),
+            Err(crate::MessageError::Placeholder { field: "algorithm" })
+        ));
+    }
+
+    #[cfg(all(feature = "std", feature = "tokio"))]
+    #[test]
+    fn test_async_io() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let header = crate::request::RequestHeader::encapsulation(
+                8,
+                crate::request::Algorithm::KYBER_512,
+                &[3; 800],
+            )
+            .unwrap();
+
+            // A tiny buffer so that every message takes many partial reads and writes
+            let (mut requester, mut responder) = tokio::io::duplex(7);
+            let requester_side = async {
+                crate::async_io::write_request(&mut requester, &header, &[3; 800])
+                    .await
+                    .unwrap();
+                crate::async_io::read_response(&mut requester)
+                    .await
+                    .unwrap()
+            };
+            let responder_side = async {
+                let (request, body) = crate::async_io::read_request(&mut responder).await.unwrap();
+                assert_eq!(body, vec![3; 800]);
+                crate::async_io::write_response(&mut responder, request.identifier, Ok(&[5; 300]))
+                    .await
+                    .unwrap();
+                request
+            };
+            let ((response, data), request) = tokio::join!(requester_side, responder_side);
+            assert!(request == header);
+            assert_eq!(response.identifier, 8);
+
+            #[cfg(feature = "compress")]
+            let data = crate::response::decompress_response_body(&response, &data).unwrap();
+            assert_eq!(data, vec![5; 300]);
+
+            // The stream ends in the middle of the header and in the middle of the data
+            let serialized = crate::response::serialize_response(1, &[1, 2, 3]).unwrap();
+            let mut cut_header = &serialized[..5];
+            assert_eq!(
+                crate::async_io::read_response(&mut cut_header).await.err(),
+                Some(crate::MessageError::UnexpectedEof)
+            );
+            let mut cut_data = &serialized[..serialized.len() - 1];
+            assert_eq!(
+                crate::async_io::read_response(&mut cut_data).await.err(),
+                Some(crate::MessageError::UnexpectedEof)
             );
 
-            let buffer = &*std::ptr::slice_from_raw_parts(buffer_c, total_length);
+            // A message of exactly the maximum size is accepted, one byte more is refused
+            let mut writer = crate::request::RequestStreamWriter::new();
+            writer.push(&header, &[3; 800]).unwrap();
+            let request = writer.into_bytes();
+            let read =
+                crate::async_io::read_request_bounded(&mut &request[..], request.len()).await;
+            assert!(read.is_ok());
             assert_eq!(
-                buffer,
-                vec![4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 1]
-                    .as_slice()
+                crate::async_io::read_request_bounded(&mut &request[..], request.len() - 1)
+                    .await
+                    .err(),
+                Some(crate::MessageError::MessageTooLarge {
+                    max: request.len() - 1,
+                    found: request.len()
+                })
+            );
+            let read =
+                crate::async_io::read_response_bounded(&mut &serialized[..], serialized.len())
+                    .await;
+            assert!(read.is_ok());
+            assert_eq!(
+                crate::async_io::read_response_bounded(&mut &serialized[..], serialized.len() - 1)
+                    .await
+                    .err(),
+                Some(crate::MessageError::MessageTooLarge {
+                    max: serialized.len() - 1,
+                    found: serialized.len()
+                })
             );
+            let too_large = crate::response::ResponseHeader::new(
+                1,
+                crate::response::ResponseStatus::Ok,
+                u32::MAX,
+            )
+            .to_bytes();
+            assert!(matches!(
+                crate::async_io::read_response(&mut &too_large[..]).await,
+                Err(crate::MessageError::MessageTooLarge {
+                    max: crate::request::MAX_MESSAGE_SIZE,
+                    ..
+                })
+            ));
+        });
+    }
 
-            libc::free(buffer_c as *mut libc::c_void);
+    #[test]
+    fn test_encapsulation_response_layout() {
+        // Large enough for the data of every algorithm, which the hot path below writes without allocating
+        let mut buffer = [0u8; 32 * 1024];
+        for algorithm in crate::request::Algorithm::iter() {
+            let (shared_secret, ciphertext) =
+                crate::response::encapsulation_response_layout(algorithm);
+            let sizes = algorithm.sizes();
+            assert_eq!(shared_secret.len(), sizes.shared_secret_len);
+            assert_eq!(ciphertext.len(), sizes.ciphertext_len);
+
+            for _ in 0..100 {
+                buffer[shared_secret.clone()].fill(0x11);
+                buffer[ciphertext.clone()].fill(0x22);
+                crate::response::write_two_entries_prefixes(
+                    &mut buffer,
+                    shared_secret.len(),
+                    ciphertext.len(),
+                )
+                .unwrap();
+            }
+
+            let expected = crate::response::structure_two_entries(
+                &vec![0x11; sizes.shared_secret_len],
+                &vec![0x22; sizes.ciphertext_len],
+            );
+            assert_eq!(&buffer[..ciphertext.end], &expected[..]);
+            let (entry1, entry2) =
+                crate::request::destructure_two_entries(&buffer[..ciphertext.end]).unwrap();
+            assert_eq!(entry1, &buffer[shared_secret]);
+            assert_eq!(entry2, &buffer[ciphertext]);
         }
+
+        buffer[..20].fill(0xEE);
+        assert_eq!(
+            crate::response::write_two_entries_prefixes(&mut buffer[..20], 4, 1),
+            Err(crate::MessageError::BufferTooShort {
+                needed: 21,
+                got: 20
+            })
+        );
+        assert_eq!(&buffer[..20], &[0xEE; 20][..]);
+        let (_, end) = crate::response::two_entries_layout(usize::MAX, 1);
+        assert_eq!(end.end, usize::MAX);
     }
 
     #[test]
-    fn test_destructuring_two_entries_rust() {
-        let mut priv_key_ct: Vec<u8> = vec![
-            4, 0, 0, 0, 0, 0, 0, 0, 13, 12, 18, 33, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 3, 1,
-        ];
+    fn test_algorithm_table() {
+        use crate::request::Algorithm;
+
+        for algorithm in Algorithm::iter() {
+            let (pq_scheme, classical_scheme) = algorithm.components().unwrap();
+            let pq_name = format!("{:?}", pq_scheme);
+            match classical_scheme {
+                Some(classical_scheme) => {
+                    let name = format!("{}__{:?}", pq_name, classical_scheme);
+                    assert_eq!(algorithm.as_str(), name);
+
+                    let pq_only: Algorithm = pq_name.parse().unwrap();
+                    assert!(pq_only.components() == Some((pq_scheme, None)));
+                    let (sizes, pq_sizes) = (algorithm.sizes(), pq_only.sizes());
+                    assert!(sizes.public_key_len > pq_sizes.public_key_len);
+                    assert!(sizes.shared_secret_len > pq_sizes.shared_secret_len);
+                }
+                None => assert_eq!(algorithm.as_str(), pq_name),
+            }
+        }
+        assert!(Algorithm::NoAlgorithm.components().is_none());
+    }
+
+    // Discriminant 0 means "unset" everywhere, it must stay with the placeholders
+    #[test]
+    fn test_placeholder_discriminants() {
+        use crate::request::{Algorithm, Operation};
+        use core::convert::TryFrom;
+
+        assert_eq!(Algorithm::NoAlgorithm as u32, 0);
+        assert_eq!(Operation::NoOperation as u32, 0);
+        assert!(Algorithm::default() == Algorithm::NoAlgorithm);
+        assert!(Operation::default() == Operation::NoOperation);
+        assert!(Algorithm::try_from(0).unwrap() == Algorithm::NoAlgorithm);
+        assert!(Operation::try_from(0).unwrap() == Operation::NoOperation);
+        assert!(Algorithm::iter().all(|algorithm| algorithm as u32 != 0));
+    }
+
+    #[test]
+    fn test_algorithm_iter() {
+        let algorithms: Vec<crate::request::Algorithm> =
+            crate::request::Algorithm::iter().collect();
+        assert_eq!(algorithms.len() as u32, crate::request::ALGORITHM_COUNT - 1);
+        for (index, algorithm) in algorithms.iter().enumerate() {
+            assert_eq!(*algorithm as usize, index + 1);
+            let sizes = algorithm.sizes();
+            println!(
+                "{} (level {}): public key {} bytes, ciphertext {} bytes",
+                algorithm,
+                algorithm.security_level().unwrap(),
+                sizes.public_key_len,
+                sizes.ciphertext_len
+            );
+        }
+    }
+
+    #[test]
+    fn test_supported_algorithms() {
+        use std::convert::TryFrom;
 
+        let supported: Vec<crate::request::Algorithm> = (0..crate::request::ALGORITHM_COUNT)
+            .map(|discriminant| crate::request::Algorithm::try_from(discriminant).unwrap())
+            .filter(|algorithm| algorithm.is_supported())
+            .collect();
+        assert!(!crate::request::Algorithm::NoAlgorithm.is_supported());
+        #[cfg(all(
+            feature = "frodo",
+            feature = "ntru",
+            feature = "rnd5",
+            feature = "kyber",
+            feature = "saber",
+            feature = "dilithium"
+        ))]
         assert_eq!(
-            crate::request::destructure_two_entries(&priv_key_ct).unwrap(),
-            (
-                vec![13, 12, 18, 33].as_slice(),
-                vec![0, 0, 2, 3, 1].as_slice()
+            supported.len(),
+            crate::request::ALGORITHM_COUNT as usize - 1
+        );
+
+        let needed = supported.len() as i64;
+        unsafe {
+            assert_eq!(
+                crate::request::supported_algorithms(std::ptr::null_mut(), 0),
+                -needed
+            );
+
+            let mut out = vec![crate::request::Algorithm::NoAlgorithm; supported.len() + 1];
+            assert_eq!(
+                crate::request::supported_algorithms(out.as_mut_ptr(), out.len()),
+                needed
+            );
+            assert!(out[..supported.len()] == supported[..]);
+            assert!(out[supported.len()] == crate::request::Algorithm::NoAlgorithm);
+
+            if needed > 0 {
+                let mut out = vec![crate::request::Algorithm::NoAlgorithm; supported.len() - 1];
+                assert_eq!(
+                    crate::request::supported_algorithms(out.as_mut_ptr(), out.len()),
+                    -needed
+                );
+            }
+        }
+    }
+
+    #[cfg(not(feature = "uuid"))]
+    #[test]
+    fn test_describe_messages() {
+        let header = crate::request::RequestHeader::encapsulation(
+            1234,
+            crate::request::Algorithm::KYBER_512,
+            &[0xab; 800],
+        )
+        .unwrap();
+        let mut request = header.to_bytes();
+        request.extend(&[0xab; 20]);
+        assert_eq!(
+            crate::inspect::describe_request(&request[..request.len() - 2]),
+            format!(
+                "request: version {}, identifier 1234, algorithm KYBER_512, operation Encapsulation, \
+                 data_len 800, deadline_ms 0, flags 0x00; data:{} ... (18 bytes) (missing 782 bytes)",
+                crate::FORMAT_VERSION,
+                " ab".repeat(16)
             )
         );
 
-        // Test with an empty input
-        assert!(crate::request::destructure_two_entries(&vec![]).is_err());
+        let mut response = crate::response::serialize_failure_response(
+            7,
+            crate::response::ResponseStatus::InvalidInput,
+        )
+        .unwrap();
+        response.push(0xff);
+        let description = crate::inspect::describe_response(&response);
+        assert!(description.starts_with(&format!(
+            "response: version {}, identifier 7, status InvalidInput, data_len 0, flags 0x00; data: none (",
+            crate::FORMAT_VERSION
+        )));
+        assert!(description.ends_with(" trailing bytes)"));
 
-        // Tests where the length are modified but the body does not match those lengths
-        priv_key_ct[0] = 255;
-        assert!(crate::request::destructure_two_entries(&priv_key_ct).is_err());
-        priv_key_ct[0] = 4;
+        // Malformed input is described instead of panicking
+        assert_eq!(
+            crate::inspect::describe_request(&[8, 210, 4]),
+            "request: header could not be parsed: buffer too short: needed 30 bytes but got 3; bytes: 08 d2 04"
+        );
+        request[13] = 0xff;
+        assert!(crate::inspect::describe_request(&request).starts_with(
+            "request: header could not be parsed: unknown algorithm with discriminant 255"
+        ));
+        assert!(crate::inspect::describe_response(&[]).ends_with("bytes: none"));
+    }
 
-        priv_key_ct[12] = 255;
-        assert!(crate::request::destructure_two_entries(&priv_key_ct).is_err());
+    #[test]
+    fn test_format_version_window() {
+        let accepted: Vec<u8> = (0..=u8::MAX)
+            .filter(|&version| crate::check_format_version(version).is_ok())
+            .collect();
+        assert_eq!(
+            accepted,
+            (crate::MIN_FORMAT_VERSION..=crate::FORMAT_VERSION).collect::<Vec<u8>>()
+        );
+        // A `Hello` announces exactly the accepted versions
+        assert_eq!(accepted, crate::SUPPORTED_FORMAT_VERSIONS);
+        for version in crate::SUPPORTED_FORMAT_VERSIONS.iter() {
+            assert_eq!(crate::check_format_version(*version), Ok(()));
+        }
+        assert_eq!(
+            crate::SUPPORTED_FORMAT_VERSIONS.last(),
+            Some(&crate::FORMAT_VERSION)
+        );
+
+        let mut serialized = crate::response::serialize_response(1, &[]).unwrap();
+        for version in [crate::MIN_FORMAT_VERSION - 1, crate::FORMAT_VERSION + 1].iter() {
+            serialized[0] = *version;
+            assert_eq!(
+                crate::response::deserialize_response_header_bounded(&serialized, 0).err(),
+                Some(crate::MessageError::VersionMismatch {
+                    expected: crate::FORMAT_VERSION,
+                    found: *version
+                })
+            );
+        }
+        serialized[0] = crate::MIN_FORMAT_VERSION;
+        let header = crate::response::deserialize_response_header_bounded(&serialized, 0).unwrap();
+        assert_eq!(header.version(), crate::MIN_FORMAT_VERSION);
+    }
+
+    #[cfg(feature = "logging")]
+    #[test]
+    fn test_logging() {
+        // Other tests log concurrently, so every message remembers the thread it came from
+        struct CapturingLogger(std::sync::Mutex<Vec<(std::thread::ThreadId, log::Level, String)>>);
+
+        impl log::Log for CapturingLogger {
+            fn enabled(&self, _: &log::Metadata) -> bool {
+                true
+            }
+
+            fn log(&self, record: &log::Record) {
+                let message = record.args().to_string();
+                let thread = std::thread::current().id();
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push((thread, record.level(), message));
+            }
+
+            fn flush(&self) {}
+        }
+
+        static LOGGER: CapturingLogger = CapturingLogger(std::sync::Mutex::new(Vec::new()));
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let secret = [0xA5; 16];
+        let response = crate::response::serialize_response(4321, &secret).unwrap();
+        crate::response::parse_response(&response).unwrap();
+        assert!(crate::request::deserialize_request_header(&[0; 10]).is_err());
+
+        let records: Vec<_> = LOGGER
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(thread, _, _)| *thread == std::thread::current().id())
+            .map(|(_, level, message)| (*level, message.clone()))
+            .collect();
+        assert!(records
+            .iter()
+            .any(|(level, message)| *level == log::Level::Debug
+                && message.contains("could not deserialize request header")
+                && message.contains("buffer too short")));
+        assert!(records
+            .iter()
+            .any(|(level, message)| *level == log::Level::Trace
+                && message.contains("identifier 4321")));
+        // Only lengths of the data are logged, never its bytes
+        assert!(!records
+            .iter()
+            .any(|(_, message)| message.contains("165") || message.contains("a5")));
     }
 }
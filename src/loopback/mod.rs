@@ -0,0 +1,68 @@
+use std::collections::VecDeque;
+
+use crate::request::{FrameDecoder, Request};
+
+/// An in-memory stream with a responder at the other end, for testing request and response handling without
+/// a real pipe or socket. Every request written to it is handed to the responder as soon as it is complete,
+/// and the serialized response the responder returns is what is read back, in the order the requests were
+/// written. It can be used wherever a stream is expected, e.g. `channel::Channel::new(LoopbackChannel::new(..))`.
+///
+/// Reading when no response is waiting returns 0 bytes, i.e. the end of the stream, so a request that
+/// the responder did not answer fails instead of blocking forever.
+pub struct LoopbackChannel<F: FnMut(Request) -> Vec<u8>> {
+    decoder: FrameDecoder,
+    responder: F,
+    responses: VecDeque<u8>,
+}
+
+impl<F: FnMut(Request) -> Vec<u8>> LoopbackChannel<F> {
+    /// Creates a channel whose requests are answered by `responder`, which returns a serialized response
+    /// such as those of `response::serialize_response`.
+    pub fn new(responder: F) -> Self {
+        LoopbackChannel {
+            decoder: FrameDecoder::new(),
+            responder,
+            responses: VecDeque::new(),
+        }
+    }
+
+    /// Number of bytes of responses that have not been read yet.
+    pub fn unread(&self) -> usize {
+        self.responses.len()
+    }
+}
+
+impl<F: FnMut(Request) -> Vec<u8>> std::io::Write for LoopbackChannel<F> {
+    /// Buffers `buf` and answers every request it completes.
+    /// # Errors
+    /// `std::io::ErrorKind::InvalidData` wrapping the error of `FrameDecoder` when the bytes written do not
+    /// form a valid request.
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut request = self.decoder.push(buf);
+        loop {
+            match request {
+                Ok(Some(complete)) => self.responses.extend((self.responder)(complete)),
+                Ok(None) => return Ok(buf.len()),
+                Err(error) => {
+                    return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, error))
+                }
+            }
+            request = self.decoder.next_request();
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(Request) -> Vec<u8>> std::io::Read for LoopbackChannel<F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = buf.len().min(self.responses.len());
+        for (byte, response) in buf.iter_mut().zip(self.responses.drain(..len)) {
+            *byte = response;
+        }
+
+        Ok(len)
+    }
+}
@@ -1,248 +1,2563 @@
-use serde::{Deserialize, Serialize};
-use std::convert::TryInto;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
 
-lazy_static! {
-    static ref REQUEST_HEADER_SIZE: u64 = bincode::serialized_size(&RequestHeader::default())
-        .expect("Unable to get size of default RequestHeader.");
-}
+// The serialized size of `RequestHeader`: version, identifier, data_len, the discriminants of
+// algorithm and operation, deadline_ms and flags. `test_header_sizes` makes sure this matches what bincode
+// produces.
+const REQUEST_HEADER_SIZE: u64 = (core::mem::size_of::<u8>()
+    + core::mem::size_of::<crate::Identifier>()
+    + core::mem::size_of::<u32>()
+    + 2 * core::mem::size_of::<u32>()
+    + core::mem::size_of::<u64>()
+    + core::mem::size_of::<u8>()) as u64;
 
 /// All possible algorithms that can be requested.
+/// The discriminants are sent on the wire, so a variant is never renumbered and a new one takes the next free
+/// number (`ALGORITHM_COUNT`), wherever it is listed. Algorithms are ordered by their discriminant, so the
+/// order is as stable as the discriminants themselves and does not depend on where a variant is listed.
 #[allow(non_camel_case_types)]
-#[derive(Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary, Debug))]
 #[repr(C)]
 pub enum Algorithm {
-    NoAlgorithm,
-    FRODO640__ECDHp256,
+    // Necessary so we can get a default size of RequestHeader at run-time so C knows
+    // what size buffer to allocate.
+    /// Placeholder for an unset algorithm, never a real one. Discriminant 0 is permanently reserved for it:
+    /// a zeroed header, a default `RequestHeader` and validation all rely on 0 meaning "unset".
+    #[default]
+    NoAlgorithm = 0,
+    FRODO640__ECDHp256 = 1,
+    FRODO640 = 2,
+    FRODO976__ECDHp384 = 3,
+    FRODO976 = 4,
+    FRODO1344__ECDHp521 = 5,
+    FRODO1344 = 6,
+    NTRU_HRSS_701 = 7,
+    NTRU_HRSS_701__ECDHp256 = 8,
+    NTRU_HPS_2048509 = 9,
+    NTRU_HPS_2048509__ECDHp256 = 10,
+    RND5_1CCA_5D = 11,
+    RND5_1CCA_5D__ECDHp256 = 12,
+    RND5_3CCA_5D = 13,
+    RND5_3CCA_5D__ECDHp384 = 14,
+    RND5_5CCA_5D = 15,
+    RND5_5CCA_5D__ECDHp521 = 16,
+    KYBER_512 = 17,
+    KYBER_512__ECDHp256 = 18,
+    KYBER_768 = 19,
+    KYBER_768__ECDHp384 = 20,
+    KYBER_1024 = 21,
+    KYBER_1024__ECDHp521 = 22,
+    SABER_LIGHT = 23,
+    SABER_LIGHT__ECDHp256 = 24,
+    SABER = 25,
+    SABER__ECDHp384 = 26,
+    SABER_FIRE = 27,
+    SABER_FIRE__ECDHp521 = 28,
+    /// ML-KEM-512 as standardized in FIPS 203. Its keys and ciphertexts have the same sizes as those of
+    /// `KYBER_512`, the round 3 submission it is based on, but the two do not interoperate.
+    ML_KEM_512 = 29,
+    ML_KEM_512__ECDHp256 = 30,
+    /// ML-KEM-768, see `ML_KEM_512`. Sized like `KYBER_768`.
+    ML_KEM_768 = 31,
+    ML_KEM_768__ECDHp384 = 32,
+    /// ML-KEM-1024, see `ML_KEM_512`. Sized like `KYBER_1024`.
+    ML_KEM_1024 = 33,
+    ML_KEM_1024__ECDHp521 = 34,
+    /// ML-DSA-44 as standardized in FIPS 204. Unlike every algorithm above it is a signature scheme, see
+    /// `Algorithm::supports_signatures`.
+    ML_DSA_44 = 35,
+    /// ML-DSA-65, see `ML_DSA_44`.
+    ML_DSA_65 = 36,
+    /// ML-DSA-87, see `ML_DSA_44`.
+    ML_DSA_87 = 37,
+}
+
+/// Sizes in bytes of the key material belonging to an `Algorithm`.
+/// For hybrid algorithms (e.g. `FRODO976__ECDHp384`) every size is the combined size of the post-quantum
+/// part followed by the ECDH part. For the ECDH part the public key and ciphertext are uncompressed points,
+/// the secret key is the scalar and the shared secret is the x-coordinate of the shared point. Signature
+/// schemes have no ciphertext and shared secret, key encapsulation mechanisms no signature, so those sizes
+/// are 0.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct AlgorithmSizes {
+    pub public_key_len: crate::size_t,
+    pub secret_key_len: crate::size_t,
+    pub ciphertext_len: crate::size_t,
+    pub shared_secret_len: crate::size_t,
+    pub signature_len: crate::size_t,
+}
+
+impl AlgorithmSizes {
+    const fn new(
+        public_key_len: usize,
+        secret_key_len: usize,
+        ciphertext_len: usize,
+        shared_secret_len: usize,
+    ) -> Self {
+        AlgorithmSizes {
+            public_key_len,
+            secret_key_len,
+            ciphertext_len,
+            shared_secret_len,
+            signature_len: 0,
+        }
+    }
+
+    const fn signature(public_key_len: usize, secret_key_len: usize, signature_len: usize) -> Self {
+        AlgorithmSizes {
+            signature_len,
+            ..AlgorithmSizes::new(public_key_len, secret_key_len, 0, 0)
+        }
+    }
+
+    const fn hybrid(self, classical: AlgorithmSizes) -> Self {
+        AlgorithmSizes {
+            public_key_len: self.public_key_len + classical.public_key_len,
+            secret_key_len: self.secret_key_len + classical.secret_key_len,
+            ciphertext_len: self.ciphertext_len + classical.ciphertext_len,
+            shared_secret_len: self.shared_secret_len + classical.shared_secret_len,
+            signature_len: self.signature_len + classical.signature_len,
+        }
+    }
+}
+
+// Sizes of the NIST round 3 parameter sets (round 2 for Round5). FIPS 203 kept the sizes of Kyber, so these
+// are those of ML-KEM as well.
+const FRODO640_SIZES: AlgorithmSizes = AlgorithmSizes::new(9616, 19888, 9720, 16);
+const FRODO976_SIZES: AlgorithmSizes = AlgorithmSizes::new(15632, 31296, 15744, 24);
+const FRODO1344_SIZES: AlgorithmSizes = AlgorithmSizes::new(21520, 43088, 21632, 32);
+const NTRU_HRSS_701_SIZES: AlgorithmSizes = AlgorithmSizes::new(1138, 1450, 1138, 32);
+const NTRU_HPS_2048509_SIZES: AlgorithmSizes = AlgorithmSizes::new(699, 935, 699, 32);
+const RND5_1CCA_5D_SIZES: AlgorithmSizes = AlgorithmSizes::new(445, 493, 549, 16);
+const RND5_3CCA_5D_SIZES: AlgorithmSizes = AlgorithmSizes::new(780, 852, 859, 24);
+const RND5_5CCA_5D_SIZES: AlgorithmSizes = AlgorithmSizes::new(972, 1068, 1063, 32);
+const KYBER_512_SIZES: AlgorithmSizes = AlgorithmSizes::new(800, 1632, 768, 32);
+const KYBER_768_SIZES: AlgorithmSizes = AlgorithmSizes::new(1184, 2400, 1088, 32);
+const KYBER_1024_SIZES: AlgorithmSizes = AlgorithmSizes::new(1568, 3168, 1568, 32);
+const SABER_LIGHT_SIZES: AlgorithmSizes = AlgorithmSizes::new(672, 1568, 736, 32);
+const SABER_SIZES: AlgorithmSizes = AlgorithmSizes::new(992, 2304, 1088, 32);
+const SABER_FIRE_SIZES: AlgorithmSizes = AlgorithmSizes::new(1312, 3040, 1472, 32);
+const ECDH_P256_SIZES: AlgorithmSizes = AlgorithmSizes::new(65, 32, 65, 32);
+const ECDH_P384_SIZES: AlgorithmSizes = AlgorithmSizes::new(97, 48, 97, 48);
+const ECDH_P521_SIZES: AlgorithmSizes = AlgorithmSizes::new(133, 66, 133, 66);
+// Sizes of ML-DSA as standardized in FIPS 204.
+const ML_DSA_44_SIZES: AlgorithmSizes = AlgorithmSizes::signature(1312, 2560, 2420);
+const ML_DSA_65_SIZES: AlgorithmSizes = AlgorithmSizes::signature(1952, 4032, 3309);
+const ML_DSA_87_SIZES: AlgorithmSizes = AlgorithmSizes::signature(2592, 4896, 4627);
+
+/// A cap on `data_len` for `deserialize_request_header_bounded` and
+/// `response::deserialize_response_header_bounded` that fits the data of every operation. The largest
+/// data is that of `FRODO1344__ECDHp521`, the algorithm with the largest keys: either its secret key and
+/// ciphertext (decapsulation) or its public and secret key (key generation), each with its length prefix.
+pub const RECOMMENDED_MAX_DATA_LEN: u32 = {
+    let sizes = FRODO1344_SIZES.hybrid(ECDH_P521_SIZES);
+    let decapsulation = sizes.secret_key_len + sizes.ciphertext_len;
+    let keypair = sizes.public_key_len + sizes.secret_key_len;
+    let largest = if decapsulation > keypair {
+        decapsulation
+    } else {
+        keypair
+    };
+    (largest + 2 * crate::LENGTH_PREFIX_SIZE) as u32
+};
+
+/// The largest serialized message, i.e. a request header, `RECOMMENDED_MAX_DATA_LEN` bytes of data and with
+/// the `checksum` feature the trailer. A response header is smaller, so this bounds responses as well. This is
+/// what a buffer needs to hold any message that the readers of this crate accept by default. Every reader
+/// refuses larger messages with `MessageError::MessageTooLarge` unless it is given another limit, e.g. with
+/// `read_request_bounded` or `FrameDecoder::set_max_message_size`.
+pub const MAX_MESSAGE_SIZE: usize = {
+    #[cfg(not(feature = "checksum"))]
+    let trailer_size = 0;
+    #[cfg(feature = "checksum")]
+    let trailer_size = crate::checksum::CHECKSUM_SIZE;
+    REQUEST_HEADER_SIZE as usize + RECOMMENDED_MAX_DATA_LEN as usize + trailer_size
+};
+
+/// The post-quantum part of an `Algorithm`, see `Algorithm::components`.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PqScheme {
     FRODO640,
-    FRODO976__ECDHp384,
     FRODO976,
-    FRODO1344__ECDHp521,
     FRODO1344,
     NTRU_HRSS_701,
-    NTRU_HRSS_701__ECDHp256,
     NTRU_HPS_2048509,
-    NTRU_HPS_2048509__ECDHp256,
     RND5_1CCA_5D,
-    RND5_1CCA_5D__ECDHp256,
     RND5_3CCA_5D,
-    RND5_3CCA_5D__ECDHp384,
     RND5_5CCA_5D,
-    RND5_5CCA_5D__ECDHp521,
     KYBER_512,
-    KYBER_512__ECDHp256,
     KYBER_768,
-    KYBER_768__ECDHp384,
     KYBER_1024,
-    KYBER_1024__ECDHp521,
     SABER_LIGHT,
-    SABER_LIGHT__ECDHp256,
     SABER,
-    SABER__ECDHp384,
     SABER_FIRE,
-    SABER_FIRE__ECDHp521,
+    ML_KEM_512,
+    ML_KEM_768,
+    ML_KEM_1024,
+    ML_DSA_44,
+    ML_DSA_65,
+    ML_DSA_87,
+}
+
+/// The classical part of a hybrid `Algorithm`, see `Algorithm::components`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassicalScheme {
+    ECDHp256,
+    ECDHp384,
+    ECDHp521,
+}
+
+impl ClassicalScheme {
+    const fn sizes(self) -> AlgorithmSizes {
+        match self {
+            ClassicalScheme::ECDHp256 => ECDH_P256_SIZES,
+            ClassicalScheme::ECDHp384 => ECDH_P384_SIZES,
+            ClassicalScheme::ECDHp521 => ECDH_P521_SIZES,
+        }
+    }
+}
+
+impl PqScheme {
+    /// Returns whether the family of this scheme is enabled through its cargo feature (`frodo`, `ntru`,
+    /// `rnd5`, `kyber`, `saber` or `dilithium`, all enabled by default). ML-KEM belongs to the `kyber` family
+    /// and ML-DSA to the `dilithium` family.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            PqScheme::FRODO640 | PqScheme::FRODO976 | PqScheme::FRODO1344 => {
+                cfg!(feature = "frodo")
+            }
+            PqScheme::NTRU_HRSS_701 | PqScheme::NTRU_HPS_2048509 => cfg!(feature = "ntru"),
+            PqScheme::RND5_1CCA_5D | PqScheme::RND5_3CCA_5D | PqScheme::RND5_5CCA_5D => {
+                cfg!(feature = "rnd5")
+            }
+            PqScheme::KYBER_512
+            | PqScheme::KYBER_768
+            | PqScheme::KYBER_1024
+            | PqScheme::ML_KEM_512
+            | PqScheme::ML_KEM_768
+            | PqScheme::ML_KEM_1024 => cfg!(feature = "kyber"),
+            PqScheme::SABER_LIGHT | PqScheme::SABER | PqScheme::SABER_FIRE => {
+                cfg!(feature = "saber")
+            }
+            PqScheme::ML_DSA_44 | PqScheme::ML_DSA_65 | PqScheme::ML_DSA_87 => {
+                cfg!(feature = "dilithium")
+            }
+        }
+    }
+
+    /// Returns the NIST security category (1, 2, 3 or 5) claimed for this scheme in its submission.
+    pub fn security_level(&self) -> u8 {
+        match self {
+            PqScheme::FRODO640
+            | PqScheme::NTRU_HPS_2048509
+            | PqScheme::RND5_1CCA_5D
+            | PqScheme::KYBER_512
+            | PqScheme::SABER_LIGHT
+            | PqScheme::ML_KEM_512 => 1,
+            PqScheme::ML_DSA_44 => 2,
+            PqScheme::FRODO976
+            | PqScheme::NTRU_HRSS_701
+            | PqScheme::RND5_3CCA_5D
+            | PqScheme::KYBER_768
+            | PqScheme::SABER
+            | PqScheme::ML_KEM_768
+            | PqScheme::ML_DSA_65 => 3,
+            PqScheme::FRODO1344
+            | PqScheme::RND5_5CCA_5D
+            | PqScheme::KYBER_1024
+            | PqScheme::SABER_FIRE
+            | PqScheme::ML_KEM_1024
+            | PqScheme::ML_DSA_87 => 5,
+        }
+    }
+}
+
+// Everything that is known about an `Algorithm`, see `ALGORITHMS`.
+struct AlgorithmInfo {
+    algorithm: Algorithm,
+    name: &'static str,
+    canonical_name: &'static str,
+    components: Option<(PqScheme, Option<ClassicalScheme>)>,
+    sizes: AlgorithmSizes,
+    supports_signatures: bool,
+}
+
+impl AlgorithmInfo {
+    const fn new(
+        algorithm: Algorithm,
+        name: &'static str,
+        pq_scheme: PqScheme,
+        sizes: AlgorithmSizes,
+    ) -> Self {
+        AlgorithmInfo {
+            algorithm,
+            name,
+            canonical_name: name,
+            components: Some((pq_scheme, None)),
+            sizes,
+            supports_signatures: false,
+        }
+    }
+
+    // Turns the algorithm into the hybrid of its post-quantum scheme with `classical`.
+    const fn with_ecdh(self, classical: ClassicalScheme) -> Self {
+        let components = match self.components {
+            Some((pq_scheme, _)) => Some((pq_scheme, Some(classical))),
+            None => None,
+        };
+
+        AlgorithmInfo {
+            components,
+            sizes: self.sizes.hybrid(classical.sizes()),
+            ..self
+        }
+    }
+
+    const fn standardized_as(self, canonical_name: &'static str) -> Self {
+        AlgorithmInfo {
+            canonical_name,
+            ..self
+        }
+    }
+
+    // Turns the algorithm into a signature scheme, see `Algorithm::supports_signatures`.
+    const fn signing(self) -> Self {
+        AlgorithmInfo {
+            supports_signatures: true,
+            ..self
+        }
+    }
+}
+
+// The single source of the names, schemes, sizes and kind of every `Algorithm`, indexed by discriminant. Its length
+// makes sure every discriminant has an entry, `test_algorithm_names` that every entry sits at the discriminant
+// of its algorithm and `test_algorithm_table` that its name and sizes match its schemes.
+static ALGORITHMS: [AlgorithmInfo; ALGORITHM_COUNT as usize] = [
+    AlgorithmInfo {
+        algorithm: Algorithm::NoAlgorithm,
+        name: "NoAlgorithm",
+        canonical_name: "NoAlgorithm",
+        components: None,
+        sizes: AlgorithmSizes::new(0, 0, 0, 0),
+        supports_signatures: false,
+    },
+    AlgorithmInfo::new(
+        Algorithm::FRODO640__ECDHp256,
+        "FRODO640__ECDHp256",
+        PqScheme::FRODO640,
+        FRODO640_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp256),
+    AlgorithmInfo::new(
+        Algorithm::FRODO640,
+        "FRODO640",
+        PqScheme::FRODO640,
+        FRODO640_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::FRODO976__ECDHp384,
+        "FRODO976__ECDHp384",
+        PqScheme::FRODO976,
+        FRODO976_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp384),
+    AlgorithmInfo::new(
+        Algorithm::FRODO976,
+        "FRODO976",
+        PqScheme::FRODO976,
+        FRODO976_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::FRODO1344__ECDHp521,
+        "FRODO1344__ECDHp521",
+        PqScheme::FRODO1344,
+        FRODO1344_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp521),
+    AlgorithmInfo::new(
+        Algorithm::FRODO1344,
+        "FRODO1344",
+        PqScheme::FRODO1344,
+        FRODO1344_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::NTRU_HRSS_701,
+        "NTRU_HRSS_701",
+        PqScheme::NTRU_HRSS_701,
+        NTRU_HRSS_701_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::NTRU_HRSS_701__ECDHp256,
+        "NTRU_HRSS_701__ECDHp256",
+        PqScheme::NTRU_HRSS_701,
+        NTRU_HRSS_701_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp256),
+    AlgorithmInfo::new(
+        Algorithm::NTRU_HPS_2048509,
+        "NTRU_HPS_2048509",
+        PqScheme::NTRU_HPS_2048509,
+        NTRU_HPS_2048509_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::NTRU_HPS_2048509__ECDHp256,
+        "NTRU_HPS_2048509__ECDHp256",
+        PqScheme::NTRU_HPS_2048509,
+        NTRU_HPS_2048509_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp256),
+    AlgorithmInfo::new(
+        Algorithm::RND5_1CCA_5D,
+        "RND5_1CCA_5D",
+        PqScheme::RND5_1CCA_5D,
+        RND5_1CCA_5D_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::RND5_1CCA_5D__ECDHp256,
+        "RND5_1CCA_5D__ECDHp256",
+        PqScheme::RND5_1CCA_5D,
+        RND5_1CCA_5D_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp256),
+    AlgorithmInfo::new(
+        Algorithm::RND5_3CCA_5D,
+        "RND5_3CCA_5D",
+        PqScheme::RND5_3CCA_5D,
+        RND5_3CCA_5D_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::RND5_3CCA_5D__ECDHp384,
+        "RND5_3CCA_5D__ECDHp384",
+        PqScheme::RND5_3CCA_5D,
+        RND5_3CCA_5D_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp384),
+    AlgorithmInfo::new(
+        Algorithm::RND5_5CCA_5D,
+        "RND5_5CCA_5D",
+        PqScheme::RND5_5CCA_5D,
+        RND5_5CCA_5D_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::RND5_5CCA_5D__ECDHp521,
+        "RND5_5CCA_5D__ECDHp521",
+        PqScheme::RND5_5CCA_5D,
+        RND5_5CCA_5D_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp521),
+    AlgorithmInfo::new(
+        Algorithm::KYBER_512,
+        "KYBER_512",
+        PqScheme::KYBER_512,
+        KYBER_512_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::KYBER_512__ECDHp256,
+        "KYBER_512__ECDHp256",
+        PqScheme::KYBER_512,
+        KYBER_512_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp256),
+    AlgorithmInfo::new(
+        Algorithm::KYBER_768,
+        "KYBER_768",
+        PqScheme::KYBER_768,
+        KYBER_768_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::KYBER_768__ECDHp384,
+        "KYBER_768__ECDHp384",
+        PqScheme::KYBER_768,
+        KYBER_768_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp384),
+    AlgorithmInfo::new(
+        Algorithm::KYBER_1024,
+        "KYBER_1024",
+        PqScheme::KYBER_1024,
+        KYBER_1024_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::KYBER_1024__ECDHp521,
+        "KYBER_1024__ECDHp521",
+        PqScheme::KYBER_1024,
+        KYBER_1024_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp521),
+    AlgorithmInfo::new(
+        Algorithm::SABER_LIGHT,
+        "SABER_LIGHT",
+        PqScheme::SABER_LIGHT,
+        SABER_LIGHT_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::SABER_LIGHT__ECDHp256,
+        "SABER_LIGHT__ECDHp256",
+        PqScheme::SABER_LIGHT,
+        SABER_LIGHT_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp256),
+    AlgorithmInfo::new(Algorithm::SABER, "SABER", PqScheme::SABER, SABER_SIZES),
+    AlgorithmInfo::new(
+        Algorithm::SABER__ECDHp384,
+        "SABER__ECDHp384",
+        PqScheme::SABER,
+        SABER_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp384),
+    AlgorithmInfo::new(
+        Algorithm::SABER_FIRE,
+        "SABER_FIRE",
+        PqScheme::SABER_FIRE,
+        SABER_FIRE_SIZES,
+    ),
+    AlgorithmInfo::new(
+        Algorithm::SABER_FIRE__ECDHp521,
+        "SABER_FIRE__ECDHp521",
+        PqScheme::SABER_FIRE,
+        SABER_FIRE_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp521),
+    AlgorithmInfo::new(
+        Algorithm::ML_KEM_512,
+        "ML_KEM_512",
+        PqScheme::ML_KEM_512,
+        KYBER_512_SIZES,
+    )
+    .standardized_as("ML-KEM-512"),
+    AlgorithmInfo::new(
+        Algorithm::ML_KEM_512__ECDHp256,
+        "ML_KEM_512__ECDHp256",
+        PqScheme::ML_KEM_512,
+        KYBER_512_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp256)
+    .standardized_as("ML-KEM-512__ECDHp256"),
+    AlgorithmInfo::new(
+        Algorithm::ML_KEM_768,
+        "ML_KEM_768",
+        PqScheme::ML_KEM_768,
+        KYBER_768_SIZES,
+    )
+    .standardized_as("ML-KEM-768"),
+    AlgorithmInfo::new(
+        Algorithm::ML_KEM_768__ECDHp384,
+        "ML_KEM_768__ECDHp384",
+        PqScheme::ML_KEM_768,
+        KYBER_768_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp384)
+    .standardized_as("ML-KEM-768__ECDHp384"),
+    AlgorithmInfo::new(
+        Algorithm::ML_KEM_1024,
+        "ML_KEM_1024",
+        PqScheme::ML_KEM_1024,
+        KYBER_1024_SIZES,
+    )
+    .standardized_as("ML-KEM-1024"),
+    AlgorithmInfo::new(
+        Algorithm::ML_KEM_1024__ECDHp521,
+        "ML_KEM_1024__ECDHp521",
+        PqScheme::ML_KEM_1024,
+        KYBER_1024_SIZES,
+    )
+    .with_ecdh(ClassicalScheme::ECDHp521)
+    .standardized_as("ML-KEM-1024__ECDHp521"),
+    AlgorithmInfo::new(
+        Algorithm::ML_DSA_44,
+        "ML_DSA_44",
+        PqScheme::ML_DSA_44,
+        ML_DSA_44_SIZES,
+    )
+    .signing()
+    .standardized_as("ML-DSA-44"),
+    AlgorithmInfo::new(
+        Algorithm::ML_DSA_65,
+        "ML_DSA_65",
+        PqScheme::ML_DSA_65,
+        ML_DSA_65_SIZES,
+    )
+    .signing()
+    .standardized_as("ML-DSA-65"),
+    AlgorithmInfo::new(
+        Algorithm::ML_DSA_87,
+        "ML_DSA_87",
+        PqScheme::ML_DSA_87,
+        ML_DSA_87_SIZES,
+    )
+    .signing()
+    .standardized_as("ML-DSA-87"),
+];
+
+impl Algorithm {
+    /// Returns every algorithm except the `NoAlgorithm` placeholder, in the order of their discriminants.
+    pub fn iter() -> impl Iterator<Item = Algorithm> {
+        (1..ALGORITHM_COUNT).filter_map(|discriminant| Algorithm::try_from(discriminant).ok())
+    }
+
+    /// Returns the name of the algorithm, which is spelled exactly like its variant.
+    pub fn as_str(&self) -> &'static str {
+        self.info().name
+    }
+
+    /// Returns the name of the algorithm as standardized, e.g. "ML-KEM-768" for `ML_KEM_768` and
+    /// "ML-KEM-768__ECDHp384" for its hybrid. Algorithms without a standardized name, including the draft
+    /// `KYBER_*` ones, are named as by `as_str`.
+    pub fn canonical_name(&self) -> &'static str {
+        self.info().canonical_name
+    }
+
+    /// Returns the sizes of the key material of this algorithm. All sizes are 0 for `NoAlgorithm`.
+    pub fn sizes(&self) -> AlgorithmSizes {
+        self.info().sizes
+    }
+
+    /// Returns the post-quantum scheme of this algorithm together with its classical scheme when it is a
+    /// hybrid, so that both can be handled separately. Returns `None` for `NoAlgorithm`.
+    pub fn components(&self) -> Option<(PqScheme, Option<ClassicalScheme>)> {
+        self.info().components
+    }
+
+    /// Returns whether this algorithm combines a post-quantum scheme with a classical one.
+    pub fn is_hybrid(&self) -> bool {
+        matches!(self.components(), Some((_, Some(_))))
+    }
+
+    /// Returns the NIST security category of this algorithm, see `PqScheme::security_level`. For a hybrid this
+    /// is the category of its post-quantum scheme. Returns `None` for `NoAlgorithm`, so that e.g.
+    /// `algorithm.security_level() < Some(3)` also holds for it.
+    pub fn security_level(&self) -> Option<u8> {
+        self.components()
+            .map(|(pq_scheme, _)| pq_scheme.security_level())
+    }
+
+    /// Returns whether this algorithm is a signature scheme, i.e. one that supports `Operation::Sign` and
+    /// `Operation::Verify` instead of `Operation::Encapsulation` and `Operation::Decapsulation`. Every other
+    /// algorithm except `NoAlgorithm` is a key encapsulation mechanism. Both kinds generate key pairs.
+    pub fn supports_signatures(&self) -> bool {
+        self.info().supports_signatures
+    }
+
+    /// Returns whether this algorithm is supported by this build, see `PqScheme::is_supported`.
+    /// `NoAlgorithm` is never supported.
+    pub fn is_supported(&self) -> bool {
+        matches!(self.components(), Some((pq_scheme, _)) if pq_scheme.is_supported())
+    }
+
+    fn info(&self) -> &'static AlgorithmInfo {
+        &ALGORITHMS[*self as usize]
+    }
+}
+
+impl core::fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses the exact spelling of a variant, as returned by `Algorithm::as_str`, or the standardized name
+/// returned by `Algorithm::canonical_name`. "KYBER_768" is the draft `KYBER_768` while both "ML_KEM_768" and
+/// "ML-KEM-768" are `ML_KEM_768`.
+impl core::str::FromStr for Algorithm {
+    type Err = crate::MessageError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        (0..)
+            .map_while(|discriminant| Algorithm::try_from(discriminant).ok())
+            .find(|algorithm| algorithm.as_str() == name || algorithm.canonical_name() == name)
+            .ok_or_else(|| crate::MessageError::UnknownAlgorithmName(name.to_string()))
+    }
+}
+
+/// Number of `Algorithm` variants, `NoAlgorithm` included. The discriminants are `0..ALGORITHM_COUNT`.
+pub const ALGORITHM_COUNT: u32 = 38;
+
+/// Number of `Operation` variants, `NoOperation` included. The discriminants are `0..OPERATION_COUNT`.
+pub const OPERATION_COUNT: u32 = 8;
+
+/// Converts the discriminant as used on the wire (and in C) back into an `Algorithm`. Headers are always
+/// read through this, so a discriminant outside `0..ALGORITHM_COUNT` never turns into an `Algorithm`.
+impl TryFrom<u32> for Algorithm {
+    type Error = crate::MessageError;
+
+    fn try_from(discriminant: u32) -> Result<Self, Self::Error> {
+        usize::try_from(discriminant)
+            .ok()
+            .and_then(|index| ALGORITHMS.get(index))
+            .map(|info| info.algorithm)
+            .ok_or(crate::MessageError::UnknownAlgorithm(discriminant))
+    }
+}
+
+impl TryFrom<u32> for Operation {
+    type Error = crate::MessageError;
+
+    fn try_from(discriminant: u32) -> Result<Self, Self::Error> {
+        match discriminant {
+            0 => Ok(Operation::NoOperation),
+            1 => Ok(Operation::KeypairGeneration),
+            2 => Ok(Operation::Encapsulation),
+            3 => Ok(Operation::Decapsulation),
+            4 => Ok(Operation::Sign),
+            5 => Ok(Operation::Verify),
+            6 => Ok(Operation::Hello),
+            7 => Ok(Operation::Cancel),
+            _ => Err(crate::MessageError::UnknownOperation(discriminant)),
+        }
+    }
 }
 
 /// All possible operations that can be requested.
-#[derive(Serialize, Deserialize, PartialEq)]
+/// The discriminants are sent on the wire just like those of `Algorithm`, so the same rules apply, including
+/// the ordering by discriminant.
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary, Debug))]
 #[repr(C)]
 pub enum Operation {
-    NoOperation,
-    KeypairGeneration,
-    Encapsulation,
-    Decapsulation,
+    /// Placeholder for an unset operation, never a real one. Discriminant 0 is permanently reserved for it,
+    /// as for `Algorithm::NoAlgorithm`.
+    #[default]
+    NoOperation = 0,
+    KeypairGeneration = 1,
+    Encapsulation = 2,
+    Decapsulation = 3,
+    /// Signs the message in the request body.
+    Sign = 4,
+    /// Verifies the signature in the request body. A processing error is reported through a
+    /// non-zero `ResponseHeader::success` and an empty body. When the verification could be performed
+    /// `success` is 0 and the body is a single byte: 1 when the signature is valid and 0 when it is not.
+    Verify = 5,
+    /// Asks the responder which format versions it supports, so that a requester can fall back to an older
+    /// version. The request has no algorithm and an empty body and is accepted by `deserialize_request_header`
+    /// whatever its version. The body of the response lists the supported versions, one byte each.
+    /// See `build_hello_request`, `response::build_hello_response` and `parse_hello_response`.
+    Hello = 6,
+    /// Asks the responder to abort the request it received earlier with the same identifier, e.g. because the
+    /// requester went away during a long key generation. Unlike every other operation the identifier does not
+    /// name a new request but the target of the cancellation. The request has no algorithm and an empty body
+    /// and is not answered on its own: the responder answers the target instead, with
+    /// `response::ResponseStatus::Cancelled` when it stopped the work in time. See `build_cancel_request`.
+    Cancel = 7,
 }
 
-// Necessary so we can get a default size of RequestHeader at run-time so C knows
-// what size buffer to allocate.
-impl Default for Algorithm {
-    fn default() -> Self {
-        Algorithm::NoAlgorithm
+impl Operation {
+    /// Returns the name of the operation, which is spelled exactly like its variant.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::NoOperation => "NoOperation",
+            Operation::KeypairGeneration => "KeypairGeneration",
+            Operation::Encapsulation => "Encapsulation",
+            Operation::Decapsulation => "Decapsulation",
+            Operation::Sign => "Sign",
+            Operation::Verify => "Verify",
+            Operation::Hello => "Hello",
+            Operation::Cancel => "Cancel",
+        }
+    }
+
+    /// Returns how the body of a request for this operation is laid out.
+    /// - KeypairGeneration, Hello, Cancel and NoOperation: no body.
+    /// - Encapsulation: the public key.
+    /// - Decapsulation: the secret key and the ciphertext, structured with `structure_two_entries`.
+    /// - Sign: the message.
+    /// - Verify: the message and the signature, structured with `structure_two_entries`.
+    pub fn request_body_shape(&self) -> BodyShape {
+        match self {
+            Operation::NoOperation
+            | Operation::KeypairGeneration
+            | Operation::Hello
+            | Operation::Cancel => BodyShape::Empty,
+            Operation::Encapsulation | Operation::Sign => BodyShape::Single,
+            Operation::Decapsulation | Operation::Verify => BodyShape::TwoEntries,
+        }
+    }
+
+    /// Returns how the body of a successful response for this operation is laid out. A failed response
+    /// always has an empty body.
+    /// - NoOperation and Cancel: no body, as a cancellation is never answered on its own.
+    /// - KeypairGeneration: the public key and the secret key, structured with `structure_two_entries`.
+    /// - Encapsulation: the shared secret and the ciphertext, structured with `structure_two_entries`.
+    /// - Decapsulation: the shared secret.
+    /// - Sign: the signature.
+    /// - Verify: a single byte, see `Operation::Verify`.
+    /// - Hello: the supported versions, see `Operation::Hello`.
+    pub fn response_body_shape(&self) -> BodyShape {
+        match self {
+            Operation::NoOperation | Operation::Cancel => BodyShape::Empty,
+            Operation::KeypairGeneration | Operation::Encapsulation => BodyShape::TwoEntries,
+            Operation::Decapsulation | Operation::Sign | Operation::Verify | Operation::Hello => {
+                BodyShape::Single
+            }
+        }
     }
 }
 
-impl Default for Operation {
-    fn default() -> Self {
-        Operation::NoOperation
+impl core::fmt::Display for Operation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses the exact spelling of a variant, as returned by `Operation::as_str`.
+impl core::str::FromStr for Operation {
+    type Err = crate::MessageError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        (0..)
+            .map_while(|discriminant| Operation::try_from(discriminant).ok())
+            .find(|operation| operation.as_str() == name)
+            .ok_or_else(|| crate::MessageError::UnknownOperationName(name.to_string()))
+    }
+}
+
+/// The layout of the body of a request or response, see `Operation::request_body_shape` and
+/// `Operation::response_body_shape`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyShape {
+    /// There is no body, so `data_len` is 0.
+    Empty,
+    /// The body is a single value without a length prefix.
+    Single,
+    /// The body consists of two entries structured with `structure_two_entries`.
+    TwoEntries,
+}
+
+impl BodyShape {
+    /// Checks whether `body` is laid out according to this shape.
+    /// # Errors
+    /// `MessageError::LengthMismatch` when an `Empty` body is not empty. For `TwoEntries` any error of
+    /// `destructure_entries`, or `MessageError::InvalidField` when the body does not consist of exactly two
+    /// entries.
+    pub fn check(&self, body: &[u8]) -> Result<(), crate::MessageError> {
+        match self {
+            BodyShape::Empty if !body.is_empty() => Err(crate::MessageError::LengthMismatch {
+                expected: 0,
+                found: body.len(),
+            }),
+            BodyShape::Empty | BodyShape::Single => Ok(()),
+            BodyShape::TwoEntries => {
+                if destructure_entries(body)?.len() != 2 {
+                    return Err(crate::MessageError::InvalidField {
+                        field: "data",
+                        reason: "body does not consist of two entries",
+                    });
+                }
+
+                Ok(())
+            }
+        }
     }
 }
 
 // Ensure that RequestHeader always has a fixed size! If this size changes then change version number!
+// Only append fields so that a `Hello` of another version can still be read.
 /// Header that describes the request sent.
 /// # Explanation of the header
 /// - version is used for compatibility reasons. Typically there is no need to do anything with this
-///   as pq_message_lib deals with version internally.
+///   as pq_message_lib deals with version internally. From Rust it can only be read through
+///   `version()`, as both `RequestHeader::builder` and `Default` stamp `FORMAT_VERSION`.
 /// - identifier is used so that the receiver of the `RequestHeader` can link it back to the original request.
 /// - data_len describes the length of the upcoming data that belongs to this `RequestHeader`. The data after that
-///   will belong to a new `RequestHeader`.
+///   will belong to a new `RequestHeader`. As this is a `u32` a single request can carry at most 2^32 - 1 bytes;
+///   callers are responsible for not truncating larger lengths when filling in this field.
 /// - algorithm is the `Algorithm` that the request is about.
 /// - operation is the `Operation` that the request is about.
-#[derive(Serialize, Deserialize, Default, PartialEq)]
+/// - deadline_ms is the moment, in milliseconds since the Unix epoch, after which the requester is no longer
+///   interested in the response so the responder can skip the work (see `is_expired`). 0 means there is no deadline.
+/// - flags is reserved for features that need a bit in the header, so that adding them does not change the layout.
+///   No bits are defined yet: this version sends 0 and keeps whatever bits it reads.
+#[derive(PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary, Debug))]
+#[repr(C)]
 pub struct RequestHeader {
-    pub version: u8,
-    pub identifier: u64,
+    pub(crate) version: u8,
+    pub identifier: crate::Identifier,
     pub data_len: u32,
     pub algorithm: Algorithm,
     pub operation: Operation,
+    pub deadline_ms: u64,
+    pub flags: u8,
+}
+
+impl Default for RequestHeader {
+    fn default() -> Self {
+        RequestHeader {
+            version: crate::FORMAT_VERSION,
+            identifier: 0,
+            data_len: 0,
+            algorithm: Algorithm::default(),
+            operation: Operation::default(),
+            deadline_ms: 0,
+            flags: 0,
+        }
+    }
+}
+
+impl RequestHeader {
+    /// Returns the format version the header was produced with.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the identifier as a UUID, e.g. one set with `RequestHeaderBuilder::uuid`.
+    #[cfg(feature = "uuid")]
+    pub fn uuid(&self) -> uuid::Uuid {
+        uuid::Uuid::from_u128(self.identifier)
+    }
+
+    /// Serializes the header into `get_serialized_request_header_size()` bytes: every field in order,
+    /// little-endian, with algorithm and operation as `u32` discriminants.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(REQUEST_HEADER_SIZE as usize);
+        bytes.push(self.version);
+        bytes.extend(&self.identifier.to_le_bytes());
+        bytes.extend(&self.data_len.to_le_bytes());
+        bytes.extend(&(self.algorithm as u32).to_le_bytes());
+        bytes.extend(&(self.operation as u32).to_le_bytes());
+        bytes.extend(&self.deadline_ms.to_le_bytes());
+        bytes.push(self.flags);
+        trace_header("serialized", self);
+
+        bytes
+    }
+
+    /// Reads a header serialized by `to_bytes`. Any bytes after the header are ignored and, unlike
+    /// `deserialize_request_header`, the version is not checked.
+    /// # Errors
+    /// `MessageError::BufferTooShort` when `bytes` is too short, `MessageError::UnknownAlgorithm` and
+    /// `MessageError::UnknownOperation` when a discriminant is not known.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, crate::MessageError> {
+        let request_header = Self::read_fields(bytes);
+        match &request_header {
+            Ok(request_header) => trace_header("deserialized", request_header),
+            Err(error) => log_debug!("could not deserialize request header: {}", error),
+        }
+
+        request_header
+    }
+
+    fn read_fields(bytes: &[u8]) -> Result<Self, crate::MessageError> {
+        if bytes.len() < REQUEST_HEADER_SIZE as usize {
+            return Err(crate::MessageError::BufferTooShort {
+                needed: REQUEST_HEADER_SIZE as usize,
+                got: bytes.len(),
+            });
+        }
+
+        let mut fields = crate::FieldReader::new(bytes);
+        Ok(RequestHeader {
+            version: fields.u8(),
+            identifier: fields.identifier(),
+            data_len: fields.u32(),
+            algorithm: Algorithm::try_from(fields.u32())?,
+            operation: Operation::try_from(fields.u32())?,
+            deadline_ms: fields.u64(),
+            flags: fields.u8(),
+        })
+    }
+
+    /// Returns a builder for a header of the current `FORMAT_VERSION`.
+    pub fn builder() -> RequestHeaderBuilder {
+        RequestHeaderBuilder::default()
+    }
+
+    /// Returns the header of a `KeypairGeneration` request, which has no data (see `Request::empty_body`).
+    /// # Errors
+    /// `MessageError::Placeholder` when `algorithm` is `NoAlgorithm`.
+    pub fn keypair_generation(
+        identifier: crate::Identifier,
+        algorithm: Algorithm,
+    ) -> Result<Self, crate::MessageError> {
+        Self::builder()
+            .identifier(identifier)
+            .algorithm(algorithm)
+            .operation(Operation::KeypairGeneration)
+            .build()
+    }
+
+    /// Returns the header of an `Encapsulation` request whose data is `public_key`.
+    /// # Errors
+    /// `MessageError::LengthOverflow` when `public_key` is too long to be described by `data_len` and
+    /// `MessageError::Placeholder` when `algorithm` is `NoAlgorithm`.
+    pub fn encapsulation(
+        identifier: crate::Identifier,
+        algorithm: Algorithm,
+        public_key: &[u8],
+    ) -> Result<Self, crate::MessageError> {
+        let data_len = crate::encode_data_len(public_key.len())?;
+
+        Self::builder()
+            .identifier(identifier)
+            .data_len(data_len)
+            .algorithm(algorithm)
+            .operation(Operation::Encapsulation)
+            .build()
+    }
+
+    /// Returns the header of a `Decapsulation` request whose data is `secret_key` and `ciphertext` structured
+    /// with `structure_two_entries`.
+    /// # Errors
+    /// `MessageError::LengthOverflow` when the structured data is too long to be described by `data_len` and
+    /// `MessageError::Placeholder` when `algorithm` is `NoAlgorithm`.
+    pub fn decapsulation(
+        identifier: crate::Identifier,
+        algorithm: Algorithm,
+        secret_key: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Self, crate::MessageError> {
+        let data_len = secret_key
+            .len()
+            .checked_add(ciphertext.len())
+            .and_then(|length| length.checked_add(2 * crate::LENGTH_PREFIX_SIZE))
+            .ok_or(crate::MessageError::LengthOverflow)
+            .and_then(crate::encode_data_len)?;
+
+        Self::builder()
+            .identifier(identifier)
+            .data_len(data_len)
+            .algorithm(algorithm)
+            .operation(Operation::Decapsulation)
+            .build()
+    }
+
+    /// Returns the header of a `Hello` request, which has no data. See `build_hello_request`.
+    pub fn hello(identifier: crate::Identifier) -> Self {
+        RequestHeader {
+            identifier,
+            operation: Operation::Hello,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the header of a `Cancel` request for the request with `identifier_to_cancel`, which has no
+    /// data. See `build_cancel_request`.
+    pub fn cancel(identifier_to_cancel: crate::Identifier) -> Self {
+        RequestHeader {
+            identifier: identifier_to_cancel,
+            operation: Operation::Cancel,
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether the deadline of this request has passed at `now_ms`, in milliseconds since the
+    /// Unix epoch. A request without a deadline never expires.
+    pub fn is_expired(&self, now_ms: u64) -> bool {
+        self.deadline_ms != 0 && now_ms > self.deadline_ms
+    }
+
+    /// Checks whether the algorithm and operation of this header make sense together.
+    /// `NoAlgorithm` and `NoOperation` are only placeholders and are always rejected, except that a `Hello`
+    /// and a `Cancel` must use `NoAlgorithm` and have no data. Signing and verifying need a signature scheme
+    /// and encapsulating and decapsulating a key encapsulation mechanism, see
+    /// `Algorithm::supports_signatures`. Every way of serializing a header for sending checks this, only
+    /// `to_bytes` does not so that a default header can still be used to compute sizes.
+    /// # Errors
+    /// `MessageError::Placeholder` for `NoAlgorithm` or `NoOperation`, otherwise `MessageError::InvalidField`
+    /// naming the field that was wrong.
+    pub fn validate(&self) -> Result<(), crate::MessageError> {
+        match self.operation {
+            Operation::Hello => self.validate_without_algorithm(
+                "Hello does not use an algorithm",
+                "Hello has an empty body",
+            ),
+            Operation::Cancel => self.validate_without_algorithm(
+                "Cancel does not use an algorithm",
+                "Cancel has an empty body",
+            ),
+            _ if self.algorithm == Algorithm::NoAlgorithm => {
+                Err(crate::MessageError::Placeholder { field: "algorithm" })
+            }
+            Operation::NoOperation => Err(crate::MessageError::Placeholder { field: "operation" }),
+            Operation::Sign | Operation::Verify if !self.algorithm.supports_signatures() => {
+                Err(crate::MessageError::InvalidField {
+                    field: "operation",
+                    reason: "a key encapsulation mechanism cannot sign or verify",
+                })
+            }
+            Operation::Encapsulation | Operation::Decapsulation
+                if self.algorithm.supports_signatures() =>
+            {
+                Err(crate::MessageError::InvalidField {
+                    field: "operation",
+                    reason: "a signature scheme cannot encapsulate or decapsulate",
+                })
+            }
+            Operation::KeypairGeneration
+            | Operation::Encapsulation
+            | Operation::Decapsulation
+            | Operation::Sign
+            | Operation::Verify => Ok(()),
+        }
+    }
+
+    // Checks the header of an operation that is not about key material, which has neither an algorithm nor
+    // data.
+    fn validate_without_algorithm(
+        &self,
+        algorithm_reason: &'static str,
+        data_len_reason: &'static str,
+    ) -> Result<(), crate::MessageError> {
+        if self.algorithm != Algorithm::NoAlgorithm {
+            Err(crate::MessageError::InvalidField {
+                field: "algorithm",
+                reason: algorithm_reason,
+            })
+        } else if self.data_len != 0 {
+            Err(crate::MessageError::InvalidField {
+                field: "data_len",
+                reason: data_len_reason,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Logs the fields of `header`, none of which are sensitive.
+fn trace_header(action: &str, header: &RequestHeader) {
+    log_trace!(
+        "{} request header: version {}, identifier {}, algorithm {}, operation {}, data_len {}, deadline_ms {}, flags {:#04x}",
+        action,
+        header.version,
+        header.identifier,
+        header.algorithm,
+        header.operation,
+        header.data_len,
+        header.deadline_ms,
+        header.flags
+    );
+}
+
+/// Builds a `RequestHeader`, see `RequestHeader::builder`. Fields that are not set keep their default,
+/// i.e. 0, `NoAlgorithm`, `NoOperation`, no deadline and no flags.
+#[derive(Default)]
+pub struct RequestHeaderBuilder {
+    identifier: crate::Identifier,
+    data_len: u32,
+    algorithm: Algorithm,
+    operation: Operation,
+    deadline_ms: u64,
+    flags: u8,
+}
+
+impl RequestHeaderBuilder {
+    pub fn identifier(mut self, identifier: crate::Identifier) -> Self {
+        self.identifier = identifier;
+        self
+    }
+
+    /// Uses `uuid` as the identifier.
+    #[cfg(feature = "uuid")]
+    pub fn uuid(self, uuid: uuid::Uuid) -> Self {
+        self.identifier(uuid.as_u128())
+    }
+
+    pub fn data_len(mut self, data_len: u32) -> Self {
+        self.data_len = data_len;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    pub fn operation(mut self, operation: Operation) -> Self {
+        self.operation = operation;
+        self
+    }
+
+    pub fn deadline_ms(mut self, deadline_ms: u64) -> Self {
+        self.deadline_ms = deadline_ms;
+        self
+    }
+
+    pub fn flags(mut self, flags: u8) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Returns the header with `FORMAT_VERSION` as its version.
+    /// # Errors
+    /// `MessageError::InvalidField` or `MessageError::Placeholder` when the algorithm and operation are not
+    /// valid together (see `RequestHeader::validate`).
+    pub fn build(self) -> Result<RequestHeader, crate::MessageError> {
+        let request_header = RequestHeader {
+            version: crate::FORMAT_VERSION,
+            identifier: self.identifier,
+            data_len: self.data_len,
+            algorithm: self.algorithm,
+            operation: self.operation,
+            deadline_ms: self.deadline_ms,
+            flags: self.flags,
+        };
+        if let Err(error) = request_header.validate() {
+            log_debug!("refusing to build request header: {}", error);
+            return Err(error);
+        }
+
+        Ok(request_header)
+    }
 }
 
 /// Convenience struct to allow request body to be stored together together with the header.
+/// The body, e.g. the private key of a decapsulation request, is securely cleared when it is dropped; the
+/// clearing is guaranteed to not be optimized away. Copies of the body made elsewhere (e.g. by cloning it
+/// or by growing it past its capacity) are not cleared.
 pub struct Request {
     pub header: RequestHeader,
-    pub body: Vec<u8>,
+    pub body: zeroize::Zeroizing<Vec<u8>>,
+}
+
+impl Request {
+    /// Returns a request without data for `header`, e.g. one made by `RequestHeader::keypair_generation`.
+    /// # Errors
+    /// `MessageError::LengthMismatch` when the `data_len` of `header` is not 0.
+    pub fn empty_body(header: RequestHeader) -> Result<Self, crate::MessageError> {
+        if header.data_len != 0 {
+            return Err(crate::MessageError::LengthMismatch {
+                expected: header.data_len as usize,
+                found: 0,
+            });
+        }
+
+        Ok(Request {
+            header,
+            body: zeroize::Zeroizing::new(Vec::new()),
+        })
+    }
+
+    /// Serializes the header followed by the body, the same bytes as a single request pushed to a
+    /// `RequestStreamWriter` (so with the `checksum` feature the checksum trailer is included).
+    /// # Errors
+    /// The errors of `RequestStreamWriter::push`, e.g. `MessageError::LengthMismatch` when the body is not
+    /// `data_len` bytes long.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, crate::MessageError> {
+        let mut writer = RequestStreamWriter::new();
+        writer.push(&self.header, &self.body)?;
+
+        Ok(writer.into_bytes())
+    }
+
+    /// Reads a request serialized by `to_bytes`, using `data_len` to find the end of the body.
+    /// # Errors
+    /// `MessageError::BufferTooShort` when `bytes` is shorter than the header and its `data_len` bytes of data,
+    /// `MessageError::LengthMismatch` when there are bytes after the request and any of the errors of
+    /// `deserialize_request_header`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, crate::MessageError> {
+        let mut reader = RequestStreamReader::new(bytes);
+        let request = reader.read_request()?;
+        if reader.offset != bytes.len() {
+            return Err(crate::MessageError::LengthMismatch {
+                expected: reader.offset,
+                found: bytes.len(),
+            });
+        }
+
+        Ok(request)
+    }
 }
 
 /// Returns the size needed for the buffer where the serialized request header will be stored.
-/// Will evaluate only when used for the first time.
+/// The size is a constant computed at compile time, so this cannot fail or panic.
 #[no_mangle]
 pub extern "C" fn get_serialized_request_header_size() -> u64 {
-    *REQUEST_HEADER_SIZE
+    REQUEST_HEADER_SIZE
+}
+
+/// Sets `sizes` to the sizes of the key material of `algorithm`.
+/// # Returns
+/// 0 on success.
+/// -1 when sizes was a null pointer.
+/// -2 when algorithm was `NoAlgorithm`.
+/// # Safety
+/// `sizes` must point to a valid `AlgorithmSizes`.
+#[no_mangle]
+pub unsafe extern "C" fn algorithm_sizes(algorithm: Algorithm, sizes: *mut AlgorithmSizes) -> i16 {
+    if sizes.is_null() {
+        return -1;
+    } else if algorithm == Algorithm::NoAlgorithm {
+        return -2;
+    }
+
+    *sizes = algorithm.sizes();
+
+    0
+}
+
+/// Fills `out` with the algorithms supported by this build (see `Algorithm::is_supported`), so that a
+/// requester can avoid asking for algorithms that will fail. Call it with a null `out` and a `capacity` of 0
+/// to find out how many there are.
+/// # Returns
+/// The number of algorithms written to `out` on success.
+/// Minus the number of supported algorithms when `capacity` is too small (or `out` is null), in which case
+/// nothing is written.
+/// # Safety
+/// `out` must point to at least `capacity` writable `Algorithm`s.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn supported_algorithms(out: *mut Algorithm, capacity: libc::size_t) -> i64 {
+    let supported: Vec<Algorithm> = Algorithm::iter().filter(Algorithm::is_supported).collect();
+    if out.is_null() || capacity < supported.len() {
+        return -(supported.len() as i64);
+    }
+
+    let out = core::slice::from_raw_parts_mut(out, supported.len());
+    out.copy_from_slice(&supported);
+
+    supported.len() as i64
 }
 
 /// Receive a serialized header. Simply attach the raw bytes behind this serialized header when sending
-/// over a channel.
+/// over a channel. With the `checksum` feature the message must be finished with `write_checksum`.
+/// `deadline_ms` is described at `RequestHeader`; pass 0 for no deadline.
 /// # Returns
-/// 0 on success, -1 on serialization failure.
+/// The number of bytes written to `target_buffer` on success, i.e. `get_serialized_request_header_size()`.
+/// -1 on serialization failure, -2 when the algorithm and operation are not valid together
+/// (see `RequestHeader::validate`), -3 when the algorithm or operation is `NoAlgorithm` or `NoOperation`, which
+/// are only placeholders. This used to return 0 on success, so check for a negative value to
+/// detect failure.
 /// # Safety
 /// Ensure that `target_buffer` is large enough before executing this function.
+#[cfg(feature = "std")]
 #[no_mangle]
 pub unsafe extern "C" fn serialize_request_header(
     target_buffer: *mut libc::c_uchar,
     target_buffer_len: libc::size_t,
-    identifier: u64,
+    identifier: crate::Identifier,
     data_len: u32,
     algorithm: Algorithm,
     operation: Operation,
-) -> i16 {
-    if target_buffer.is_null() || target_buffer_len < get_serialized_request_header_size() as usize
+    deadline_ms: u64,
+) -> i64 {
+    // Compared as `u64`, which every `size_t` fits in, so that neither side is truncated
+    if target_buffer.is_null() || (target_buffer_len as u64) < get_serialized_request_header_size()
     {
         return -1;
     }
 
-    let request_header = RequestHeader {
-        version: crate::FORMAT_VERSION,
-        identifier,
-        data_len,
-        algorithm,
-        operation,
-    };
-
-    if let Ok(encoded) = bincode::serialize(&request_header) {
-        std::ptr::copy_nonoverlapping(encoded.as_ptr(), target_buffer, encoded.len());
-        0
-    } else {
+    match serialize_request_header_vec(identifier, data_len, algorithm, operation, deadline_ms) {
+        Ok(encoded) => {
+            core::ptr::copy_nonoverlapping(encoded.as_ptr(), target_buffer, encoded.len());
+            encoded.len() as i64
+        }
+        Err(crate::MessageError::InvalidField { .. }) => -2,
+        Err(crate::MessageError::Placeholder { .. }) => -3,
         // Unsure whether this is actually reachable but produce an error just in case so we don't crash.
         // Maybe in case of out-of-memory this can occur?
-        -1
+        Err(_) => -1,
     }
 }
 
-/// Given a a buffer will return a `ResponseHeader`. This header can be used to determine how many bytes
-/// of data are coming up.
+/// Same as `serialize_request_header` except that the header is written at `offset` within
+/// `target_buffer`, e.g. to build several framed messages in one reused buffer without querying
+/// `get_serialized_request_header_size` first.
 /// # Returns
-/// A RequestHeader for success. Anything else is a DeserializationError (e.g. when the provided buffer is too short)
-pub fn deserialize_request_header(
-    request_header: &[u8],
-) -> Result<RequestHeader, crate::DeserializationError> {
-    bincode::deserialize(&request_header).map_err(|_| crate::DeserializationError)
-}
+/// The offset right after the header on success, where the body can be written.
+/// -1 on serialization failure or when the header does not fit in `target_buffer_len` bytes at `offset`.
+/// -2 and -3 just like `serialize_request_header`.
+/// # Safety
+/// `target_buffer` must point to at least `target_buffer_len` writable bytes.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn serialize_request_header_at(
+    target_buffer: *mut libc::c_uchar,
+    target_buffer_len: libc::size_t,
+    offset: libc::size_t,
+    identifier: crate::Identifier,
+    data_len: u32,
+    algorithm: Algorithm,
+    operation: Operation,
+    deadline_ms: u64,
+) -> i64 {
+    let header_size = match usize::try_from(get_serialized_request_header_size()) {
+        Ok(header_size) => header_size,
+        Err(_) => return -1,
+    };
+    match offset.checked_add(header_size) {
+        Some(end) if end <= target_buffer_len => (),
+        _ => return -1,
+    }
+    if target_buffer.is_null() {
+        return -1;
+    }
 
-/// Function which will put a `RequestHeader` and data together in a `Request`.
-/// This is purely a convenience function such that one can operate on a `Request` instead
-/// of keeping the header and data separate.
-pub fn deserialize_request(request_header: RequestHeader, request_data: Vec<u8>) -> Request {
-    Request {
-        header: request_header,
-        body: request_data,
+    let written = serialize_request_header(
+        target_buffer.add(offset),
+        target_buffer_len - offset,
+        identifier,
+        data_len,
+        algorithm,
+        operation,
+        deadline_ms,
+    );
+    if written < 0 {
+        return written;
     }
-}
 
-/// Given the length of two entries returns the length of the buffer required to fit both entries including their lengths.
-#[no_mangle]
-pub extern "C" fn structure_two_entries_length(
-    entry1_length: libc::size_t,
-    entry2_length: libc::size_t,
-) -> libc::size_t {
-    entry1_length + entry2_length + 2 * std::mem::size_of::<usize>()
+    (offset + header_size) as i64
 }
 
-/// Given two entries and their length this function will put them back-to-back into data with length included.
+/// Writes a whole request into `out`: the header, `body` and with the `checksum` feature the trailer, so the
+/// result can be sent as it is. These are the same bytes as `serialize_request_header` followed by `body`
+/// (and `write_checksum`), without a deadline.
 /// # Returns
-/// 0 on success.
-/// -1 when data was a null pointer.
-/// -2 when entry1 was a null pointer.
-/// -3 when entry2 was a null pointer.
+/// The number of bytes written to `out` on success.
+/// -1 when `out` is a null pointer, or `body` is a null pointer while `body_len` is not 0.
+/// -2 and -3 just like `serialize_request_header`.
+/// -4 when `out_len` is too small to hold the request. Nothing is written in that case.
+/// -5 when `body_len` is too long to be described by the `data_len` of the header.
 /// # Safety
-/// If entry1_length or entry2_length are not appropriate (too long for example) then an out of bounds
-/// access will occur; this is a bug introduced by the caller. When used in combination with the
-/// `structure_two_entries_length` function this will never occur.
+/// `body` must point to at least `body_len` bytes (unless `body_len` is 0) and `out` must point to at least
+/// `out_len` writable bytes.
+#[cfg(feature = "std")]
 #[no_mangle]
-pub unsafe extern "C" fn structure_two_entries(
-    data: *mut libc::c_uchar,
-    entry1_length: libc::size_t,
-    entry2_length: libc::size_t,
-    entry1: *const libc::c_uchar,
-    entry2: *const libc::c_uchar,
-) -> i16 {
-    if data.is_null() {
+pub unsafe extern "C" fn build_request(
+    out: *mut libc::c_uchar,
+    out_len: libc::size_t,
+    identifier: crate::Identifier,
+    algorithm: Algorithm,
+    operation: Operation,
+    body: *const libc::c_uchar,
+    body_len: libc::size_t,
+) -> i64 {
+    if out.is_null() || (body.is_null() && body_len != 0) {
         return -1;
-    } else if entry1.is_null() {
-        return -2;
-    } else if entry2.is_null() {
-        return -3;
     }
 
-    let usize_size_in_bytes = std::mem::size_of::<usize>();
-    std::ptr::copy_nonoverlapping(
-        entry1_length.to_le_bytes().as_ptr(),
-        data,
-        usize_size_in_bytes,
-    );
+    let body = if body_len == 0 {
+        &[][..]
+    } else {
+        core::slice::from_raw_parts(body, body_len)
+    };
+    let data_len = match crate::encode_data_len(body_len) {
+        Ok(data_len) => data_len,
+        Err(_) => return -5,
+    };
+    let header = match RequestHeader::builder()
+        .identifier(identifier)
+        .data_len(data_len)
+        .algorithm(algorithm)
+        .operation(operation)
+        .build()
+    {
+        Ok(header) => header,
+        Err(crate::MessageError::InvalidField { .. }) => return -2,
+        Err(crate::MessageError::Placeholder { .. }) => return -3,
+        Err(_) => return -1,
+    };
 
-    let data = data.add(usize_size_in_bytes);
-    std::ptr::copy(entry1, data, entry1_length);
+    let mut writer = RequestStreamWriter::new();
+    if writer.push(&header, body).is_err() {
+        return -1;
+    }
+    let request = zeroize::Zeroizing::new(writer.into_bytes());
+    if request.len() > out_len {
+        return -4;
+    }
+
+    core::ptr::copy_nonoverlapping(request.as_ptr(), out, request.len());
+
+    request.len() as i64
+}
+
+/// Given a pointer to `data_len` bytes will return the `RequestHeader` at their start, the counterpart of
+/// `deserialize_response_header` for a responder written in C. This header can be used to determine how many
+/// bytes of data are coming up. A `Hello` is accepted whatever its version, just like with
+/// `deserialize_request_header`.
+/// # Returns
+/// 0 on success.
+/// -1 when a null pointer was passed in.
+/// -2 when `data_len` is too short to hold the header (or with the `checksum` feature the whole message).
+/// -3 for deserialization failure, e.g. an unknown algorithm or operation.
+/// -4 for a version in the header that this crate does not accept. `request_header` is still set so that
+/// the version of the other side can be read.
+/// -5 for mismatch of the checksum trailer (only with the `checksum` feature).
+/// # Safety
+/// `data` must point to at least `data_len` readable bytes and `request_header` to a writable `RequestHeader`.
+/// With the `checksum` feature `data` must hold the complete message, that is the header followed by its data
+/// and the checksum trailer.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn deserialize_request_header_c(
+    data: *const libc::c_uchar,
+    data_len: libc::size_t,
+    request_header: *mut RequestHeader,
+) -> i16 {
+    if data.is_null() || request_header.is_null() {
+        return -1;
+    } else if (data_len as u64) < get_serialized_request_header_size() {
+        return -2;
+    }
+
+    let data = core::slice::from_raw_parts(data, data_len);
+    match RequestHeader::from_bytes(data) {
+        Ok(deserialized) => *request_header = deserialized,
+        Err(_) => return -3,
+    }
+
+    if (*request_header).operation != Operation::Hello
+        && crate::check_format_version((*request_header).version).is_err()
+    {
+        return -4;
+    }
+
+    #[cfg(feature = "checksum")]
+    {
+        // On a 32-bit target a data_len close to u32::MAX overflows the length of the message, which then
+        // does not fit in `data` either
+        let framing_len =
+            get_serialized_request_header_size() as usize + crate::checksum::CHECKSUM_SIZE;
+        let message = ((*request_header).data_len as usize)
+            .checked_add(framing_len)
+            .and_then(|message_len| data.get(..message_len));
+        match message {
+            Some(message) if crate::checksum::verify_checksum(message).is_err() => return -5,
+            Some(_) => {}
+            None => return -2,
+        }
+    }
+
+    0
+}
+
+/// Receive a serialized header for use from Rust. This produces exactly the same bytes as
+/// `serialize_request_header` without having to deal with pointers. Simply attach the raw bytes
+/// behind this serialized header when sending over a channel.
+/// # Errors
+/// `MessageError::InvalidField` or `MessageError::Placeholder` when the algorithm and operation are not
+/// valid together (see `RequestHeader::validate`).
+pub fn serialize_request_header_vec(
+    identifier: crate::Identifier,
+    data_len: u32,
+    algorithm: Algorithm,
+    operation: Operation,
+    deadline_ms: u64,
+) -> Result<Vec<u8>, crate::MessageError> {
+    let request_header = RequestHeader::builder()
+        .identifier(identifier)
+        .data_len(data_len)
+        .algorithm(algorithm)
+        .operation(operation)
+        .deadline_ms(deadline_ms)
+        .build()?;
+
+    Ok(request_header.to_bytes())
+}
+
+/// Given a a buffer will return a `RequestHeader`. This header can be used to determine how many bytes
+/// of data are coming up. Only the first `get_serialized_request_header_size()` bytes are read, so the buffer
+/// may also hold the data that follows the header.
+/// # Returns
+/// A RequestHeader for success. Otherwise a `MessageError` describing why the header could not be read,
+/// e.g. `BufferTooShort` when the provided buffer is too short or `VersionMismatch` when the header was
+/// produced with a format version this crate does not accept. A `Hello` is returned whatever its version.
+/// Check `RequestHeader::version` to adapt to the version of the other side.
+pub fn deserialize_request_header(
+    request_header: &[u8],
+) -> Result<RequestHeader, crate::MessageError> {
+    let request_header = RequestHeader::from_bytes(request_header)?;
+    if request_header.operation != Operation::Hello {
+        crate::check_format_version(request_header.version)?;
+    }
+
+    Ok(request_header)
+}
+
+/// Like `deserialize_request_header`, but rejects a header describing more than `max_data_len` bytes of
+/// data, so that a reader never allocates for a length an attacker picked. `RECOMMENDED_MAX_DATA_LEN` fits
+/// the data of every operation.
+/// # Returns
+/// A RequestHeader for success. Otherwise `MessageError::BodyTooLarge` when `data_len` exceeds
+/// `max_data_len`, or any of the errors of `deserialize_request_header`.
+pub fn deserialize_request_header_bounded(
+    request_header: &[u8],
+    max_data_len: u32,
+) -> Result<RequestHeader, crate::MessageError> {
+    let request_header = deserialize_request_header(request_header)?;
+    if request_header.data_len > max_data_len {
+        log_debug!(
+            "request header describes {} bytes of data but at most {} are accepted",
+            request_header.data_len,
+            max_data_len
+        );
+        return Err(crate::MessageError::BodyTooLarge {
+            max: max_data_len,
+            found: request_header.data_len,
+        });
+    }
+
+    Ok(request_header)
+}
+
+/// Receive a serialized header followed by an HMAC-SHA256 tag over it (see `auth::AUTH_TAG_SIZE`) computed
+/// with `key`. This prevents an attacker on the channel from altering e.g. the operation without being noticed.
+/// Simply attach the raw bytes behind the tag when sending over a channel, like with `serialize_request_header_vec`.
+/// # Errors
+/// `MessageError::InvalidField` or `MessageError::Placeholder` when the algorithm and operation are not
+/// valid together (see `RequestHeader::validate`).
+#[cfg(feature = "auth")]
+pub fn serialize_request_header_authenticated(
+    identifier: crate::Identifier,
+    data_len: u32,
+    algorithm: Algorithm,
+    operation: Operation,
+    deadline_ms: u64,
+    key: &[u8],
+) -> Result<Vec<u8>, crate::MessageError> {
+    let mut serialized =
+        serialize_request_header_vec(identifier, data_len, algorithm, operation, deadline_ms)?;
+    let tag = crate::auth::compute_tag(key, &serialized);
+    serialized.extend_from_slice(&tag);
+
+    Ok(serialized)
+}
+
+/// Given a buffer produced by `serialize_request_header_authenticated` will verify the tag with `key` and
+/// return the `RequestHeader`. Nothing of the header is looked at before the tag has been verified.
+/// # Returns
+/// A RequestHeader for success. Otherwise `MessageError::AuthFailed` when the tag does not match, or any
+/// of the errors of `deserialize_request_header`.
+#[cfg(feature = "auth")]
+pub fn deserialize_request_header_authenticated(
+    request_header: &[u8],
+    key: &[u8],
+) -> Result<RequestHeader, crate::MessageError> {
+    let header_size = get_serialized_request_header_size() as usize;
+    let needed = header_size + crate::auth::AUTH_TAG_SIZE;
+    if request_header.len() < needed {
+        return Err(crate::MessageError::BufferTooShort {
+            needed,
+            got: request_header.len(),
+        });
+    }
+
+    let (header, rest) = request_header.split_at(header_size);
+    crate::auth::verify_tag(key, header, &rest[..crate::auth::AUTH_TAG_SIZE])?;
+
+    deserialize_request_header(header)
+}
+
+/// Returns a serialized `Hello` request, which has no data. Like `Request::to_bytes` this is the whole
+/// request, with the `checksum` feature including its trailer, so it can be written to the stream as it is.
+pub fn build_hello_request(identifier: crate::Identifier) -> Vec<u8> {
+    serialize_empty_request(&RequestHeader::hello(identifier))
+}
+
+/// Returns a serialized `Cancel` request, which has no data, asking the responder to abort the request with
+/// `identifier_to_cancel`. The identifier of the cancel request is that of its target, see
+/// `Operation::Cancel`. Like `build_hello_request` this is the whole request.
+pub fn build_cancel_request(identifier_to_cancel: crate::Identifier) -> Vec<u8> {
+    serialize_empty_request(&RequestHeader::cancel(identifier_to_cancel))
+}
+
+// Serializes a request without data the way `RequestStreamWriter::push` does. `Hello` and `Cancel` headers
+// made by `RequestHeader` always pass its checks, so there is nothing to fail.
+fn serialize_empty_request(header: &RequestHeader) -> Vec<u8> {
+    #[allow(unused_mut)]
+    let mut request = header.to_bytes();
+    #[cfg(feature = "checksum")]
+    crate::checksum::append_checksum(&mut request);
+
+    request
+}
+
+/// Given the data of the response to a `Hello` returns the format versions supported by the responder.
+/// # Errors
+/// `MessageError::BufferTooShort` when the response does not list any version.
+pub fn parse_hello_response(response_data: &[u8]) -> Result<Vec<u8>, crate::MessageError> {
+    if response_data.is_empty() {
+        return Err(crate::MessageError::BufferTooShort { needed: 1, got: 0 });
+    }
+
+    Ok(response_data.to_vec())
+}
+
+/// Function which will put a `RequestHeader` and data together in a `Request`.
+/// This is purely a convenience function such that one can operate on a `Request` instead
+/// of keeping the header and data separate.
+/// With the `checksum` feature the received message (header, data and trailer) has to be passed through
+/// `checksum::verify_checksum` first. The data is cleared when the `Request` is dropped.
+pub fn deserialize_request(request_header: RequestHeader, request_data: Vec<u8>) -> Request {
+    Request {
+        header: request_header,
+        body: zeroize::Zeroizing::new(request_data),
+    }
+}
+
+/// Reads a single request from `reader`: first the header, then exactly `data_len` bytes of data (and with the
+/// `checksum` feature the trailer, which is verified).
+/// # Errors
+/// `MessageError::UnexpectedEof` when the stream ends before the whole header (or the trailer) has been read,
+/// `MessageError::LengthMismatch` when it ends in the middle of the data, with the number of bytes that
+/// were read as `found`, `MessageError::MessageTooLarge` when the request would exceed `MAX_MESSAGE_SIZE`, the
+/// errors of `deserialize_request_header`, `MessageError::ChecksumMismatch` with the `checksum` feature and
+/// `MessageError::Io` when reading fails.
+#[cfg(feature = "std")]
+pub fn read_request<R: std::io::Read>(reader: &mut R) -> Result<Request, crate::MessageError> {
+    read_request_bounded(reader, MAX_MESSAGE_SIZE)
+}
+
+/// Like `read_request`, but accepts requests of up to `max_message_size` bytes, header and trailer included,
+/// instead of `MAX_MESSAGE_SIZE`.
+/// # Errors
+/// The errors of `read_request`, with `MessageError::MessageTooLarge` for a request exceeding
+/// `max_message_size`.
+#[cfg(feature = "std")]
+pub fn read_request_bounded<R: std::io::Read>(
+    reader: &mut R,
+    max_message_size: usize,
+) -> Result<Request, crate::MessageError> {
+    let header_size = get_serialized_request_header_size() as usize;
+    let mut message = zeroize::Zeroizing::new(vec![0; header_size]);
+    read_exact(reader, &mut message)?;
+    let header = deserialize_request_header(&message)?;
+    crate::check_message_size(header_size, header.data_len, max_message_size)?;
+
+    let data_len = header.data_len as usize;
+    message.resize(header_size + data_len, 0);
+    let mut filled = header_size;
+    while filled < message.len() {
+        match reader.read(&mut message[filled..]) {
+            Ok(0) => {
+                log_debug!(
+                    "stream ended after {} of {} bytes of request data",
+                    filled - header_size,
+                    data_len
+                );
+                return Err(crate::MessageError::LengthMismatch {
+                    expected: data_len,
+                    found: filled - header_size,
+                });
+            }
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(error) => return Err(error.into()),
+        }
+    }
+
+    #[cfg(feature = "checksum")]
+    {
+        let mut trailer = [0; crate::checksum::CHECKSUM_SIZE];
+        read_exact(reader, &mut trailer)?;
+        message.extend_from_slice(&trailer);
+        crate::checksum::verify_checksum(&message)?;
+    }
+
+    Ok(deserialize_request(
+        header,
+        message[header_size..header_size + data_len].to_vec(),
+    ))
+}
+
+/// Reads a single request from `reader` like `read_request`, but gives up when it has not arrived completely
+/// within `timeout`, so that a peer that stalls in the middle of a request cannot block the reader forever.
+/// The deadline is checked between reads: a read that blocks is not interrupted, so give the stream a read
+/// timeout shorter than `timeout` (e.g. with `UnixStream::set_read_timeout`), after which the read is retried
+/// until the deadline has passed.
+///
+/// On a timeout the bytes read so far are consumed from the stream and lost, they are reported as `consumed`.
+/// When that is 0 the stream is still at the start of a request and can be read again, otherwise the start
+/// of the next request can no longer be found and the stream should be closed.
+/// # Errors
+/// `MessageError::Timeout` when the deadline passes, `MessageError::UnexpectedEof` when the stream ends in
+/// the middle of the request and otherwise the errors of `read_request`.
+#[cfg(feature = "std")]
+pub fn read_request_timeout<R: std::io::Read>(
+    reader: &mut R,
+    timeout: core::time::Duration,
+) -> Result<Request, crate::MessageError> {
+    read_request_timeout_bounded(reader, timeout, MAX_MESSAGE_SIZE)
+}
+
+/// Like `read_request_timeout`, but accepts requests of up to `max_message_size` bytes, header and trailer
+/// included, instead of `MAX_MESSAGE_SIZE`.
+/// # Errors
+/// The errors of `read_request_timeout`, with `MessageError::MessageTooLarge` for a request exceeding
+/// `max_message_size`.
+#[cfg(feature = "std")]
+pub fn read_request_timeout_bounded<R: std::io::Read>(
+    reader: &mut R,
+    timeout: core::time::Duration,
+    max_message_size: usize,
+) -> Result<Request, crate::MessageError> {
+    let deadline = std::time::Instant::now().checked_add(timeout);
+    let header_size = get_serialized_request_header_size() as usize;
+    let mut message = zeroize::Zeroizing::new(vec![0; header_size]);
+    crate::read_exact_until(reader, &mut message, 0, deadline)?;
+    let header = deserialize_request_header(&message)?;
+    let message_size = crate::check_message_size(header_size, header.data_len, max_message_size)?;
+
+    let data_len = header.data_len as usize;
+    message.resize(message_size, 0);
+    crate::read_exact_until(reader, &mut message[header_size..], header_size, deadline)?;
+
+    #[cfg(feature = "checksum")]
+    crate::checksum::verify_checksum(&message)?;
+
+    Ok(deserialize_request(
+        header,
+        message[header_size..header_size + data_len].to_vec(),
+    ))
+}
+
+/// Fills `buffer` from `reader`, turning the end of the stream into `MessageError::UnexpectedEof`.
+#[cfg(feature = "std")]
+fn read_exact<R: std::io::Read>(
+    reader: &mut R,
+    buffer: &mut [u8],
+) -> Result<(), crate::MessageError> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => {
+            Err(crate::MessageError::UnexpectedEof)
+        }
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Serializes any number of requests back-to-back into a single buffer, which can be read again with
+/// `RequestStreamReader`. With the `checksum` feature every request gets its own checksum trailer.
+#[derive(Default)]
+pub struct RequestStreamWriter {
+    stream: Vec<u8>,
+}
+
+impl RequestStreamWriter {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Appends a request to the stream. Nothing is appended when an error is returned.
+    /// # Errors
+    /// `MessageError::LengthMismatch` when `body` is not `data_len` bytes long, `MessageError::VersionMismatch`
+    /// when the header does not carry the `FORMAT_VERSION` of this crate and `MessageError::InvalidField` or
+    /// `MessageError::Placeholder` when the header is not valid (see `RequestHeader::validate`).
+    pub fn push(&mut self, header: &RequestHeader, body: &[u8]) -> Result<(), crate::MessageError> {
+        if let Err(error) = check_sendable(header, body) {
+            log_debug!("refusing to serialize request: {}", error);
+            return Err(error);
+        }
+
+        let mut request = header.to_bytes();
+        request.extend_from_slice(body);
+        #[cfg(feature = "checksum")]
+        crate::checksum::append_checksum(&mut request);
+        self.stream.extend(request);
+
+        Ok(())
+    }
+
+    /// Returns the serialized requests.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.stream
+    }
+}
+
+/// The checks of `RequestStreamWriter::push`.
+fn check_sendable(header: &RequestHeader, body: &[u8]) -> Result<(), crate::MessageError> {
+    validate_body_len(header, body)?;
+    if header.version != crate::FORMAT_VERSION {
+        return Err(crate::MessageError::VersionMismatch {
+            expected: crate::FORMAT_VERSION,
+            found: header.version,
+        });
+    }
+
+    header.validate()
+}
+
+/// Checks that `body` is exactly the `data_len` bytes that `header` describes, so that a body that was cut
+/// short or that runs into the next request is caught where it is read instead of further along.
+/// # Errors
+/// `MessageError::LengthMismatch` when the length of `body` differs from `data_len`.
+pub fn validate_body_len(header: &RequestHeader, body: &[u8]) -> Result<(), crate::MessageError> {
+    if header.data_len as usize != body.len() {
+        log_debug!(
+            "request body of {} bytes but data_len is {}",
+            body.len(),
+            header.data_len
+        );
+        return Err(crate::MessageError::LengthMismatch {
+            expected: header.data_len as usize,
+            found: body.len(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Iterates over the requests in a buffer produced by `RequestStreamWriter`, using the `data_len` of every
+/// header to find the start of the next request. Iteration ends after the first error.
+pub struct RequestStreamReader<'a> {
+    stream: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> RequestStreamReader<'a> {
+    pub fn new(stream: &'a [u8]) -> Self {
+        RequestStreamReader { stream, offset: 0 }
+    }
+
+    fn read_request(&mut self) -> Result<Request, crate::MessageError> {
+        let remaining = &self.stream[self.offset..];
+        let header = deserialize_request_header(remaining)?;
+
+        let header_size = get_serialized_request_header_size() as usize;
+        #[cfg(not(feature = "checksum"))]
+        let trailer_size = 0;
+        #[cfg(feature = "checksum")]
+        let trailer_size = crate::checksum::CHECKSUM_SIZE;
+
+        // On a 32-bit target a data_len close to u32::MAX overflows, such a request never fits in the stream
+        let request_size = (header.data_len as usize).checked_add(header_size + trailer_size);
+        let request_size = match request_size {
+            Some(request_size) if request_size <= remaining.len() => request_size,
+            request_size => {
+                return Err(crate::MessageError::BufferTooShort {
+                    needed: request_size
+                        .and_then(|request_size| request_size.checked_add(self.offset))
+                        .unwrap_or(usize::MAX),
+                    got: self.stream.len(),
+                })
+            }
+        };
+
+        #[cfg(feature = "checksum")]
+        crate::checksum::verify_checksum(&remaining[..request_size])?;
+
+        let body = &remaining[header_size..request_size - trailer_size];
+        validate_body_len(&header, body)?;
+        self.offset += request_size;
+
+        Ok(deserialize_request(header, body.to_vec()))
+    }
+}
+
+impl<'a> Iterator for RequestStreamReader<'a> {
+    type Item = Result<Request, crate::MessageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset == self.stream.len() {
+            return None;
+        }
+
+        let request = self.read_request();
+        if request.is_err() {
+            self.offset = self.stream.len();
+        }
+
+        Some(request)
+    }
+}
+
+/// Reassembles requests from bytes that arrive in pieces of any size, e.g. from a non-blocking socket. Bytes
+/// are buffered until a whole request (header, `data_len` bytes of data and with the `checksum` feature the
+/// trailer) has arrived, whatever is left over is kept for the next one. Like `Channel`, the data of a request
+/// is limited to `max_data_len` bytes (`RECOMMENDED_MAX_DATA_LEN` by default), and the whole request to
+/// `max_message_size` bytes (`MAX_MESSAGE_SIZE` by default). Raise both for algorithms with larger data.
+pub struct FrameDecoder {
+    buffer: zeroize::Zeroizing<Vec<u8>>,
+    max_data_len: u32,
+    max_message_size: usize,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        FrameDecoder {
+            buffer: zeroize::Zeroizing::new(Vec::new()),
+            max_data_len: RECOMMENDED_MAX_DATA_LEN,
+            max_message_size: MAX_MESSAGE_SIZE,
+        }
+    }
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets the largest `data_len` of a request that is accepted.
+    pub fn set_max_data_len(&mut self, max_data_len: u32) {
+        self.max_data_len = max_data_len;
+    }
+
+    /// Sets the largest request that is accepted, header and trailer included.
+    pub fn set_max_message_size(&mut self, max_message_size: usize) {
+        self.max_message_size = max_message_size;
+    }
+
+    /// Number of bytes buffered that are not part of a returned request yet.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Buffers `bytes` and returns the first request that is complete, if any. When `bytes` completes more
+    /// than one request the others are returned by `next_request`.
+    /// # Errors
+    /// The errors of `next_request`.
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Option<Request>, crate::MessageError> {
+        self.buffer.extend_from_slice(bytes);
+
+        self.next_request()
+    }
+
+    /// Returns the next request when it has been buffered completely, without buffering more bytes.
+    /// # Errors
+    /// `MessageError::BodyTooLarge` when `data_len` exceeds `max_data_len`, `MessageError::MessageTooLarge`
+    /// when the request would exceed `max_message_size`, the errors of `deserialize_request_header` and
+    /// `MessageError::ChecksumMismatch` with the `checksum` feature. All
+    /// buffered bytes are discarded then: as the requests in the stream can no longer be told apart, it
+    /// should be closed.
+    pub fn next_request(&mut self) -> Result<Option<Request>, crate::MessageError> {
+        let result = self.decode();
+        if result.is_err() {
+            self.buffer.clear();
+        }
+
+        result
+    }
+
+    fn decode(&mut self) -> Result<Option<Request>, crate::MessageError> {
+        let header_size = get_serialized_request_header_size() as usize;
+        if self.buffer.len() < header_size {
+            return Ok(None);
+        }
+        let header = deserialize_request_header_bounded(&self.buffer, self.max_data_len)?;
+        let request_size =
+            crate::check_message_size(header_size, header.data_len, self.max_message_size)?;
+        if self.buffer.len() < request_size {
+            return Ok(None);
+        }
+        let request = RequestStreamReader::new(&self.buffer[..request_size]).read_request()?;
+        self.buffer.drain(..request_size);
+
+        Ok(Some(request))
+    }
+}
+
+/// Keeps track of the requests that are still waiting for their response, so that every `ResponseHeader`
+/// can be matched back to the `RequestHeader` it answers by its identifier.
+#[derive(Default)]
+pub struct PendingRequests {
+    requests: alloc::collections::BTreeMap<crate::Identifier, RequestHeader>,
+}
+
+impl PendingRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a request that has been sent.
+    /// # Errors
+    /// `MessageError::InvalidField` when a request with the same identifier is still outstanding, as their
+    /// responses could not be told apart. The outstanding request is kept in that case.
+    pub fn register(&mut self, header: RequestHeader) -> Result<(), crate::MessageError> {
+        match self.requests.entry(header.identifier) {
+            alloc::collections::btree_map::Entry::Occupied(_) => {
+                Err(crate::MessageError::InvalidField {
+                    field: "identifier",
+                    reason: "a request with this identifier is still outstanding",
+                })
+            }
+            alloc::collections::btree_map::Entry::Vacant(entry) => {
+                entry.insert(header);
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the request that `response` answers and stops tracking it. `None` is returned when no request
+    /// with the identifier of `response` is outstanding, e.g. because it was already answered before.
+    pub fn match_response(
+        &mut self,
+        response: &crate::response::ResponseHeader,
+    ) -> Option<RequestHeader> {
+        self.requests.remove(&response.identifier)
+    }
+
+    /// Returns whether the request with `identifier` is still waiting for its response.
+    pub fn contains(&self, identifier: crate::Identifier) -> bool {
+        self.requests.contains_key(&identifier)
+    }
+
+    /// Number of requests that are still waiting for their response.
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+}
+
+/// Returns the size of the length prefix in front of every entry, i.e. `LENGTH_PREFIX_SIZE`. The prefix is
+/// always a little-endian 64-bit integer, whatever the width of `size_t`, see `write_entry_prefix`.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub extern "C" fn entry_prefix_size() -> libc::size_t {
+    crate::LENGTH_PREFIX_SIZE
+}
+
+/// Writes the length prefix of an entry of `len` bytes to the start of `buf`, exactly as
+/// `structure_two_entries` does.
+/// # Returns
+/// The number of bytes written, i.e. `entry_prefix_size()`, on success.
+/// -1 when buf was a null pointer.
+/// -2 when buf_len cannot hold the prefix. Nothing is written in that case.
+/// # Safety
+/// `buf` must point to at least `buf_len` writable bytes.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn write_entry_prefix(
+    buf: *mut libc::c_uchar,
+    buf_len: libc::size_t,
+    len: u64,
+) -> i64 {
+    if buf.is_null() {
+        return -1;
+    } else if buf_len < crate::LENGTH_PREFIX_SIZE {
+        return -2;
+    }
+
+    core::ptr::copy_nonoverlapping(len.to_le_bytes().as_ptr(), buf, crate::LENGTH_PREFIX_SIZE);
+
+    crate::LENGTH_PREFIX_SIZE as i64
+}
+
+/// Reads the length prefix at the start of `buf` into `len`, so that the entry follows after the returned
+/// number of bytes. The length is not checked against the rest of `buf`, nor against what fits in a
+/// `size_t`; do so before using it.
+/// # Returns
+/// The number of bytes consumed, i.e. `entry_prefix_size()`, on success.
+/// -1 when buf or len was a null pointer.
+/// -2 when buf_len is too short to hold a prefix. `len` is not changed in that case.
+/// # Safety
+/// `buf` must point to at least `buf_len` readable bytes and `len` to a writable `uint64_t`.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn read_entry_prefix(
+    buf: *const libc::c_uchar,
+    buf_len: libc::size_t,
+    len: *mut u64,
+) -> i64 {
+    if buf.is_null() || len.is_null() {
+        return -1;
+    } else if buf_len < crate::LENGTH_PREFIX_SIZE {
+        return -2;
+    }
+
+    let mut prefix = [0; crate::LENGTH_PREFIX_SIZE];
+    core::ptr::copy_nonoverlapping(buf, prefix.as_mut_ptr(), crate::LENGTH_PREFIX_SIZE);
+    *len = u64::from_le_bytes(prefix);
+
+    crate::LENGTH_PREFIX_SIZE as i64
+}
+
+/// Given the length of two entries returns the length of the buffer required to fit both entries including their lengths.
+/// # Returns
+/// The length on success.
+/// -1 when the length does not fit in a `size_t` (or an `int64_t`). Never allocate a buffer in that case.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub extern "C" fn structure_two_entries_length(
+    entry1_length: libc::size_t,
+    entry2_length: libc::size_t,
+) -> i64 {
+    entry1_length
+        .checked_add(entry2_length)
+        .and_then(|length| length.checked_add(2 * crate::LENGTH_PREFIX_SIZE))
+        .and_then(|length| i64::try_from(length).ok())
+        .unwrap_or(-1)
+}
+
+/// Given two entries and their length this function will put them back-to-back into data with length included.
+/// # Returns
+/// 0 on success.
+/// -1 when data was a null pointer.
+/// -2 when entry1 was a null pointer while entry1_length is not 0.
+/// -3 when entry2 was a null pointer while entry2_length is not 0.
+/// # Safety
+/// If entry1_length or entry2_length are not appropriate (too long for example) then an out of bounds
+/// access will occur; this is a bug introduced by the caller. When used in combination with the
+/// `structure_two_entries_length` function this will never occur.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn structure_two_entries(
+    data: *mut libc::c_uchar,
+    entry1_length: libc::size_t,
+    entry2_length: libc::size_t,
+    entry1: *const libc::c_uchar,
+    entry2: *const libc::c_uchar,
+) -> i16 {
+    if data.is_null() {
+        return -1;
+    } else if entry1.is_null() && entry1_length != 0 {
+        return -2;
+    } else if entry2.is_null() && entry2_length != 0 {
+        return -3;
+    }
+
+    core::ptr::copy_nonoverlapping(
+        (entry1_length as u64).to_le_bytes().as_ptr(),
+        data,
+        crate::LENGTH_PREFIX_SIZE,
+    );
+
+    let data = data.add(crate::LENGTH_PREFIX_SIZE);
+    copy_entry(entry1, data, entry1_length);
 
     let data = data.add(entry1_length);
-    std::ptr::copy_nonoverlapping(
-        entry2_length.to_le_bytes().as_ptr(),
+    core::ptr::copy_nonoverlapping(
+        (entry2_length as u64).to_le_bytes().as_ptr(),
         data,
-        usize_size_in_bytes,
+        crate::LENGTH_PREFIX_SIZE,
     );
 
-    let data = data.add(usize_size_in_bytes);
-    std::ptr::copy(entry2, data, entry2_length);
+    let data = data.add(crate::LENGTH_PREFIX_SIZE);
+    copy_entry(entry2, data, entry2_length);
+    log_trace!(
+        "structured 2 entries of {} and {} bytes",
+        entry1_length,
+        entry2_length
+    );
 
     0
 }
 
-/// Given a buffer which was constructed using `structure_two_entries` this function will structure
-/// it back into two separate slices. A `DestructureError` will be returned in case
-/// this is not possible or would cause safety issues.
-pub fn destructure_two_entries(data: &[u8]) -> Result<(&[u8], &[u8]), crate::DestructureError> {
-    let usize_size_in_bytes = std::mem::size_of::<usize>();
-    let entry1_length = data
-        .get(..usize_size_in_bytes)
-        .ok_or(crate::DestructureError)?;
-    let rest = &data[usize_size_in_bytes..];
-
-    let entry1_length = usize::from_le_bytes(
-        entry1_length
-            .try_into()
-            .map_err(|_| crate::DestructureError)?,
-    );
-    let entry1 = rest.get(..entry1_length).ok_or(crate::DestructureError)?;
-    let rest = &rest[entry1_length..];
+/// Given the length of three entries returns the length of the buffer required to fit all three entries including
+/// their lengths.
+/// # Returns
+/// The length on success.
+/// -1 when the length does not fit in a `size_t` (or an `int64_t`). Never allocate a buffer in that case.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub extern "C" fn structure_three_entries_length(
+    entry1_length: libc::size_t,
+    entry2_length: libc::size_t,
+    entry3_length: libc::size_t,
+) -> i64 {
+    entry1_length
+        .checked_add(entry2_length)
+        .and_then(|length| length.checked_add(entry3_length))
+        .and_then(|length| length.checked_add(3 * crate::LENGTH_PREFIX_SIZE))
+        .and_then(|length| i64::try_from(length).ok())
+        .unwrap_or(-1)
+}
+
+/// Given three entries and their length this function will put them back-to-back into data with length included,
+/// e.g. a shared secret, some metadata and a ciphertext.
+/// # Returns
+/// 0 on success.
+/// -1 when data was a null pointer.
+/// -2 when entry1 was a null pointer while entry1_length is not 0.
+/// -3 when entry2 was a null pointer while entry2_length is not 0.
+/// -4 when entry3 was a null pointer while entry3_length is not 0.
+/// -5 when the structured entries do not fit in a `size_t`, see `structure_three_entries_length`.
+/// # Safety
+/// If any of the lengths are not appropriate (too long for example) then an out of bounds access will
+/// occur; this is a bug introduced by the caller. When used in combination with the
+/// `structure_three_entries_length` function this will never occur.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn structure_three_entries(
+    data: *mut libc::c_uchar,
+    entry1_length: libc::size_t,
+    entry2_length: libc::size_t,
+    entry3_length: libc::size_t,
+    entry1: *const libc::c_uchar,
+    entry2: *const libc::c_uchar,
+    entry3: *const libc::c_uchar,
+) -> i16 {
+    if data.is_null() {
+        return -1;
+    } else if entry1.is_null() && entry1_length != 0 {
+        return -2;
+    } else if entry2.is_null() && entry2_length != 0 {
+        return -3;
+    } else if entry3.is_null() && entry3_length != 0 {
+        return -4;
+    }
 
-    let entry2_length = rest
-        .get(..usize_size_in_bytes)
-        .ok_or(crate::DestructureError)?;
-    let rest = &rest[usize_size_in_bytes..];
+    let entry_lengths = [entry1_length, entry2_length, entry3_length];
+    let entries = [entry1, entry2, entry3];
+    match structure_n_entries(data, entry_lengths.as_ptr(), entries.as_ptr(), 3) {
+        -6 => -5,
+        status => status,
+    }
+}
 
-    let entry2_length = usize::from_le_bytes(
-        entry2_length
-            .try_into()
-            .map_err(|_| crate::DestructureError)?,
+/// Given the lengths of `entry_count` entries returns the length of the buffer required to fit all entries
+/// including their lengths.
+/// # Returns
+/// The required length on success.
+/// 0 when `entry_lengths` was a null pointer or `entry_count` was 0.
+/// -1 when the length does not fit in a `size_t` (or an `int64_t`). Never allocate a buffer in that case.
+/// # Safety
+/// `entry_lengths` must point to at least `entry_count` lengths.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn structure_n_entries_length(
+    entry_lengths: *const libc::size_t,
+    entry_count: libc::size_t,
+) -> i64 {
+    if entry_lengths.is_null() || entry_count == 0 {
+        return 0;
+    }
+
+    let entry_lengths = core::slice::from_raw_parts(entry_lengths, entry_count);
+    n_entries_length(entry_lengths)
+        .and_then(|length| i64::try_from(length).ok())
+        .unwrap_or(-1)
+}
+
+// Length of the structured entries with the given lengths, or `None` when it overflows.
+#[cfg(feature = "std")]
+fn n_entries_length(entry_lengths: &[usize]) -> Option<usize> {
+    entry_lengths
+        .iter()
+        .try_fold(0usize, |total, &length| total.checked_add(length))?
+        .checked_add(entry_lengths.len().checked_mul(crate::LENGTH_PREFIX_SIZE)?)
+}
+
+/// Given `entry_count` entries and their lengths this function will put them back-to-back into data with
+/// length included. This is the generalization of `structure_two_entries`; for two entries both produce
+/// exactly the same bytes.
+/// # Returns
+/// 0 on success.
+/// -1 when data was a null pointer.
+/// -2 when entry_lengths was a null pointer.
+/// -3 when entries was a null pointer.
+/// -4 when one of the entries was a null pointer while its length is not 0.
+/// -5 when entry_count was 0.
+/// -6 when the structured entries do not fit in a `size_t`, see `structure_n_entries_length`.
+/// # Safety
+/// `entry_lengths` and `entries` must both point to at least `entry_count` elements. If any of the
+/// lengths are not appropriate (too long for example) then an out of bounds access will occur; this
+/// is a bug introduced by the caller. When used in combination with the `structure_n_entries_length`
+/// function this will never occur.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn structure_n_entries(
+    data: *mut libc::c_uchar,
+    entry_lengths: *const libc::size_t,
+    entries: *const *const libc::c_uchar,
+    entry_count: libc::size_t,
+) -> i16 {
+    if data.is_null() {
+        return -1;
+    } else if entry_lengths.is_null() {
+        return -2;
+    } else if entries.is_null() {
+        return -3;
+    } else if entry_count == 0 {
+        return -5;
+    }
+
+    let entry_lengths = core::slice::from_raw_parts(entry_lengths, entry_count);
+    let entries = core::slice::from_raw_parts(entries, entry_count);
+    if entries
+        .iter()
+        .zip(entry_lengths)
+        .any(|(entry, &entry_length)| entry.is_null() && entry_length != 0)
+    {
+        return -4;
+    } else if n_entries_length(entry_lengths).is_none() {
+        return -6;
+    }
+
+    let mut data = data;
+    for (entry, &entry_length) in entries.iter().zip(entry_lengths) {
+        core::ptr::copy_nonoverlapping(
+            (entry_length as u64).to_le_bytes().as_ptr(),
+            data,
+            crate::LENGTH_PREFIX_SIZE,
+        );
+
+        data = data.add(crate::LENGTH_PREFIX_SIZE);
+        copy_entry(*entry, data, entry_length);
+        data = data.add(entry_length);
+    }
+    log_trace!(
+        "structured {} entries of {:?} bytes",
+        entry_count,
+        entry_lengths
     );
-    let entry2 = rest.get(..entry2_length).ok_or(crate::DestructureError)?;
+
+    0
+}
+
+// Copies an entry into structured data. An empty entry may be a null pointer, which `core::ptr::copy` does not
+// accept even for 0 bytes, so nothing is copied for it.
+#[cfg(feature = "std")]
+unsafe fn copy_entry(entry: *const libc::c_uchar, data: *mut libc::c_uchar, entry_length: usize) {
+    if entry_length != 0 {
+        core::ptr::copy(entry, data, entry_length);
+    }
+}
+
+/// Reads the length-prefixed entry starting at `offset` in `data`.
+/// Returns the entry together with the offset right after it.
+fn read_entry(data: &[u8], offset: usize) -> Result<(&[u8], usize), crate::MessageError> {
+    let entry = parse_entry(data, offset);
+    match &entry {
+        Ok((entry, _)) => log_trace!("read entry of {} bytes at offset {}", entry.len(), offset),
+        Err(error) => log_debug!("could not read entry at offset {}: {}", offset, error),
+    }
+
+    entry
+}
+
+fn parse_entry(data: &[u8], offset: usize) -> Result<(&[u8], usize), crate::MessageError> {
+    let length_end = offset + crate::LENGTH_PREFIX_SIZE;
+    let too_short = |needed| crate::MessageError::BufferTooShort {
+        needed,
+        got: data.len(),
+    };
+
+    let entry_length = data.get(offset..length_end).ok_or(too_short(length_end))?;
+    let entry_length =
+        u64::from_le_bytes(entry_length.try_into().map_err(|_| too_short(length_end))?);
+    let entry_length =
+        usize::try_from(entry_length).map_err(|_| crate::MessageError::LengthOverflow)?;
+
+    let entry_end = length_end
+        .checked_add(entry_length)
+        .ok_or(crate::MessageError::LengthOverflow)?;
+    let entry = data
+        .get(length_end..entry_end)
+        .ok_or(too_short(entry_end))?;
+
+    Ok((entry, entry_end))
+}
+
+/// Given a buffer which was constructed using `structure_two_entries` this function will structure
+/// it back into two separate slices. A `MessageError` will be returned in case
+/// this is not possible or would cause safety issues.
+pub fn destructure_two_entries(data: &[u8]) -> Result<(&[u8], &[u8]), crate::MessageError> {
+    let (entry1, entry2, _) = destructure_two_entries_with_offset(data)?;
 
     Ok((entry1, entry2))
 }
+
+/// Same as `destructure_two_entries`, additionally returning the number of bytes the two entries took up,
+/// like the `consumed` of the C `destructure_two_entries_checked`. Anything after that is ignored, so a
+/// buffer holding several structures back to back can be walked by destructuring `&data[consumed..]` next.
+pub fn destructure_two_entries_with_offset(
+    data: &[u8],
+) -> Result<(&[u8], &[u8], usize), crate::MessageError> {
+    let (entry1, offset) = read_entry(data, 0)?;
+    let (entry2, consumed) = read_entry(data, offset)?;
+
+    Ok((entry1, entry2, consumed))
+}
+
+/// Given a buffer which was constructed using `structure_three_entries` this function will structure
+/// it back into three separate slices. A `MessageError` will be returned in case
+/// this is not possible or would cause safety issues.
+#[allow(clippy::type_complexity)]
+pub fn destructure_three_entries(
+    data: &[u8],
+) -> Result<(&[u8], &[u8], &[u8]), crate::MessageError> {
+    let (entry1, offset) = read_entry(data, 0)?;
+    let (entry2, offset) = read_entry(data, offset)?;
+    let (entry3, _) = read_entry(data, offset)?;
+
+    Ok((entry1, entry2, entry3))
+}
+
+/// Same as `destructure_two_entries` except that the entries are copied out of `data`, so that they
+/// can outlive the buffer.
+pub fn destructure_two_entries_owned(
+    data: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), crate::MessageError> {
+    let (entry1, entry2) = destructure_two_entries(data)?;
+
+    Ok((entry1.to_vec(), entry2.to_vec()))
+}
+
+/// Lazily goes through the entries of a buffer which was constructed using `structure_entries` (or
+/// `structure_two_entries`), reading one entry per `next()` in the order they were structured. The
+/// iteration ends at the exact end of the buffer, so an empty buffer has no entries. When a length
+/// points past the end of the buffer the error is returned once after which the iteration ends.
+pub struct EntryIter<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> EntryIter<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        EntryIter { data, offset: 0 }
+    }
+}
+
+impl<'a> Iterator for EntryIter<'a> {
+    type Item = Result<&'a [u8], crate::MessageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.offset >= self.data.len() {
+            return None;
+        }
+
+        match read_entry(self.data, self.offset) {
+            Ok((entry, next_offset)) => {
+                self.offset = next_offset;
+                Some(Ok(entry))
+            }
+            Err(error) => {
+                self.offset = self.data.len();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+impl core::iter::FusedIterator for EntryIter<'_> {}
+
+/// The most entries `destructure_entries` returns, so that a buffer of many empty entries cannot make it
+/// allocate an arbitrary amount of memory. Far more than any operation uses; pass another cap to
+/// `destructure_entries_bounded` when needed.
+pub const DEFAULT_MAX_ENTRIES: usize = 64;
+
+/// Given a buffer which was constructed using `structure_entries` (or `structure_two_entries`) this function
+/// will structure it back into separate slices, in the order they were structured. The whole buffer has to
+/// consist of entries. A `MessageError` will be returned in case the buffer contains no entries at all,
+/// more than `DEFAULT_MAX_ENTRIES` entries or if any of the lengths would point past the end of the buffer.
+/// See `EntryIter` to go through the entries without collecting them.
+pub fn destructure_entries(data: &[u8]) -> Result<Vec<&[u8]>, crate::MessageError> {
+    destructure_entries_bounded(data, DEFAULT_MAX_ENTRIES)
+}
+
+/// Like `destructure_entries`, but with `max_entries` as the most entries that are accepted.
+/// # Errors
+/// `MessageError::TooManyEntries` when `data` holds more than `max_entries` entries and the errors of
+/// `destructure_entries`.
+pub fn destructure_entries_bounded(
+    data: &[u8],
+    max_entries: usize,
+) -> Result<Vec<&[u8]>, crate::MessageError> {
+    if data.is_empty() {
+        return Err(crate::MessageError::BufferTooShort {
+            needed: crate::LENGTH_PREFIX_SIZE,
+            got: 0,
+        });
+    }
+
+    let mut entries = Vec::new();
+    for entry in EntryIter::new(data) {
+        if entries.len() == max_entries {
+            log_debug!("refusing to destructure more than {} entries", max_entries);
+            return Err(crate::MessageError::TooManyEntries { max: max_entries });
+        }
+        entries.push(entry?);
+    }
+
+    Ok(entries)
+}
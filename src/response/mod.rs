@@ -1,96 +1,1137 @@
-use serde::{Deserialize, Serialize};
-use std::convert::{TryFrom, TryInto};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use zeroize::Zeroize;
 
-lazy_static! {
-    static ref RESPONSE_HEADER_SIZE: u64 = bincode::serialized_size(&ResponseHeader::default())
-        .expect("Unable to get size of default RequestHeader.");
-}
+// The serialized size of `ResponseHeader`: version, identifier, success, data_len and flags.
+// `test_header_sizes` makes sure this matches what bincode produces.
+const RESPONSE_HEADER_SIZE: u64 = (core::mem::size_of::<u8>()
+    + core::mem::size_of::<crate::Identifier>()
+    + core::mem::size_of::<i8>()
+    + core::mem::size_of::<u32>()
+    + core::mem::size_of::<u8>()) as u64;
+
+/// Bit of `ResponseHeader::flags` that marks the data as compressed, see `decompress_response_body`.
+pub const RESPONSE_FLAG_COMPRESSED: u8 = 0b0000_0001;
+
+/// Bit of `ResponseHeader::flags` that marks the data of a failure as a UTF-8 diagnostic message instead of
+/// result data, see `serialize_response_with_error`.
+pub const RESPONSE_FLAG_DIAGNOSTIC: u8 = 0b0000_0010;
+
+// Size of the little-endian `u32` in front of compressed data that holds the length of the original data.
+#[cfg(feature = "compress")]
+const ORIGINAL_LENGTH_SIZE: usize = core::mem::size_of::<u32>();
 
 // The actual data is appended after this header has been serialized since serde
 // does not support deserializing dynamically sized structs.
 /// Header that describes the response sent.
 /// # Explanation of the header
 /// - version is used for compatibility reasons. Typically there is no need to do anything with this
-///   as pq_message_lib deals with version internally.
+///   as pq_message_lib deals with version internally. From Rust it can only be read through
+///   `version()`, as both `ResponseHeader::new` and `Default` stamp `FORMAT_VERSION`.
 /// - identifier is used so that the receiver of the `ResponseHeader` can link it back to the original request.
-/// - success indicates there was a failure or not. 0 means success while anything else is a failure.
-///   Note that the data_len field will always be 0 when there was a failure.
+/// - success indicates there was a failure or not. 0 means success while anything else is a failure, the
+///   known codes are those of `ResponseStatus` (see `status`). Note that the data_len field will always be 0
+///   when there was a failure, unless `RESPONSE_FLAG_DIAGNOSTIC` is set.
 /// - data_len describes the length of the upcoming data that belongs to this `ResponseHeader`. The data after that
-///   will belong to a new `ResponseHeader`.
-#[derive(Serialize, Deserialize, Default, PartialEq)]
+///   will belong to a new `ResponseHeader`. As this is a `u32` a single response can carry at most 2^32 - 1 bytes.
+///   For compressed data this is the length of the data as it is sent.
+/// - flags describes how the data is sent, see `ResponseFlags`. Only `RESPONSE_FLAG_COMPRESSED` and
+///   `RESPONSE_FLAG_DIAGNOSTIC` are defined, the other bits are reserved for features that need a bit in
+///   the header: this version sends 0 and keeps whatever bits it reads.
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[repr(C)]
 pub struct ResponseHeader {
-    pub version: u8,
-    pub identifier: u64,
+    pub(crate) version: u8,
+    pub identifier: crate::Identifier,
     pub success: i8,
     pub data_len: u32,
+    pub flags: u8,
+}
+
+/// The bits of `ResponseHeader::flags`, see `ResponseHeader::response_flags`. Bits that are not defined by this
+/// version are kept as they are, so that a header can be passed on without losing them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseFlags(u8);
+
+impl ResponseFlags {
+    /// The data is compressed, see `RESPONSE_FLAG_COMPRESSED`.
+    pub const COMPRESSED: ResponseFlags = ResponseFlags(RESPONSE_FLAG_COMPRESSED);
+    /// The data of a failure is a diagnostic message, see `RESPONSE_FLAG_DIAGNOSTIC`.
+    pub const DIAGNOSTIC: ResponseFlags = ResponseFlags(RESPONSE_FLAG_DIAGNOSTIC);
+    /// The bits that are reserved for future features.
+    pub const RESERVED: ResponseFlags =
+        ResponseFlags(!(RESPONSE_FLAG_COMPRESSED | RESPONSE_FLAG_DIAGNOSTIC));
+
+    /// Returns the flags with all bits of `bits`, including reserved ones.
+    pub const fn from_bits(bits: u8) -> Self {
+        ResponseFlags(bits)
+    }
+
+    /// Returns the flags as they are stored in `ResponseHeader::flags`.
+    pub const fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Whether all bits of `other` are set.
+    pub const fn contains(&self, other: ResponseFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Sets all bits of `other`.
+    pub fn insert(&mut self, other: ResponseFlags) {
+        self.0 |= other.0;
+    }
+
+    /// Clears all bits of `other`.
+    pub fn remove(&mut self, other: ResponseFlags) {
+        self.0 &= !other.0;
+    }
+}
+
+/// Outcome of a request, stored in the `success` byte of `ResponseHeader`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i8)]
+pub enum ResponseStatus {
+    Ok = 0,
+    /// The operation itself failed, e.g. a decapsulation was rejected.
+    OperationFailed = -1,
+    /// The algorithm is not built into the responder.
+    UnsupportedAlgorithm = -2,
+    /// The data of the request does not fit the algorithm and operation, e.g. a key of the wrong size.
+    InvalidInput = -3,
+    /// Something went wrong in the responder that has nothing to do with the request.
+    InternalError = -4,
+    /// The operation is not built into the responder, e.g. because this version does not know it yet.
+    UnsupportedOperation = -5,
+    /// The request was aborted by a `Cancel` before it was done, see `request::Operation::Cancel`.
+    Cancelled = -6,
+}
+
+impl TryFrom<i8> for ResponseStatus {
+    type Error = i8;
+
+    /// Returns the unknown code itself when it does not belong to any `ResponseStatus`, e.g. because
+    /// the responder is newer than this crate.
+    fn try_from(raw: i8) -> Result<Self, Self::Error> {
+        match raw {
+            0 => Ok(ResponseStatus::Ok),
+            -1 => Ok(ResponseStatus::OperationFailed),
+            -2 => Ok(ResponseStatus::UnsupportedAlgorithm),
+            -3 => Ok(ResponseStatus::InvalidInput),
+            -4 => Ok(ResponseStatus::InternalError),
+            -5 => Ok(ResponseStatus::UnsupportedOperation),
+            -6 => Ok(ResponseStatus::Cancelled),
+            raw => Err(raw),
+        }
+    }
+}
+
+impl Default for ResponseHeader {
+    fn default() -> Self {
+        ResponseHeader::new(0, ResponseStatus::Ok, 0)
+    }
+}
+
+impl ResponseHeader {
+    /// Creates a header of the current `FORMAT_VERSION` without any flags. For a failure `data_len`
+    /// should be 0.
+    pub fn new(identifier: crate::Identifier, status: ResponseStatus, data_len: u32) -> Self {
+        ResponseHeader {
+            version: crate::FORMAT_VERSION,
+            identifier,
+            success: status as i8,
+            data_len,
+            flags: 0,
+        }
+    }
+
+    /// Returns the format version the header was produced with.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the identifier as a UUID, i.e. that of the request this response answers.
+    #[cfg(feature = "uuid")]
+    pub fn uuid(&self) -> uuid::Uuid {
+        uuid::Uuid::from_u128(self.identifier)
+    }
+
+    /// Serializes the header into `get_serialized_response_header_size()` bytes: every field in order,
+    /// little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(RESPONSE_HEADER_SIZE as usize);
+        bytes.push(self.version);
+        bytes.extend(&self.identifier.to_le_bytes());
+        bytes.extend(&self.success.to_le_bytes());
+        bytes.extend(&self.data_len.to_le_bytes());
+        bytes.push(self.flags);
+        trace_header("serialized", self);
+
+        bytes
+    }
+
+    /// Reads a header serialized by `to_bytes`. Any bytes after the header are ignored and the version
+    /// is not checked.
+    /// # Errors
+    /// `MessageError::BufferTooShort` when `bytes` is too short.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, crate::MessageError> {
+        let response_header = Self::read_fields(bytes);
+        match &response_header {
+            Ok(response_header) => trace_header("deserialized", response_header),
+            Err(error) => log_debug!("could not deserialize response header: {}", error),
+        }
+
+        response_header
+    }
+
+    fn read_fields(bytes: &[u8]) -> Result<Self, crate::MessageError> {
+        if bytes.len() < RESPONSE_HEADER_SIZE as usize {
+            return Err(crate::MessageError::BufferTooShort {
+                needed: RESPONSE_HEADER_SIZE as usize,
+                got: bytes.len(),
+            });
+        }
+
+        let mut fields = crate::FieldReader::new(bytes);
+        Ok(ResponseHeader {
+            version: fields.u8(),
+            identifier: fields.identifier(),
+            success: fields.u8() as i8,
+            data_len: fields.u32(),
+            flags: fields.u8(),
+        })
+    }
+
+    /// The status of the response, or `None` when the code is not known to this crate. Any code other
+    /// than `ResponseStatus::Ok`, known or not, is a failure.
+    pub fn status(&self) -> Option<ResponseStatus> {
+        ResponseStatus::try_from(self.success).ok()
+    }
+
+    /// The status byte as it was sent, including codes that are not known to this crate.
+    pub fn raw_status(&self) -> i8 {
+        self.success
+    }
+
+    /// Whether the data following this header is compressed, see `decompress_response_body`.
+    pub fn is_compressed(&self) -> bool {
+        self.response_flags().contains(ResponseFlags::COMPRESSED)
+    }
+
+    /// Whether the data following this header is a diagnostic message, see `serialize_response_with_error`.
+    pub fn is_diagnostic(&self) -> bool {
+        self.response_flags().contains(ResponseFlags::DIAGNOSTIC)
+    }
+
+    /// The `flags` byte as `ResponseFlags`.
+    pub fn response_flags(&self) -> ResponseFlags {
+        ResponseFlags::from_bits(self.flags)
+    }
+
+    /// Replaces the `flags` byte with `flags`.
+    pub fn set_response_flags(&mut self, flags: ResponseFlags) {
+        self.flags = flags.bits();
+    }
+
+    /// Checks that a failure carries no data other than a diagnostic message and that only a failure carries
+    /// one, as every header read from a peer has to. A responder that sends result data with a failure is
+    /// broken or malicious, so it is rejected before anyone reads that data.
+    /// # Errors
+    /// `MessageError::ProtocolViolation` when `success` is not 0 and `data_len` is not 0 without
+    /// `RESPONSE_FLAG_DIAGNOSTIC`, and `MessageError::InvalidField` naming `flags` when that flag is set while
+    /// `success` is 0.
+    pub fn validate(&self) -> Result<(), crate::MessageError> {
+        if self.success == 0 && self.is_diagnostic() {
+            log_debug!("successful response header is marked as diagnostic");
+            return Err(crate::MessageError::InvalidField {
+                field: "flags",
+                reason: "only a failure response carries a diagnostic message",
+            });
+        }
+        if self.success != 0 && self.data_len != 0 && !self.is_diagnostic() {
+            log_debug!(
+                "failure response header with status {} describes {} bytes of data",
+                self.success,
+                self.data_len
+            );
+            return Err(crate::MessageError::ProtocolViolation {
+                reason: "a failure response carries no data",
+            });
+        }
+
+        Ok(())
+    }
+}
+
+// Logs the fields of `header`, none of which are sensitive.
+fn trace_header(action: &str, header: &ResponseHeader) {
+    log_trace!(
+        "{} response header: version {}, identifier {}, success {}, data_len {}, flags {:#04x}",
+        action,
+        header.version,
+        header.identifier,
+        header.success,
+        header.data_len,
+        header.flags
+    );
 }
 
 /// Convenience struct to allow response body to be stored together together with the header.
+/// # Ownership
+/// A `Response` created with `Response::new` owns its body, which was allocated by Rust. Whoever ends up
+/// with the `Response`, on either side of the FFI boundary, has to release the body exactly once with
+/// `free_response_body(body, header.data_len)` and must never pass it to the C `free`. A `Response` created
+/// with `response_from_parts` only borrows its body, which stays with whoever allocated it.
+#[cfg(feature = "std")]
 #[repr(C)]
 pub struct Response {
     pub header: ResponseHeader,
     pub body: *const libc::c_uchar,
 }
 
+#[cfg(feature = "std")]
+impl Response {
+    /// Creates a successful `Response` which takes ownership of `body`, see the ownership notes of `Response`.
+    /// # Errors
+    /// `MessageError::LengthOverflow` when `body` is too long to be described by `data_len`.
+    pub fn new(identifier: crate::Identifier, body: Vec<u8>) -> Result<Self, crate::MessageError> {
+        let data_len = crate::encode_data_len(body.len())?;
+        // A boxed slice has no spare capacity, so its length is all that is needed to free it again.
+        let body = alloc::boxed::Box::into_raw(body.into_boxed_slice());
+
+        Ok(Response {
+            header: ResponseHeader::new(identifier, ResponseStatus::Ok, data_len),
+            body: body as *const libc::c_uchar,
+        })
+    }
+}
+
+// Only the length of the body is printed: the body may contain key material and nothing guarantees
+// that `body` points to `data_len` readable bytes.
+#[cfg(feature = "std")]
+impl core::fmt::Debug for Response {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Response")
+            .field("header", &self.header)
+            .field("body_len", &self.header.data_len)
+            .finish()
+    }
+}
+
+/// A parsed response that borrows its body from the buffer it was parsed from instead of copying it, the
+/// bounds-checked counterpart of `Response` for use from Rust. The lifetime ties the body to that buffer, so
+/// the view cannot outlive it. The body is always exactly `data_len` bytes and returned as it was sent, so
+/// compressed data (see `ResponseHeader::is_compressed`) has to be decompressed afterwards.
+pub struct ResponseView<'a> {
+    header: ResponseHeader,
+    body: &'a [u8],
+}
+
+impl<'a> ResponseView<'a> {
+    /// Parses the response at the start of `buf` and borrows its body from `buf`.
+    /// # Errors
+    /// The errors of `parse_response`.
+    pub fn from_bytes(buf: &'a [u8]) -> Result<Self, crate::MessageError> {
+        let (header, body) = parse_response(buf)?;
+
+        Ok(ResponseView { header, body })
+    }
+
+    pub fn header(&self) -> &ResponseHeader {
+        &self.header
+    }
+
+    /// The `data_len` bytes of data of the response.
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+
+    /// The diagnostic message of a failure sent with `serialize_response_with_error`, or `None` when the
+    /// response carries none or it is not valid UTF-8.
+    pub fn error_message(&self) -> Option<&'a str> {
+        if !self.header.is_diagnostic() {
+            return None;
+        }
+
+        core::str::from_utf8(self.body).ok()
+    }
+}
+
+// Only the length of the body is printed, just like for `Response`.
+impl core::fmt::Debug for ResponseView<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ResponseView")
+            .field("header", &self.header)
+            .field("body_len", &self.body.len())
+            .finish()
+    }
+}
+
+/// Wrapper around a serialized response (as returned by `serialize_response`) which securely
+/// clears the response when it is dropped. The clearing is guaranteed to not be optimized away.
+pub struct ZeroizingResponse(Vec<u8>);
+
+impl From<Vec<u8>> for ZeroizingResponse {
+    fn from(response: Vec<u8>) -> Self {
+        ZeroizingResponse(response)
+    }
+}
+
+impl core::ops::Deref for ZeroizingResponse {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for ZeroizingResponse {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for ZeroizingResponse {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
 /// Returns the size needed for the buffer where the serialized response header will be stored.
-/// Will evaluate only when used for the first time.
+/// The size is a constant computed at compile time, so this cannot fail or panic.
 #[no_mangle]
 pub extern "C" fn get_serialized_response_header_size() -> u64 {
-    *RESPONSE_HEADER_SIZE
+    RESPONSE_HEADER_SIZE
 }
 
-/// The length of data can at most be 2^32 bytes!
-/// In case of error (that is not a SerializationError) this will only return the header
-/// with success status not set to 0.
+/// Returns the serialized response of a successful request, the header followed by `data`.
+/// The length of data can at most be 2^32 - 1 bytes!
+/// With the `compress` feature the data is compressed whenever that makes it smaller, in which case
+/// `RESPONSE_FLAG_COMPRESSED` is set in the header.
+/// # Errors
+/// `MessageError::LengthOverflow` is returned when `data` is too long to be described by `data_len`.
+/// Such a payload is never turned into a failure response as that would be indistinguishable
+/// from a failed operation for the receiver.
 pub fn serialize_response(
-    identifier: u64,
-    data: Option<&[u8]>,
-) -> Result<Vec<u8>, crate::SerializationError> {
-    let mut response_header = ResponseHeader {
-        version: crate::FORMAT_VERSION,
-        identifier,
-        ..Default::default()
+    identifier: crate::Identifier,
+    data: &[u8],
+) -> Result<Vec<u8>, crate::MessageError> {
+    serialize_response_result(identifier, Ok(data))
+}
+
+/// Returns the serialized response of a failed request: only the header, with `status` as success status
+/// and no data.
+/// # Errors
+/// `MessageError::InvalidField` for `ResponseStatus::Ok`, which is not a failure.
+pub fn serialize_failure_response(
+    identifier: crate::Identifier,
+    status: ResponseStatus,
+) -> Result<Vec<u8>, crate::MessageError> {
+    serialize_response_result(identifier, Err(status))
+}
+
+/// Returns the serialized response telling the requester that the requested algorithm is not built into the
+/// responder, so that it can try another one: only the header with `ResponseStatus::UnsupportedAlgorithm`.
+pub fn serialize_unsupported_algorithm_response(
+    identifier: crate::Identifier,
+) -> Result<Vec<u8>, crate::MessageError> {
+    serialize_failure_response(identifier, ResponseStatus::UnsupportedAlgorithm)
+}
+
+/// Returns the serialized response telling the requester that the requested operation is not built into the
+/// responder: only the header with `ResponseStatus::UnsupportedOperation`.
+pub fn serialize_unsupported_operation_response(
+    identifier: crate::Identifier,
+) -> Result<Vec<u8>, crate::MessageError> {
+    serialize_failure_response(identifier, ResponseStatus::UnsupportedOperation)
+}
+
+/// Returns the serialized response of a failed request with `status` as success status, followed by `message`
+/// to tell the requester why it failed. `RESPONSE_FLAG_DIAGNOSTIC` is set so that the message is not taken for
+/// result data, see `ResponseView::error_message`. The message is sent as it is, so it must not contain
+/// anything sensitive.
+/// # Errors
+/// `MessageError::InvalidField` for `ResponseStatus::Ok`, which is not a failure, and
+/// `MessageError::LengthOverflow` when `message` is too long to be described by `data_len`.
+pub fn serialize_response_with_error(
+    identifier: crate::Identifier,
+    status: ResponseStatus,
+    message: &str,
+) -> Result<Vec<u8>, crate::MessageError> {
+    if status == ResponseStatus::Ok {
+        log_debug!("refusing to serialize diagnostic response with status Ok");
+        return Err(crate::MessageError::InvalidField {
+            field: "success",
+            reason: "a failed response needs a failure status",
+        });
+    }
+    let data_len = crate::encode_data_len(message.len())?;
+
+    let mut response_header = ResponseHeader::new(identifier, status, data_len);
+    response_header.flags |= RESPONSE_FLAG_DIAGNOSTIC;
+
+    assemble_response(&response_header, message.as_bytes())
+}
+
+/// Writes the serialized response to `buf` instead of returning it, for a responder that reuses its buffers.
+/// `Ok` is serialized like `serialize_response` and `Err` like `serialize_failure_response`, producing the
+/// same bytes. Returns the number of bytes written, the rest of `buf` is left untouched.
+/// # Errors
+/// `MessageError::BufferTooShort` when the response does not fit into `buf`, in which case nothing is
+/// written, and the errors of `serialize_response` and `serialize_failure_response`.
+pub fn serialize_response_into(
+    buf: &mut [u8],
+    identifier: crate::Identifier,
+    data: Result<&[u8], ResponseStatus>,
+) -> Result<usize, crate::MessageError> {
+    let (response_header, compressed) = prepare_response(identifier, data)?;
+    let data = compressed
+        .as_deref()
+        .map_or(data.unwrap_or(&[]), |compressed| &compressed[..]);
+
+    write_response(buf, &response_header, data)
+}
+
+/// `serialize_response` for `Ok` and `serialize_failure_response` for `Err`.
+pub(crate) fn serialize_response_result(
+    identifier: crate::Identifier,
+    data: Result<&[u8], ResponseStatus>,
+) -> Result<Vec<u8>, crate::MessageError> {
+    let (response_header, compressed) = prepare_response(identifier, data)?;
+    let data = compressed
+        .as_deref()
+        .map_or(data.unwrap_or(&[]), |compressed| &compressed[..]);
+
+    assemble_response(&response_header, data)
+}
+
+// Returns the header of the response for `data` and, with the `compress` feature, the compressed data when
+// that is what has to be sent instead of `data`.
+#[allow(clippy::type_complexity)]
+fn prepare_response(
+    identifier: crate::Identifier,
+    data: Result<&[u8], ResponseStatus>,
+) -> Result<(ResponseHeader, Option<zeroize::Zeroizing<Vec<u8>>>), crate::MessageError> {
+    let mut response_header = ResponseHeader::new(identifier, ResponseStatus::Ok, 0);
+
+    #[cfg(not(feature = "compress"))]
+    let compressed = None;
+    #[cfg(feature = "compress")]
+    let compressed = data.ok().and_then(compress_body);
+    #[cfg(feature = "compress")]
+    let data = match &compressed {
+        Some(compressed) => {
+            response_header.flags |= RESPONSE_FLAG_COMPRESSED;
+            Ok(compressed.as_slice())
+        }
+        None => data,
     };
 
     match data {
-        Some(data) => {
-            if let Ok(convert_data_len) = u32::try_from(data.len()) {
-                response_header.success = 0;
-                response_header.data_len = convert_data_len;
-            } else {
-                response_header.success = -1;
-                response_header.data_len = 0;
-            }
+        Ok(data) => {
+            response_header.success = ResponseStatus::Ok as i8;
+            response_header.data_len = crate::encode_data_len(data.len())?;
+        }
+        Err(ResponseStatus::Ok) => {
+            log_debug!("refusing to serialize failure response with status Ok");
+            return Err(crate::MessageError::InvalidField {
+                field: "success",
+                reason: "a failed response needs a failure status",
+            });
         }
-        None => {
-            response_header.success = -1;
+        Err(status) => {
+            response_header.success = status as i8;
             response_header.data_len = 0;
         }
     };
 
-    let mut serialized =
-        bincode::serialize(&response_header).map_err(|_| crate::SerializationError)?;
-    if response_header.success == 0 {
-        serialized.extend(data.unwrap());
-    }
+    Ok((response_header, compressed))
+}
+
+// Serializes `response_header` followed by `data`, which has to be `data_len` bytes, and the checksum trailer.
+fn assemble_response(
+    response_header: &ResponseHeader,
+    data: &[u8],
+) -> Result<Vec<u8>, crate::MessageError> {
+    // Allocate everything up front so that no copy of the data is left behind in freed memory, which a
+    // `ZeroizingResponse` could not clear.
+    let mut serialized = alloc::vec![0; response_len(data)];
+    write_response(&mut serialized, response_header, data)?;
 
     Ok(serialized)
 }
 
+// Same as `assemble_response`, writing into the start of `buf`. Returns the number of bytes written.
+fn write_response(
+    buf: &mut [u8],
+    response_header: &ResponseHeader,
+    data: &[u8],
+) -> Result<usize, crate::MessageError> {
+    let len = response_len(data);
+    if buf.len() < len {
+        log_debug!(
+            "response of {} bytes does not fit into {} bytes",
+            len,
+            buf.len()
+        );
+        return Err(crate::MessageError::BufferTooShort {
+            needed: len,
+            got: buf.len(),
+        });
+    }
+
+    let header_size = RESPONSE_HEADER_SIZE as usize;
+    buf[..header_size].copy_from_slice(&response_header.to_bytes());
+    buf[header_size..header_size + data.len()].copy_from_slice(data);
+    #[cfg(feature = "checksum")]
+    crate::checksum::fill_checksum(&mut buf[..len]);
+
+    Ok(len)
+}
+
+// Size of the serialized response carrying `data`, trailer included.
+fn response_len(data: &[u8]) -> usize {
+    #[cfg(not(feature = "checksum"))]
+    let trailer_size = 0;
+    #[cfg(feature = "checksum")]
+    let trailer_size = crate::checksum::CHECKSUM_SIZE;
+
+    RESPONSE_HEADER_SIZE as usize + data.len() + trailer_size
+}
+
+/// Compresses `data` with deflate, prefixed by its original length. Returns `None` when that would not
+/// make the data any smaller. The compressed data is cleared when it is dropped just like the original.
+#[cfg(feature = "compress")]
+fn compress_body(data: &[u8]) -> Option<zeroize::Zeroizing<Vec<u8>>> {
+    let original_len = u32::try_from(data.len()).ok()?;
+    let deflated = zeroize::Zeroizing::new(miniz_oxide::deflate::compress_to_vec(data, 6));
+    if ORIGINAL_LENGTH_SIZE + deflated.len() >= data.len() {
+        return None;
+    }
+
+    let mut compressed = Vec::with_capacity(ORIGINAL_LENGTH_SIZE + deflated.len());
+    compressed.extend(&original_len.to_le_bytes());
+    compressed.extend(deflated.iter());
+
+    Some(zeroize::Zeroizing::new(compressed))
+}
+
+/// Returns the data belonging to `header` as it was passed to `serialize_response`, decompressing it when
+/// `RESPONSE_FLAG_COMPRESSED` is set. `body` has to start with the data, anything after `data_len` bytes is ignored.
+/// # Errors
+/// `MessageError::BufferTooShort` when `body` is shorter than `data_len`, `MessageError::InvalidField` when the
+/// compressed data is corrupt and `MessageError::LengthMismatch` when it does not decompress to its original length.
+#[cfg(feature = "compress")]
+pub fn decompress_response_body(
+    header: &ResponseHeader,
+    body: &[u8],
+) -> Result<Vec<u8>, crate::MessageError> {
+    let body = body
+        .get(..header.data_len as usize)
+        .ok_or(crate::MessageError::BufferTooShort {
+            needed: header.data_len as usize,
+            got: body.len(),
+        })?;
+    if !header.is_compressed() {
+        return Ok(body.to_vec());
+    }
+
+    let original_len = original_body_len(body)?;
+    let decompressed = miniz_oxide::inflate::decompress_to_vec_with_limit(
+        &body[ORIGINAL_LENGTH_SIZE..],
+        original_len,
+    )
+    .map_err(|_| crate::MessageError::InvalidField {
+        field: "data",
+        reason: "compressed data is corrupt",
+    })?;
+    if decompressed.len() != original_len {
+        return Err(crate::MessageError::LengthMismatch {
+            expected: original_len,
+            found: decompressed.len(),
+        });
+    }
+
+    Ok(decompressed)
+}
+
+/// Reads the original length in front of compressed data.
+#[cfg(feature = "compress")]
+fn original_body_len(body: &[u8]) -> Result<usize, crate::MessageError> {
+    let prefix = body
+        .get(..ORIGINAL_LENGTH_SIZE)
+        .ok_or(crate::MessageError::BufferTooShort {
+            needed: ORIGINAL_LENGTH_SIZE,
+            got: body.len(),
+        })?;
+    let mut original_len = [0; ORIGINAL_LENGTH_SIZE];
+    original_len.copy_from_slice(prefix);
+
+    Ok(u32::from_le_bytes(original_len) as usize)
+}
+
+/// Returns the size of the buffer needed by `decompress_response_body_c` for the data belonging to `header`.
+/// For data that is not compressed this is simply its `data_len`.
+/// # Returns
+/// The size on success.
+/// -1 when header or body was a null pointer.
+/// -2 when the compressed data is too short to be valid.
+/// # Safety
+/// `header` must point to a valid `ResponseHeader` and `body` to at least `data_len` bytes.
+#[cfg(all(feature = "std", feature = "compress"))]
+#[no_mangle]
+pub unsafe extern "C" fn get_decompressed_response_body_size(
+    header: *const ResponseHeader,
+    body: *const libc::c_uchar,
+) -> i64 {
+    if header.is_null() || body.is_null() {
+        return -1;
+    }
+
+    let body = core::slice::from_raw_parts(body, (*header).data_len as usize);
+    if !(*header).is_compressed() {
+        return body.len() as i64;
+    }
+
+    match original_body_len(body) {
+        Ok(original_len) => original_len as i64,
+        Err(_) => -2,
+    }
+}
+
+/// C version of `decompress_response_body` which writes the data into `out_buffer`, see
+/// `get_decompressed_response_body_size` for how large it has to be.
+/// # Returns
+/// The number of bytes written to `out_buffer` on success.
+/// -1 when header, body or out_buffer was a null pointer.
+/// -2 when out_buffer_len is too small to hold the data.
+/// -3 when the compressed data is corrupt.
+/// # Safety
+/// `header` must point to a valid `ResponseHeader`, `body` to at least `data_len` bytes and `out_buffer` to at
+/// least `out_buffer_len` writable bytes.
+#[cfg(all(feature = "std", feature = "compress"))]
+#[no_mangle]
+pub unsafe extern "C" fn decompress_response_body_c(
+    header: *const ResponseHeader,
+    body: *const libc::c_uchar,
+    out_buffer: *mut libc::c_uchar,
+    out_buffer_len: libc::size_t,
+) -> i64 {
+    if header.is_null() || body.is_null() || out_buffer.is_null() {
+        return -1;
+    }
+
+    let body = core::slice::from_raw_parts(body, (*header).data_len as usize);
+    let decompressed = match decompress_response_body(&*header, body) {
+        Ok(decompressed) => zeroize::Zeroizing::new(decompressed),
+        Err(_) => return -3,
+    };
+    if decompressed.len() > out_buffer_len {
+        return -2;
+    }
+
+    core::ptr::copy_nonoverlapping(decompressed.as_ptr(), out_buffer, decompressed.len());
+
+    decompressed.len() as i64
+}
+
+/// C version of `serialize_response` which writes the serialized response into `out_buffer`. A null `data`
+/// results in only the header with `ResponseStatus::OperationFailed` as success status.
+/// # Returns
+/// The number of bytes written to `out_buffer` on success.
+/// -1 when out_buffer was a null pointer.
+/// -2 when out_buffer_len is too small to hold the response.
+/// -3 when data_len is too long to be described by the `data_len` of the header.
+/// -4 on serialization failure.
+/// # Safety
+/// `data` must point to at least `data_len` bytes (unless it is a null pointer) and `out_buffer` must point to
+/// at least `out_buffer_len` writable bytes. The buffer needs room for `get_serialized_response_header_size()`
+/// plus `data_len` bytes (plus the trailer with the `checksum` feature).
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn serialize_response_c(
+    identifier: crate::Identifier,
+    data: *const libc::c_uchar,
+    data_len: libc::size_t,
+    out_buffer: *mut libc::c_uchar,
+    out_buffer_len: libc::size_t,
+) -> i64 {
+    if out_buffer.is_null() {
+        return -1;
+    }
+
+    let data = if data.is_null() {
+        Err(ResponseStatus::OperationFailed)
+    } else {
+        Ok(core::slice::from_raw_parts(data, data_len))
+    };
+    let response = match serialize_response_result(identifier, data) {
+        Ok(response) => ZeroizingResponse::from(response),
+        Err(crate::MessageError::LengthOverflow) => return -3,
+        Err(_) => return -4,
+    };
+    if response.len() > out_buffer_len {
+        return -2;
+    }
+
+    core::ptr::copy_nonoverlapping(response.as_ptr(), out_buffer, response.len());
+
+    response.len() as i64
+}
+
+/// Returns the serialized response to a `Hello` request, listing the format versions this crate supports.
+pub fn build_hello_response(identifier: crate::Identifier) -> Result<Vec<u8>, crate::MessageError> {
+    serialize_response(identifier, &crate::SUPPORTED_FORMAT_VERSIONS)
+}
+
+/// Writes a response to `W` in chunks, so that the data never has to be held in memory all at once.
+/// The written bytes are exactly the same as those of `serialize_response` for the same data, except that
+/// the data is never compressed.
+#[cfg(feature = "std")]
+pub struct ResponseWriter<W: std::io::Write> {
+    writer: W,
+    remaining: u32,
+    #[cfg(feature = "checksum")]
+    crc: u32,
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write> ResponseWriter<W> {
+    /// Writes the header of a successful response carrying `data_len` bytes of data.
+    pub fn new(
+        mut writer: W,
+        identifier: crate::Identifier,
+        data_len: u32,
+    ) -> Result<Self, crate::MessageError> {
+        let response_header = ResponseHeader::new(identifier, ResponseStatus::Ok, data_len);
+        let serialized = response_header.to_bytes();
+        writer.write_all(&serialized)?;
+
+        Ok(ResponseWriter {
+            writer,
+            remaining: data_len,
+            #[cfg(feature = "checksum")]
+            crc: crate::checksum::crc32c_update(!0, &serialized),
+        })
+    }
+
+    /// Writes the next chunk of data.
+    /// # Errors
+    /// `MessageError::LengthOverflow` when the chunk does not fit in the `data_len` announced in the header.
+    /// Nothing is written in that case.
+    pub fn write_chunk(&mut self, chunk: &[u8]) -> Result<(), crate::MessageError> {
+        let chunk_len = u32::try_from(chunk.len())
+            .ok()
+            .filter(|&chunk_len| chunk_len <= self.remaining)
+            .ok_or(crate::MessageError::LengthOverflow)?;
+        self.writer.write_all(chunk)?;
+        self.remaining -= chunk_len;
+
+        #[cfg(feature = "checksum")]
+        {
+            self.crc = crate::checksum::crc32c_update(self.crc, chunk);
+        }
+
+        Ok(())
+    }
+
+    /// Finishes the response and returns the underlying writer.
+    /// # Errors
+    /// `MessageError::BufferTooShort` when less data was written than announced in the header.
+    pub fn finish(mut self) -> Result<W, crate::MessageError> {
+        if self.remaining != 0 {
+            return Err(crate::MessageError::BufferTooShort {
+                needed: self.remaining as usize,
+                got: 0,
+            });
+        }
+
+        #[cfg(feature = "checksum")]
+        self.writer.write_all(&(!self.crc).to_le_bytes())?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Reads a response from `R`. The header is read immediately while the data can be read in chunks
+/// with `read_chunk`, so that it never has to be held in memory all at once. The data is read as it was
+/// sent, so compressed data (see `ResponseHeader::is_compressed`) has to be decompressed afterwards.
+#[cfg(feature = "std")]
+pub struct ResponseReader<R: std::io::Read> {
+    reader: R,
+    header: ResponseHeader,
+    remaining: u32,
+    #[cfg(feature = "checksum")]
+    crc: u32,
+}
+
+#[cfg(feature = "std")]
+impl<R: std::io::Read> ResponseReader<R> {
+    /// Reads the header of the response.
+    /// # Errors
+    /// `MessageError::Io` when the header cannot be read, `MessageError::VersionMismatch` when the
+    /// header was produced with a format version this crate does not accept and the errors of
+    /// `ResponseHeader::validate`.
+    pub fn new(mut reader: R) -> Result<Self, crate::MessageError> {
+        let mut serialized = [0; RESPONSE_HEADER_SIZE as usize];
+        reader.read_exact(&mut serialized)?;
+
+        let header = read_response_header(&serialized)?;
+
+        #[allow(unused_mut)]
+        let mut response_reader = ResponseReader {
+            reader,
+            remaining: header.data_len,
+            header,
+            #[cfg(feature = "checksum")]
+            crc: crate::checksum::crc32c_update(!0, &serialized),
+        };
+
+        #[cfg(feature = "checksum")]
+        {
+            if response_reader.remaining == 0 {
+                response_reader.verify_trailer()?;
+            }
+        }
+
+        Ok(response_reader)
+    }
+
+    /// The header of the response.
+    pub fn header(&self) -> &ResponseHeader {
+        &self.header
+    }
+
+    /// Number of bytes of data that have not been read yet.
+    pub fn remaining(&self) -> usize {
+        self.remaining as usize
+    }
+
+    /// Reads the next chunk of data into `buffer` and returns the number of bytes read, which is 0
+    /// once all data has been read.
+    /// # Errors
+    /// `MessageError::Io` when the stream fails or ends before all data has been read. With the
+    /// `checksum` feature `MessageError::ChecksumMismatch` is returned by the read that reaches the end
+    /// of the data when the trailer does not match (or by `new` when there is no data at all).
+    pub fn read_chunk(&mut self, buffer: &mut [u8]) -> Result<usize, crate::MessageError> {
+        let chunk_len = buffer.len().min(self.remaining as usize);
+        if chunk_len == 0 {
+            return Ok(0);
+        }
+
+        let chunk = &mut buffer[..chunk_len];
+        self.reader.read_exact(chunk)?;
+        self.remaining -= chunk_len as u32;
+
+        #[cfg(feature = "checksum")]
+        {
+            self.crc = crate::checksum::crc32c_update(self.crc, chunk);
+            if self.remaining == 0 {
+                self.verify_trailer()?;
+            }
+        }
+
+        Ok(chunk_len)
+    }
+
+    /// Reads the checksum trailer following the data and compares it to the checksum of what was read.
+    #[cfg(feature = "checksum")]
+    fn verify_trailer(&mut self) -> Result<(), crate::MessageError> {
+        let mut trailer = [0; crate::checksum::CHECKSUM_SIZE];
+        self.reader.read_exact(&mut trailer)?;
+
+        let expected = u32::from_le_bytes(trailer);
+        let found = !self.crc;
+        if expected != found {
+            return Err(crate::MessageError::ChecksumMismatch { expected, found });
+        }
+
+        Ok(())
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Reads a single response from `reader`, giving up when it has not arrived completely within `timeout`. The
+/// data is returned as it was sent, so compressed data (see `ResponseHeader::is_compressed`) has to be
+/// decompressed afterwards. The deadline and what has been consumed from the stream on a timeout work
+/// exactly as for `request::read_request_timeout`.
+/// # Errors
+/// `MessageError::Timeout` when the deadline passes, `MessageError::UnexpectedEof` when the stream ends in
+/// the middle of the response, `MessageError::MessageTooLarge` when the response would exceed
+/// `request::MAX_MESSAGE_SIZE`, the errors of `deserialize_response_header_bounded`,
+/// `MessageError::ChecksumMismatch` with the `checksum` feature and `MessageError::Io` when reading fails.
+#[cfg(feature = "std")]
+pub fn read_response_timeout<R: std::io::Read>(
+    reader: &mut R,
+    timeout: core::time::Duration,
+) -> Result<(ResponseHeader, Vec<u8>), crate::MessageError> {
+    read_response_timeout_bounded(reader, timeout, crate::request::MAX_MESSAGE_SIZE)
+}
+
+/// Like `read_response_timeout`, but accepts responses of up to `max_message_size` bytes, header and trailer
+/// included, instead of `request::MAX_MESSAGE_SIZE`.
+/// # Errors
+/// The errors of `read_response_timeout`, with `MessageError::MessageTooLarge` for a response exceeding
+/// `max_message_size`.
+#[cfg(feature = "std")]
+pub fn read_response_timeout_bounded<R: std::io::Read>(
+    reader: &mut R,
+    timeout: core::time::Duration,
+    max_message_size: usize,
+) -> Result<(ResponseHeader, Vec<u8>), crate::MessageError> {
+    let deadline = std::time::Instant::now().checked_add(timeout);
+    let header_size = RESPONSE_HEADER_SIZE as usize;
+    let mut message = zeroize::Zeroizing::new(vec![0; header_size]);
+    crate::read_exact_until(reader, &mut message, 0, deadline)?;
+    let header = read_response_header(&message)?;
+    let message_size = crate::check_message_size(header_size, header.data_len, max_message_size)?;
+
+    let data_len = header.data_len as usize;
+    message.resize(message_size, 0);
+    crate::read_exact_until(reader, &mut message[header_size..], header_size, deadline)?;
+
+    #[cfg(feature = "checksum")]
+    crate::checksum::verify_checksum(&message)?;
+
+    let data = message[header_size..header_size + data_len].to_vec();
+    Ok((header, data))
+}
+
+/// Given a buffer will return a `ResponseHeader`, rejecting a header describing more than `max_data_len`
+/// bytes of data so that a reader never allocates for a length an attacker picked.
+/// `request::RECOMMENDED_MAX_DATA_LEN` fits the data of every operation.
+/// # Returns
+/// A ResponseHeader for success. Otherwise a `MessageError` describing why the header could not be read,
+/// e.g. `BufferTooShort`, `VersionMismatch`, `BodyTooLarge` when `data_len` exceeds `max_data_len` or
+/// `ProtocolViolation` for a failure that carries data (see `ResponseHeader::validate`).
+pub fn deserialize_response_header_bounded(
+    response_header: &[u8],
+    max_data_len: u32,
+) -> Result<ResponseHeader, crate::MessageError> {
+    let response_header = read_response_header(response_header)?;
+    if response_header.data_len > max_data_len {
+        log_debug!(
+            "response header describes {} bytes of data but at most {} are accepted",
+            response_header.data_len,
+            max_data_len
+        );
+        return Err(crate::MessageError::BodyTooLarge {
+            max: max_data_len,
+            found: response_header.data_len,
+        });
+    }
+
+    Ok(response_header)
+}
+
+/// Reads a `ResponseHeader` that is accepted whatever its `data_len`, leaving the caller to bound that.
+/// # Errors
+/// The errors of `ResponseHeader::from_bytes`, `MessageError::VersionMismatch` for a version this crate
+/// does not accept and the errors of `ResponseHeader::validate`.
+pub(crate) fn read_response_header(
+    response_header: &[u8],
+) -> Result<ResponseHeader, crate::MessageError> {
+    let response_header = ResponseHeader::from_bytes(response_header)?;
+    crate::check_format_version(response_header.version)?;
+    response_header.validate()?;
+
+    Ok(response_header)
+}
+
+/// Parses a response serialized by `serialize_response` or `serialize_failure_response`: the header followed
+/// by exactly `data_len` bytes of data, which are returned as a slice of `buf`. Anything after the response
+/// (e.g. the next response) is ignored. The data is returned as it was sent, so compressed data (see
+/// `ResponseHeader::is_compressed`) has to be decompressed afterwards.
+/// # Errors
+/// `MessageError::BufferTooShort` when `buf` does not hold the whole response, the errors of
+/// `ResponseHeader::from_bytes`, `MessageError::VersionMismatch` for a version this crate does not accept,
+/// the errors of `ResponseHeader::validate` and `MessageError::ChecksumMismatch` with the `checksum` feature.
+pub fn parse_response(buf: &[u8]) -> Result<(ResponseHeader, &[u8]), crate::MessageError> {
+    let response_header = read_response_header(buf)?;
+
+    #[cfg(not(feature = "checksum"))]
+    let trailer_size = 0;
+    #[cfg(feature = "checksum")]
+    let trailer_size = crate::checksum::CHECKSUM_SIZE;
+
+    // On a 32-bit target a data_len close to u32::MAX overflows, such a response never fits in `buf`
+    let header_size = RESPONSE_HEADER_SIZE as usize;
+    let response_size = (response_header.data_len as usize).checked_add(header_size + trailer_size);
+    let response_size = match response_size {
+        Some(response_size) if response_size <= buf.len() => response_size,
+        _ => {
+            let needed = response_size.unwrap_or(usize::MAX);
+            log_debug!(
+                "response of {} bytes is cut off after {} bytes",
+                needed,
+                buf.len()
+            );
+            return Err(crate::MessageError::BufferTooShort {
+                needed,
+                got: buf.len(),
+            });
+        }
+    };
+    let data_end = response_size - trailer_size;
+
+    #[cfg(feature = "checksum")]
+    crate::checksum::verify_checksum(&buf[..response_size])?;
+
+    let data = &buf[header_size..data_end];
+    validate_body_len(&response_header, data)?;
+
+    Ok((response_header, data))
+}
+
+/// Checks that `data` is exactly the `data_len` bytes that `header` describes, just like
+/// `request::validate_body_len` does for requests.
+/// # Errors
+/// `MessageError::LengthMismatch` when the length of `data` differs from `data_len`.
+pub fn validate_body_len(header: &ResponseHeader, data: &[u8]) -> Result<(), crate::MessageError> {
+    if header.data_len as usize != data.len() {
+        log_debug!(
+            "response data of {} bytes but data_len is {}",
+            data.len(),
+            header.data_len
+        );
+        return Err(crate::MessageError::LengthMismatch {
+            expected: header.data_len as usize,
+            found: data.len(),
+        });
+    }
+
+    Ok(())
+}
+
 /// Given a pointer will return a `ResponseHeader`. This header can be used to determine how many bytes
 /// of data are coming up.
 /// # Returns
 /// 0 on success.
 /// -1 when a null pointer was passed in.
-/// -2 for when the header will not fit in memory due to architecture.
+/// -2 for when the header (or with the `checksum` feature the whole message) will not fit in memory due to
+/// architecture.
 /// -3 for deserialization failure.
-/// -4 for mismatch of version in header.
+/// -4 for a version in the header that this crate does not accept. `response_header` is still set so that
+/// the response to a `Hello` can be read.
+/// -5 for mismatch of the checksum trailer (only with the `checksum` feature).
+/// -6 for a success marked as diagnostic (see `ResponseHeader::validate`). `response_header` is still set.
+/// -7 for a failure that carries data other than a diagnostic message, a protocol violation of the responder
+/// (see `ResponseHeader::validate`). `response_header` is still set.
 /// # Safety
 /// Unsafe because there is no absolute guarantee we don't get a pointer handed somewhere
 /// in program space that happens to deserialize succesfully to a ResponseHeader.
 /// When used in combination with `get_serialized_response_header_size` this function
 /// will be able to safely and correctly deserialize a response header.
+/// With the `checksum` feature `response_data` must point to the complete message, that is the header
+/// followed by `data_len` bytes of data and the checksum trailer.
+#[cfg(feature = "std")]
 #[no_mangle]
 pub unsafe extern "C" fn deserialize_response_header(
     response_data: *const libc::c_uchar,
@@ -102,35 +1143,203 @@ pub unsafe extern "C" fn deserialize_response_header(
 
     let response;
     if let Ok(header_size) = usize::try_from(get_serialized_response_header_size()) {
-        response = std::slice::from_raw_parts(response_data, header_size);
+        response = core::slice::from_raw_parts(response_data, header_size);
     } else {
         return -2;
     }
 
-    if let Ok(deserialized) = bincode::deserialize(response) {
+    if let Ok(deserialized) = ResponseHeader::from_bytes(response) {
         *response_header = deserialized;
     } else {
         return -3;
     }
 
-    if (*response_header).version != crate::FORMAT_VERSION {
-        -4
-    } else {
-        0
+    if crate::check_format_version((*response_header).version).is_err() {
+        return -4;
+    }
+    match (*response_header).validate() {
+        Ok(()) => (),
+        Err(crate::MessageError::ProtocolViolation { .. }) => return -7,
+        Err(_) => return -6,
     }
+
+    #[cfg(feature = "checksum")]
+    {
+        let message_len = match ((*response_header).data_len as usize)
+            .checked_add(response.len() + crate::checksum::CHECKSUM_SIZE)
+        {
+            Some(message_len) => message_len,
+            None => return -2,
+        };
+        let message = core::slice::from_raw_parts(response_data, message_len);
+        if crate::checksum::verify_checksum(message).is_err() {
+            return -5;
+        }
+    }
+
+    0
+}
+
+/// Fills in `out` with `header` and `body` after checking that `header.data_len` is exactly `body_len`, so that
+/// a `Response` built in C never describes more or less data than its body holds. The body is not copied,
+/// so it must outlive `out` and must not be released with `free_response_body`.
+/// # Returns
+/// 0 on success.
+/// -1 when out was a null pointer.
+/// -2 when body was a null pointer while body_len is not 0.
+/// -3 when `header.data_len` does not match body_len. `out` is left untouched in that case.
+/// # Safety
+/// `body` must point to at least `body_len` bytes (unless body_len is 0) and `out` must point to a writable
+/// `Response`.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn response_from_parts(
+    header: ResponseHeader,
+    body: *const libc::c_uchar,
+    body_len: libc::size_t,
+    out: *mut Response,
+) -> i16 {
+    if out.is_null() {
+        return -1;
+    } else if body.is_null() && body_len != 0 {
+        return -2;
+    } else if header.data_len as usize != body_len {
+        log_debug!(
+            "response header describes {} bytes but the body has {}",
+            header.data_len,
+            body_len
+        );
+        return -3;
+    }
+
+    out.write(Response { header, body });
+
+    0
+}
+
+/// Securely clears and frees the body of a `Response` created with `Response::new`. Nothing happens when
+/// body is a null pointer.
+/// # Safety
+/// `body` must be the body of a `Response` created with `Response::new` and `len` its `header.data_len`.
+/// The body must not be used or freed again afterwards.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn free_response_body(body: *mut libc::c_uchar, len: libc::size_t) {
+    if body.is_null() {
+        return;
+    }
+
+    let mut body = alloc::boxed::Box::from_raw(core::ptr::slice_from_raw_parts_mut(body, len));
+    body.zeroize();
 }
 
 /// Given two entries and their length this function will put them back-to-back into data with length included.
+/// Either entry may be empty, which is framed as a length of 0 and destructured as an empty slice again.
 pub fn structure_two_entries(entry1: &[u8], entry2: &[u8]) -> Vec<u8> {
+    concatenate_entries(&[entry1, entry2])
+}
+
+/// Given any number of entries this function will put them back-to-back into data with length included,
+/// in the order they were given. For two entries this produces exactly the same bytes as `structure_two_entries`.
+/// # Errors
+/// `MessageError::InvalidField` naming `entries` when there are none, like `structure_n_entries` returns -5.
+pub fn structure_entries(entries: &[&[u8]]) -> Result<Vec<u8>, crate::MessageError> {
+    if entries.is_empty() {
+        log_debug!("refusing to structure zero entries");
+        return Err(crate::MessageError::InvalidField {
+            field: "entries",
+            reason: "at least one entry has to be structured",
+        });
+    }
+
+    Ok(concatenate_entries(entries))
+}
+
+// Puts `entries` back-to-back with their length prefixes, for a number of entries that is known to be valid.
+fn concatenate_entries(entries: &[&[u8]]) -> Vec<u8> {
     let mut structured_data = Vec::new();
-    structured_data.extend(&entry1.len().to_le_bytes());
-    structured_data.extend_from_slice(entry1);
-    structured_data.extend(&entry2.len().to_le_bytes());
-    structured_data.extend_from_slice(entry2);
+    for entry in entries {
+        structured_data.extend(&(entry.len() as u64).to_le_bytes());
+        structured_data.extend_from_slice(entry);
+    }
+    log_trace!(
+        "structured {} entries into {} bytes",
+        entries.len(),
+        structured_data.len()
+    );
 
     structured_data
 }
 
+/// Returns where the two entries lie within data structured by `structure_two_entries` from entries of
+/// `entry1_len` and `entry2_len` bytes, i.e. right after their length prefixes. The second range ends where
+/// the data ends. Offsets that do not fit in a `usize` saturate, so such a layout never fits in a buffer.
+pub fn two_entries_layout(
+    entry1_len: usize,
+    entry2_len: usize,
+) -> (core::ops::Range<usize>, core::ops::Range<usize>) {
+    let entry1_start = crate::LENGTH_PREFIX_SIZE;
+    let entry1_end = entry1_start.saturating_add(entry1_len);
+    let entry2_start = entry1_end.saturating_add(crate::LENGTH_PREFIX_SIZE);
+
+    (
+        entry1_start..entry1_end,
+        entry2_start..entry2_start.saturating_add(entry2_len),
+    )
+}
+
+/// Returns where the shared secret and the ciphertext lie within the data of the response to an
+/// encapsulation with `algorithm`, see `two_entries_layout` and `request::Algorithm::sizes`. A responder can
+/// write both straight into one buffer of `ciphertext_range.end` bytes, e.g. on the stack or from a pool,
+/// and finish the data with `write_two_entries_prefixes` instead of allocating for every entry.
+pub fn encapsulation_response_layout(
+    algorithm: crate::request::Algorithm,
+) -> (core::ops::Range<usize>, core::ops::Range<usize>) {
+    let sizes = algorithm.sizes();
+
+    two_entries_layout(sizes.shared_secret_len, sizes.ciphertext_len)
+}
+
+/// Writes the length prefixes in front of two entries that are already in place in `data` at the ranges of
+/// `two_entries_layout(entry1_len, entry2_len)`. Afterwards `data` up to the end of the second range holds
+/// exactly what `structure_two_entries` produces for those entries, without anything being allocated.
+/// # Errors
+/// `MessageError::BufferTooShort` when `data` cannot hold both entries with their prefixes. Nothing is
+/// written in that case.
+pub fn write_two_entries_prefixes(
+    data: &mut [u8],
+    entry1_len: usize,
+    entry2_len: usize,
+) -> Result<(), crate::MessageError> {
+    let (entry1, entry2) = two_entries_layout(entry1_len, entry2_len);
+    if data.len() < entry2.end {
+        return Err(crate::MessageError::BufferTooShort {
+            needed: entry2.end,
+            got: data.len(),
+        });
+    }
+
+    data[..entry1.start].copy_from_slice(&(entry1_len as u64).to_le_bytes());
+    data[entry1.end..entry2.start].copy_from_slice(&(entry2_len as u64).to_le_bytes());
+
+    Ok(())
+}
+
+/// Returns the three entries back-to-back with their lengths included, e.g. a shared secret, some metadata
+/// and a ciphertext. This produces exactly the same bytes as `structure_entries` for three entries.
+pub fn structure_three_entries(entry1: &[u8], entry2: &[u8], entry3: &[u8]) -> Vec<u8> {
+    concatenate_entries(&[entry1, entry2, entry3])
+}
+
+/// Parses a little-endian `u64` length prefix. Returns `None` when `length` is not exactly
+/// `LENGTH_PREFIX_SIZE` bytes or when the length does not fit in a `usize`.
+#[cfg(feature = "std")]
+fn parse_length(length: &[u8]) -> Option<usize> {
+    use core::convert::TryInto;
+
+    usize::try_from(u64::from_le_bytes(length.try_into().ok()?)).ok()
+}
+
 /// Given a pointer of a buffer which contains two data fields it will set entry1 and entry2 pointers to those locations
 /// within the buffer. Additionally it will set the length appropriately.
 /// # Returns
@@ -140,7 +1349,7 @@ pub fn structure_two_entries(entry1: &[u8], entry2: &[u8]) -> Vec<u8> {
 /// -3 when entry2_length pointer was null.
 /// -4 when entry1 pointer was null.
 /// -5 when entry2 pointer was null.
-/// -6 or -7 if parsing the lengths is unsuccessful
+/// -6 or -7 if parsing the lengths is unsuccessful, e.g. when a length does not fit in a `size_t`
 /// -8 if the provided data would cause an out of bounds access
 /// # Safety
 /// This function does extensive checking on null pointers and checks whether
@@ -148,6 +1357,8 @@ pub fn structure_two_entries(entry1: &[u8], entry2: &[u8]) -> Vec<u8> {
 /// Having said that, if the caller inputs an invalid `data_size` there is no way
 /// for this function to ever realize this; this will not happen unless the caller
 /// has a bug since the header will inform the caller how large the buffer should be.
+/// The entries point into `data`, which remains owned by the caller, so they must not be freed.
+#[cfg(feature = "std")]
 #[no_mangle]
 pub unsafe extern "C" fn destructure_two_entries(
     data: *const libc::c_uchar,
@@ -169,44 +1380,296 @@ pub unsafe extern "C" fn destructure_two_entries(
         return -5;
     }
 
-    let data_start = std::slice::from_raw_parts(data, data_size);
-    let usize_size_in_bytes = std::mem::size_of::<usize>();
-
-    // Retrieve and set lengths of entry1
-    let unparsed_length = match data_start.get(..usize_size_in_bytes) {
-        Some(data) => data,
-        None => return -8,
+    // Only lengths of slices that lie within data are compared and subtracted, never added, so no crafted
+    // length can wrap around on any pointer width (see `split_entry`).
+    let data = core::slice::from_raw_parts(data, data_size);
+    let (parsed_entry1, rest) = match split_entry(data) {
+        Ok(split) => split,
+        Err(EntryError::Length) => return -6,
+        Err(EntryError::OutOfBounds) => return -8,
+    };
+    let (parsed_entry2, _) = match split_entry(rest) {
+        Ok(split) => split,
+        Err(EntryError::Length) => return -7,
+        Err(EntryError::OutOfBounds) => return -8,
     };
-    let data_start = &data_start[usize_size_in_bytes..];
+    let parsed_entry1_length = parsed_entry1.len();
+    let parsed_entry2_length = parsed_entry2.len();
+    *entry1_length = parsed_entry1_length;
+    *entry1 = parsed_entry1.as_ptr();
+    *entry2_length = parsed_entry2_length;
+    *entry2 = parsed_entry2.as_ptr();
+    log_trace!(
+        "destructured 2 entries of {} and {} bytes",
+        parsed_entry1_length,
+        parsed_entry2_length
+    );
 
-    let parsed_entry1_length = usize::from_le_bytes(match unparsed_length.try_into() {
-        Ok(val) => val,
-        Err(_) => return -6,
-    });
-    if parsed_entry1_length > data_start.len() {
-        return -8;
+    0
+}
+
+/// Why a single entry could not be read by `split_entry`.
+#[cfg(feature = "std")]
+enum EntryError {
+    /// The length does not fit in a `size_t`.
+    Length,
+    /// The length prefix or the entry itself lies past the end of the buffer.
+    OutOfBounds,
+}
+
+/// Splits the length-prefixed entry at the start of `data` off from whatever follows it.
+#[cfg(feature = "std")]
+fn split_entry(data: &[u8]) -> Result<(&[u8], &[u8]), EntryError> {
+    let unparsed_length = data
+        .get(..crate::LENGTH_PREFIX_SIZE)
+        .ok_or(EntryError::OutOfBounds)?;
+    let data = &data[crate::LENGTH_PREFIX_SIZE..];
+
+    let entry_length = parse_length(unparsed_length).ok_or(EntryError::Length)?;
+    if entry_length > data.len() {
+        return Err(EntryError::OutOfBounds);
     }
-    *entry1_length = parsed_entry1_length;
-    *entry1 = data_start.as_ptr();
 
-    let data_start = &data_start[parsed_entry1_length..];
+    Ok(data.split_at(entry_length))
+}
 
-    // Retrieve and set lengths of entry2
-    let unparsed_length = match data_start.get(..usize_size_in_bytes) {
-        Some(data) => data,
-        None => return -8,
+/// Given a pointer of a buffer which contains three data fields it will set entry1, entry2 and entry3 pointers to
+/// those locations within the buffer. Additionally it will set the lengths appropriately.
+/// # Returns
+/// 0 on success.
+/// -1 when data pointer was null.
+/// -2, -3 or -4 when the entry1_length, entry2_length or entry3_length pointer was null.
+/// -5, -6 or -7 when the entry1, entry2 or entry3 pointer was null.
+/// -8, -9 or -10 if parsing the length of entry1, entry2 or entry3 is unsuccessful, e.g. when it does not fit
+/// in a `size_t`.
+/// -11, -12 or -13 if entry1, entry2 or entry3 would cause an out of bounds access.
+/// # Safety
+/// Same as `destructure_two_entries`.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn destructure_three_entries(
+    data: *const libc::c_uchar,
+    data_size: libc::size_t,
+    entry1_length: *mut libc::size_t,
+    entry2_length: *mut libc::size_t,
+    entry3_length: *mut libc::size_t,
+    entry1: *mut *const libc::c_uchar,
+    entry2: *mut *const libc::c_uchar,
+    entry3: *mut *const libc::c_uchar,
+) -> i16 {
+    if data.is_null() {
+        return -1;
+    } else if entry1_length.is_null() {
+        return -2;
+    } else if entry2_length.is_null() {
+        return -3;
+    } else if entry3_length.is_null() {
+        return -4;
+    } else if entry1.is_null() {
+        return -5;
+    } else if entry2.is_null() {
+        return -6;
+    } else if entry3.is_null() {
+        return -7;
+    }
+
+    let mut data_start = core::slice::from_raw_parts(data, data_size);
+    let outputs = [
+        (entry1_length, entry1),
+        (entry2_length, entry2),
+        (entry3_length, entry3),
+    ];
+    for (index, (entry_length, entry)) in (0..).zip(outputs.iter()) {
+        let (parsed_entry, rest) = match split_entry(data_start) {
+            Ok(split) => split,
+            Err(EntryError::Length) => return -8 - index,
+            Err(EntryError::OutOfBounds) => return -11 - index,
+        };
+        **entry_length = parsed_entry.len();
+        **entry = parsed_entry.as_ptr();
+        data_start = rest;
+    }
+    log_trace!(
+        "destructured 3 entries of {}, {} and {} bytes",
+        *entry1_length,
+        *entry2_length,
+        *entry3_length
+    );
+
+    0
+}
+
+/// Same as `destructure_two_entries` except that it additionally sets consumed to the number of bytes
+/// taken up by the two entries and verifies that this is all of `data`.
+/// # Returns
+/// 0 on success.
+/// -1 up to -8 just like `destructure_two_entries`.
+/// -9 when consumed pointer was null.
+/// -10 when data contains more than the two entries. The entries and consumed are still set in that case.
+/// # Safety
+/// Same as `destructure_two_entries`.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn destructure_two_entries_checked(
+    data: *const libc::c_uchar,
+    data_size: libc::size_t,
+    entry1_length: *mut libc::size_t,
+    entry2_length: *mut libc::size_t,
+    entry1: *mut *const libc::c_uchar,
+    entry2: *mut *const libc::c_uchar,
+    consumed: *mut libc::size_t,
+) -> i16 {
+    if consumed.is_null() {
+        return -9;
+    }
+
+    let status = destructure_two_entries(
+        data,
+        data_size,
+        entry1_length,
+        entry2_length,
+        entry1,
+        entry2,
+    );
+    if status != 0 {
+        return status;
+    }
+
+    // Both entries lie within data so this cannot overflow
+    *consumed = 2 * crate::LENGTH_PREFIX_SIZE + *entry1_length + *entry2_length;
+    if *consumed != data_size {
+        return -10;
+    }
+
+    0
+}
+
+/// Returns a description of a code returned by `destructure_two_entries` or
+/// `destructure_two_entries_checked`, so that C code does not need its own table of them. Other functions
+/// use the same negative numbers for other failures, so only pass codes of these two.
+/// # Returns
+/// A static, null-terminated string that must not be freed. "success" for 0 and "unknown error" for a
+/// code these functions never return.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub extern "C" fn pq_message_error_str(code: i16) -> *const libc::c_char {
+    let description: &'static [u8] = match code {
+        0 => b"success\0",
+        -1 => b"data pointer was null\0",
+        -2 => b"entry1_length pointer was null\0",
+        -3 => b"entry2_length pointer was null\0",
+        -4 => b"entry1 pointer was null\0",
+        -5 => b"entry2 pointer was null\0",
+        -6 => b"length of entry1 does not fit in a size_t\0",
+        -7 => b"length of entry2 does not fit in a size_t\0",
+        -8 => b"an entry lies past the end of data\0",
+        -9 => b"consumed pointer was null\0",
+        -10 => b"data contains more than the two entries\0",
+        _ => b"unknown error\0",
     };
-    let data_start = &data_start[usize_size_in_bytes..];
 
-    let parsed_entry2_length = usize::from_le_bytes(match unparsed_length.try_into() {
-        Ok(val) => val,
-        Err(_) => return -7,
-    });
-    if parsed_entry2_length > data_start.len() {
-        return -8;
+    description.as_ptr() as *const libc::c_char
+}
+
+/// Given a pointer of a buffer which contains any number of data fields it will set the first `capacity`
+/// elements of `entries` to those locations within the buffer, in the order they were structured. Additionally
+/// it will set the lengths in `entry_lengths` and the number of entries found in `entry_count`.
+/// # Returns
+/// 0 on success.
+/// -1 when data pointer was null.
+/// -2 when entry_lengths pointer was null.
+/// -3 when entries pointer was null.
+/// -4 when entry_count pointer was null.
+/// -5 when data does not contain any entries.
+/// -6 when data contains more than `capacity` entries.
+/// -7 if parsing one of the lengths is unsuccessful, e.g. when a length does not fit in a `size_t`
+/// -8 if the provided data would cause an out of bounds access
+/// # Safety
+/// `entry_lengths` and `entries` must both point to at least `capacity` elements. Just like
+/// `destructure_two_entries` there is no way for this function to realize that `data_size` is invalid.
+/// The entries point into `data`, which remains owned by the caller, so they must not be freed.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn destructure_n_entries(
+    data: *const libc::c_uchar,
+    data_size: libc::size_t,
+    capacity: libc::size_t,
+    entry_lengths: *mut libc::size_t,
+    entries: *mut *const libc::c_uchar,
+    entry_count: *mut libc::size_t,
+) -> i16 {
+    if data.is_null() {
+        return -1;
+    } else if entry_lengths.is_null() {
+        return -2;
+    } else if entries.is_null() {
+        return -3;
+    } else if entry_count.is_null() {
+        return -4;
+    } else if data_size == 0 {
+        return -5;
+    }
+
+    let mut data_start = core::slice::from_raw_parts(data, data_size);
+
+    let mut found = 0;
+    while !data_start.is_empty() {
+        if found == capacity {
+            return -6;
+        }
+
+        let unparsed_length = match data_start.get(..crate::LENGTH_PREFIX_SIZE) {
+            Some(data) => data,
+            None => return -8,
+        };
+        data_start = &data_start[crate::LENGTH_PREFIX_SIZE..];
+
+        let parsed_entry_length = match parse_length(unparsed_length) {
+            Some(length) => length,
+            None => return -7,
+        };
+        if parsed_entry_length > data_start.len() {
+            return -8;
+        }
+        *entry_lengths.add(found) = parsed_entry_length;
+        *entries.add(found) = data_start.as_ptr();
+
+        data_start = &data_start[parsed_entry_length..];
+        found += 1;
     }
-    *entry2_length = parsed_entry2_length;
-    *entry2 = data_start.as_ptr();
+    *entry_count = found;
+    log_trace!("destructured {} entries", found);
 
     0
 }
+
+/// Given a pointer of a buffer which contains any number of data fields returns how many there are, without
+/// copying anything, so that the arrays for `destructure_n_entries` can be allocated with exactly that
+/// capacity. An empty buffer contains 0 entries, which `destructure_n_entries` rejects.
+/// # Returns
+/// The number of entries on success.
+/// -1 when data pointer was null.
+/// -2 if parsing one of the lengths is unsuccessful, e.g. when a length does not fit in a `size_t`
+/// -3 if the provided data would cause an out of bounds access
+/// # Safety
+/// `data` must point to at least `data_size` readable bytes.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn count_entries(data: *const libc::c_uchar, data_size: libc::size_t) -> i64 {
+    if data.is_null() {
+        return -1;
+    }
+
+    let data = core::slice::from_raw_parts(data, data_size);
+    let mut count = 0;
+    for entry in crate::request::EntryIter::new(data) {
+        match entry {
+            Ok(_) => count += 1,
+            Err(crate::MessageError::LengthOverflow) => return -2,
+            Err(_) => return -3,
+        }
+    }
+    log_trace!("counted {} entries", count);
+
+    count
+}
@@ -0,0 +1,57 @@
+use subtle::ConstantTimeEq;
+#[cfg(feature = "std")]
+use zeroize::Zeroize;
+
+/// Securely clears `len` bytes starting at `buffer`. The clearing is guaranteed to not be optimized
+/// away by the compiler, which is not the case for a plain `memset` on memory that is about to be freed.
+/// Use this on buffers that held cryptographically sensitive data (e.g. a private key or a received
+/// response) before freeing them.
+/// # Returns
+/// 0 on success.
+/// -1 when buffer was a null pointer.
+/// # Safety
+/// `buffer` must point to at least `len` writable bytes.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn zeroize_buffer(buffer: *mut libc::c_uchar, len: libc::size_t) -> i16 {
+    if buffer.is_null() {
+        return -1;
+    }
+
+    core::slice::from_raw_parts_mut(buffer, len).zeroize();
+
+    0
+}
+
+/// Compares `a` and `b` in constant time, so that the time taken does not reveal where they first differ.
+/// Use this instead of `==` for secret-dependent data, e.g. a shared secret taken from a response.
+/// The lengths are not treated as secret: when they differ `false` is returned right away, without
+/// comparing a common prefix.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// Same as `ct_eq`, for `a_len` bytes at `a` and `b_len` bytes at `b`.
+/// # Returns
+/// 1 when the buffers are equal.
+/// 0 when they are not.
+/// -1 when a or b was a null pointer.
+/// # Safety
+/// `a` must point to at least `a_len` readable bytes and `b` to at least `b_len` readable bytes.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub unsafe extern "C" fn ct_eq_c(
+    a: *const libc::c_uchar,
+    a_len: libc::size_t,
+    b: *const libc::c_uchar,
+    b_len: libc::size_t,
+) -> i16 {
+    if a.is_null() || b.is_null() {
+        return -1;
+    }
+
+    let a = core::slice::from_raw_parts(a, a_len);
+    let b = core::slice::from_raw_parts(b, b_len);
+
+    ct_eq(a, b) as i16
+}
@@ -0,0 +1,238 @@
+use alloc::vec::Vec;
+
+use crate::request::{Algorithm, Operation, RequestHeader};
+use crate::response::{ResponseHeader, ResponseStatus};
+
+// Distinct bytes in every position, so that a swapped byte order does not go unnoticed.
+const IDENTIFIER: crate::Identifier = 0x0102_0304_0506_0708;
+const DEADLINE_MS: u64 = 0x1112_1314_1516_1718;
+
+/// Round-trips a request and a response through this build, e.g. for a responder to call at startup before
+/// serving real traffic. A request header for a Kyber decapsulation is serialized and parsed again, two
+/// entries are structured and destructured again and the response carrying them is serialized and parsed
+/// again, comparing the result of every step with what went in. With the `checksum` and `compress` features
+/// their trailer and compression are part of the round trip.
+/// # Errors
+/// `MessageError::SelfTestFailed` naming the first step that failed.
+pub fn self_test() -> Result<(), crate::MessageError> {
+    let entry1 = [0x5A; 64];
+    let entry2: Vec<u8> = (0..=255).collect();
+    let structured = crate::response::structure_two_entries(&entry1, &entry2);
+
+    let header = RequestHeader::builder()
+        .identifier(IDENTIFIER)
+        .data_len(
+            crate::encode_data_len(structured.len())
+                .map_err(|error| failed("request header", error))?,
+        )
+        .algorithm(Algorithm::KYBER_512)
+        .operation(Operation::Decapsulation)
+        .deadline_ms(DEADLINE_MS)
+        .build()
+        .map_err(|error| failed("request header", error))?;
+    let parsed = crate::request::deserialize_request_header(&header.to_bytes())
+        .map_err(|error| failed("request header", error))?;
+    check(parsed == header, "request header")?;
+
+    let (parsed1, parsed2) = crate::request::destructure_two_entries(&structured)
+        .map_err(|error| failed("entries", error))?;
+    check(parsed1 == &entry1[..] && parsed2 == &entry2[..], "entries")?;
+
+    let response = crate::response::serialize_response(header.identifier, &structured)
+        .map_err(|error| failed("response", error))?;
+    let (response_header, data) =
+        crate::response::parse_response(&response).map_err(|error| failed("response", error))?;
+    #[cfg(feature = "compress")]
+    let data = &crate::response::decompress_response_body(&response_header, data)
+        .map_err(|error| failed("response", error))?[..];
+    check(
+        response_header.identifier == IDENTIFIER
+            && response_header.status() == Some(ResponseStatus::Ok)
+            && data == &structured[..],
+        "response",
+    )
+}
+
+/// Same as `self_test`, for C.
+/// # Returns
+/// 0 on success.
+/// -1 when the self-test failed. With the `logging` feature the failed step is logged.
+#[cfg(feature = "std")]
+#[no_mangle]
+pub extern "C" fn pq_message_self_test() -> i16 {
+    match self_test() {
+        Ok(()) => 0,
+        Err(error) => {
+            log_debug!("{}", error);
+            -1
+        }
+    }
+}
+
+/// Returns the canonical test vectors as serialized by this build, each with its name: a request header for
+/// every operation, response headers and structured entries, named `request_`, `response_` and `entries_`
+/// after what they hold. See `verify_test_vectors`, and `test_vectors.hex` in the repository for their bytes,
+/// which other implementations can check against as well. The file holds the layout of 8-byte identifiers,
+/// with the `uuid` feature every header is different.
+pub fn test_vectors() -> Vec<(&'static str, Vec<u8>)> {
+    let request = |algorithm, operation, data_len| RequestHeader {
+        identifier: IDENTIFIER,
+        data_len,
+        algorithm,
+        operation,
+        deadline_ms: DEADLINE_MS,
+        ..Default::default()
+    };
+    let mut diagnostic = ResponseHeader::new(IDENTIFIER, ResponseStatus::UnsupportedAlgorithm, 5);
+    diagnostic.flags |= crate::response::RESPONSE_FLAG_DIAGNOSTIC;
+
+    alloc::vec![
+        (
+            "request_keypair_generation",
+            request(Algorithm::KYBER_768, Operation::KeypairGeneration, 0).to_bytes(),
+        ),
+        (
+            "request_encapsulation",
+            request(Algorithm::ML_KEM_768, Operation::Encapsulation, 1184).to_bytes(),
+        ),
+        (
+            "request_decapsulation",
+            // The secret key and the ciphertext with their length prefixes, the largest request data
+            request(
+                Algorithm::FRODO1344__ECDHp521,
+                Operation::Decapsulation,
+                64935
+            )
+            .to_bytes(),
+        ),
+        (
+            "request_sign",
+            request(Algorithm::ML_DSA_65, Operation::Sign, 32).to_bytes(),
+        ),
+        (
+            "request_verify",
+            // A 32-byte message and its signature with their length prefixes
+            request(Algorithm::ML_DSA_44, Operation::Verify, 2468).to_bytes(),
+        ),
+        ("request_hello", RequestHeader::hello(IDENTIFIER).to_bytes()),
+        (
+            "request_cancel",
+            RequestHeader::cancel(IDENTIFIER).to_bytes()
+        ),
+        (
+            "response_ok",
+            ResponseHeader::new(IDENTIFIER, ResponseStatus::Ok, 32).to_bytes(),
+        ),
+        (
+            "response_failure",
+            ResponseHeader::new(IDENTIFIER, ResponseStatus::InvalidInput, 0).to_bytes(),
+        ),
+        ("response_diagnostic", diagnostic.to_bytes()),
+        (
+            "entries_two",
+            crate::response::structure_two_entries(&[0x5A; 3], &[0xA5, 0x01]),
+        ),
+        (
+            "entries_three",
+            crate::response::structure_three_entries(&[1], &[], &[2, 3, 4, 5]),
+        ),
+    ]
+}
+
+/// Checks `test_vectors` against the vectors recorded in the file at `path`, e.g. `test_vectors.hex` of the
+/// repository. Every line of the file holds the name of a vector and its bytes in hex, separated by a space;
+/// empty lines and lines starting with `#` are skipped. Every vector must be recorded with the same bytes,
+/// and parsing the recorded bytes and serializing them again must give those bytes back. Any change to the
+/// wire format fails this, so the file has to be recorded again along with a new `FORMAT_VERSION`.
+/// # Errors
+/// `MessageError::Io` when the file cannot be read and `MessageError::SelfTestFailed` naming the first vector
+/// that is missing or differs, or naming "test vectors" when the file is malformed or holds unknown vectors.
+#[cfg(feature = "std")]
+pub fn verify_test_vectors(path: &std::path::Path) -> Result<(), crate::MessageError> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut recorded = alloc::collections::BTreeMap::new();
+    for line in contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    {
+        let (name, hex) = line.split_once(' ').ok_or_else(|| malformed(line))?;
+        let bytes = decode_hex(hex.trim()).ok_or_else(|| malformed(line))?;
+        recorded.insert(name, bytes);
+    }
+
+    let vectors = test_vectors();
+    if let Some(unknown) = recorded
+        .keys()
+        .find(|name| vectors.iter().all(|(vector, _)| vector != *name))
+    {
+        return Err(malformed(unknown));
+    }
+    for (name, bytes) in vectors {
+        let expected = recorded.get(name).ok_or_else(|| {
+            log_debug!("test vector {} is missing", name);
+            crate::MessageError::SelfTestFailed { step: name }
+        })?;
+        check(
+            bytes == *expected && reserialize(name, expected).as_ref() == Some(expected),
+            name,
+        )?;
+    }
+
+    Ok(())
+}
+
+// Parses the bytes of the test vector `name` according to what it holds and serializes them again.
+#[cfg(feature = "std")]
+fn reserialize(name: &str, bytes: &[u8]) -> Option<Vec<u8>> {
+    if name.starts_with("request_") {
+        RequestHeader::from_bytes(bytes)
+            .ok()
+            .map(|header| header.to_bytes())
+    } else if name.starts_with("response_") {
+        ResponseHeader::from_bytes(bytes)
+            .ok()
+            .map(|header| header.to_bytes())
+    } else {
+        let entries = crate::request::destructure_entries(bytes).ok()?;
+        crate::response::structure_entries(&entries).ok()
+    }
+}
+
+// Every byte is two hex digits, so an odd number of digits leaves a chunk of one and fails.
+#[cfg(feature = "std")]
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => {
+                let high = char::from(*high).to_digit(16)?;
+                let low = char::from(*low).to_digit(16)?;
+                Some((high << 4 | low) as u8)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(feature = "std")]
+fn malformed(line: &str) -> crate::MessageError {
+    log_debug!("test vectors: unexpected line {}", line);
+    crate::MessageError::SelfTestFailed {
+        step: "test vectors",
+    }
+}
+
+fn check(passed: bool, step: &'static str) -> Result<(), crate::MessageError> {
+    if passed {
+        Ok(())
+    } else {
+        log_debug!("self-test step {} produced different values", step);
+        Err(crate::MessageError::SelfTestFailed { step })
+    }
+}
+
+fn failed(step: &'static str, error: crate::MessageError) -> crate::MessageError {
+    log_debug!("self-test step {} failed: {}", step, error);
+    crate::MessageError::SelfTestFailed { step }
+}